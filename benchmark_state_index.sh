@@ -0,0 +1,68 @@
+#!/bin/bash
+#
+# Reproduces the timings in the message of commit c008c8c, which indexes the
+# states of the explicit model by vector in a hash map instead of scanning them.
+# The commit before it and the commit itself are built in release mode in
+# temporary git worktrees. The same benchmark is run with each build:
+#
+#   stamina-toolset benchmark -m models/ModifiedYeastPolarization/ModifiedYeastPolarization.crn \
+#       -a RL --num-traces 2000
+#
+# Each run writes output/<run>/benchmark_results.csv in its worktree, with the
+# Ragtimer, C&C and total times (ms), memory (bytes) and state counts. The rows
+# are printed at the end, next to the wall-clock time of each run. The RL traces
+# are random, so state counts and times vary a little between runs.
+#
+# Usage: ./benchmark_state_index.sh [NUM_TRACES] [MODEL]
+#   NUM_TRACES  number of RL traces (default 2000)
+#   MODEL       model name under models/ (default ModifiedYeastPolarization)
+
+set -euo pipefail
+
+NUM_TRACES="${1:-2000}"
+MODEL="${2:-ModifiedYeastPolarization}"
+AFTER="c008c8c"
+BEFORE="${AFTER}^"
+
+REPO_ROOT="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+WORK_DIR="$(mktemp -d)"
+RUN_NAME="state_index_$(date +%Y%m%d-%H%M%S)"
+
+cleanup() {
+  for NAME in before after; do
+    git -C "$REPO_ROOT" worktree remove --force "$WORK_DIR/$NAME" 2>/dev/null || true
+  done
+  rm -rf "$WORK_DIR"
+}
+trap cleanup EXIT
+
+RESULTS=()
+for NAME in before after; do
+  if [ "$NAME" = "before" ]; then COMMIT="$BEFORE"; else COMMIT="$AFTER"; fi
+  TREE="$WORK_DIR/$NAME"
+  echo "Building $NAME ($COMMIT) in $TREE"
+  git -C "$REPO_ROOT" worktree add --detach "$TREE" "$COMMIT" >/dev/null
+  # The builds share a target directory, so dependencies are only built once
+  (cd "$TREE" && CARGO_TARGET_DIR="$WORK_DIR/target" cargo build -r)
+  cp "$WORK_DIR/target/release/stamina-toolset" "$TREE/stamina-toolset"
+
+  echo "Running benchmark for $NAME: model $MODEL, $NUM_TRACES RL traces"
+  START=$(date +%s%N)
+  (cd "$TREE" && ./stamina-toolset benchmark \
+    --model "models/$MODEL/$MODEL.crn" \
+    --approach RL \
+    --num-traces "$NUM_TRACES" \
+    --output "$RUN_NAME") > "$WORK_DIR/$NAME.log" 2>&1
+  END=$(date +%s%N)
+  WALL_MS=$(( (END - START) / 1000000 ))
+
+  CSV="$TREE/output/$RUN_NAME/benchmark_results.csv"
+  HEADER=$(head -n 1 "$CSV")
+  RESULTS+=("$NAME ($COMMIT), wall clock ${WALL_MS} ms: $(tail -n 1 "$CSV")")
+done
+
+echo
+echo "$HEADER"
+for RESULT in "${RESULTS[@]}"; do
+  echo "$RESULT"
+done
@@ -6,6 +6,23 @@ pub fn parse_args() -> clap::ArgMatches {
 		.version("0.0.1")
 		.author("Formal Verification Research at Utah State University")
 		.about("More details coming soon")
+		// Model parameters, available on every subcommand
+		.arg(
+			Arg::new("param")
+				.long("param")
+				.value_name("NAME=VALUE")
+				.help("Overrides a parameter declared in the model, e.g., --param k1=0.1 (may be repeated)")
+				.action(clap::ArgAction::Append)
+				.global(true),
+		)
+		// Property file, available on every subcommand
+		.arg(
+			Arg::new("prop")
+				.long("prop")
+				.value_name("PROP")
+				.help("Reads the target from a CSL property file, e.g., model.prop, in place of the model's own")
+				.global(true),
+		)
 		// Benchmark commands
         .subcommand(
             Command::new("benchmark")
@@ -154,6 +171,55 @@ pub fn parse_args() -> clap::ArgMatches {
                 .default_value(DEFAULT_TIMEOUT_SECONDS),
             )
         )
+        // Conversion commands
+        .subcommand(
+            Command::new("convert")
+            .about("Converts a model to a PRISM CTMC or DTMC (.sm) with its target in a companion .prop file")
+            .arg(
+                Arg::new("model")
+                    .short('m')
+                    .long("model")
+                    .value_name("MODEL")
+                    .help("Sets the input model file (required)")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("output")
+                    .short('o')
+                    .long("output")
+                    .value_name("OUTPUT")
+                    .help(format!("Sets the output file name without extensions (default {})", DEFAULT_OUTPUT_NAME))
+                    .default_value(DEFAULT_OUTPUT_NAME),
+            )
+            .arg(
+                Arg::new("cap")
+                    .long("cap")
+                    .value_name("CAP")
+                    .help(format!("Sets the upper bound of every variable when --bounds is not used, or that the bounder cannot bound (default {})", DEFAULT_PRISM_CAP))
+                    .default_value(DEFAULT_PRISM_CAP),
+            )
+			.arg(
+				Arg::new("bounds")
+					.long("bounds")
+					.help("Run the variable bounding tool and use its tight bounds as variable ranges")
+					.action(clap::ArgAction::SetTrue),
+			)
+            .arg(
+                Arg::new("bits")
+                    .short('b')
+                    .long("bits")
+                    .value_name("BITS")
+					.help(format!("Sets the number of bits to use for bounding (default {})", DEFAULT_BOUNDER_BITS))
+                    .default_value(DEFAULT_BOUNDER_BITS),
+            )
+            .arg(
+                Arg::new("max-steps")
+                    .long("max-steps")
+                    .value_name("MAX_STEPS")
+                    .help(format!("Sets the limit on the number of bounding steps (default {})", DEFAULT_BOUNDER_STEPS))
+                    .default_value(DEFAULT_BOUNDER_STEPS),
+            )
+        )
         // Cycle & Commute commands
         .subcommand(
             Command::new("cycle-commute")
@@ -170,8 +236,27 @@ pub fn parse_args() -> clap::ArgMatches {
                 Arg::new("trace")
                 .long("trace")
                 .value_name("TRACE")
-                .help("Provide a tab-separated list of transitions (required)")
-                .required(true),
+                .help("Provide a tab-separated list of transitions, or a file with one such trace per line (required unless --import is given)")
+                .required_unless_present("import"),
+            )
+            .arg(
+                Arg::new("import")
+                .long("import")
+                .value_name("IMPORT")
+                .help("Expand the explicit state space in IMPORT.tra,sta,lab, e.g., from a previous run, with cycles")
+                .conflicts_with("trace"),
+            )
+            .arg(
+                Arg::new("check")
+                .long("check")
+                .help("Checks the explicit model and reports the probability range of the target")
+                .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("target-sink")
+                .long("target-sink")
+                .help("Collapses the target states of the explicit model into a single sink")
+                .action(clap::ArgAction::SetTrue),
             )
             .arg(
                 Arg::new("cycle-length")
@@ -232,6 +317,51 @@ pub fn parse_args() -> clap::ArgMatches {
                 .default_value(DEFAULT_TIMEOUT_SECONDS),
             ),
         )
+        // Formatting commands
+        .subcommand(
+            Command::new("fmt")
+            .about("Rewrites a model file (.crn) in canonical form")
+            .arg(
+                Arg::new("model")
+                    .value_name("MODEL")
+                    .help("Sets the model file to format (required)")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("check")
+                    .long("check")
+                    .help("Leave the file unchanged, and fail if it is not formatted")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("keywords")
+                    .long("keywords")
+                    .value_name("KEYWORDS")
+                    .help("Sets the keywords to write: crn (species, reaction, ...) or vas (variable, transition, ...). By default the file's own are kept")
+                    .value_parser(["crn", "vas"]),
+            )
+        )
+        // Lint commands
+        .subcommand(
+            Command::new("lint")
+            .about("Parses a model and reports every problem found, with line and column")
+            .arg(
+                Arg::new("model")
+                    .short('m')
+                    .long("model")
+                    .value_name("MODEL")
+                    .help("Sets the input model file (required)")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .help(format!("Sets the output format: text or json (default {})", DEFAULT_LINT_FORMAT))
+                    .value_parser(["text", "json"])
+                    .default_value(DEFAULT_LINT_FORMAT),
+            )
+        )
         .subcommand(
             Command::new("ragtimer")
                 .about("Build explicit state space from input model and generate traces using Ragtimer approaches")
@@ -280,6 +410,18 @@ pub fn parse_args() -> clap::ArgMatches {
 						.help(&format!("Sets the output file name without extensions (default {})", DEFAULT_OUTPUT_NAME))
 						.default_value(DEFAULT_OUTPUT_NAME),
 				)
+				.arg(
+					Arg::new("check")
+						.long("check")
+						.help("Checks the explicit model and reports the probability range of the target")
+						.action(clap::ArgAction::SetTrue),
+				)
+				.arg(
+					Arg::new("target-sink")
+						.long("target-sink")
+						.help("Collapses the target states of the explicit model into a single sink")
+						.action(clap::ArgAction::SetTrue),
+				)
                 .arg(
                     Arg::new("timeout")
                         .short('t')
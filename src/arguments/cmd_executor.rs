@@ -6,12 +6,51 @@ use crate::{
 		ragtimer::{ragtimer, RagtimerApproach},
 		rl_traces::default_magic_numbers,
 	},
-	dependency::graph::make_dependency_graph,
+	cycle_commute::commute::{cycle_commute_from_explicit, cycle_commute_from_traces},
+	dependency::{graph::make_dependency_graph, trimmer::trim_model},
+	exporter::{
+		crn_exporter::{format_model_file, write_crn_model, KeywordSet},
+		prism_exporter::convert_model,
+	},
 	logging::messages::*,
-	model::vas_model::AbstractVas,
+	model::vas_model::{AbstractVas, VasValue},
+	parser::{
+		parameters::ParameterOverrides,
+		parser::{lint_model, ModelOptions},
+	},
 };
 
 pub fn run_commands(args: &clap::ArgMatches) {
+	// Parameter overrides apply to every model read by any subcommand
+	let parameters: Vec<String> = args
+		.subcommand()
+		.and_then(|(_, sub_m)| sub_m.get_many::<String>("param"))
+		.map(|values| values.cloned().collect())
+		.unwrap_or_default();
+	let parameters = match ParameterOverrides::from_assignments(&parameters) {
+		Ok(overrides) => {
+			for parameter in parameters.iter() {
+				message!("Parameter override: {}", parameter);
+			}
+			overrides
+		}
+		Err(e) => {
+			error!("{}", e);
+			return;
+		}
+	};
+	// A property file replaces the target of every model read by any subcommand
+	let property_file = args
+		.subcommand()
+		.and_then(|(_, sub_m)| sub_m.get_one::<String>("prop"))
+		.cloned();
+	if let Some(property_file) = &property_file {
+		message!("Property file: {}", property_file);
+	}
+	let options = ModelOptions {
+		parameters,
+		property_file,
+	};
 	match args.subcommand() {
 		// Benchmark set
 		Some(("benchmark", sub_m)) => {
@@ -44,6 +83,7 @@ pub fn run_commands(args: &clap::ArgMatches) {
 					magic_numbers.num_traces = num_traces;
 					ragtimer_benchmark(
 						model,
+						&options,
 						cycle_length,
 						commute_depth,
 						RagtimerApproach::ReinforcementLearning(magic_numbers),
@@ -58,6 +98,7 @@ pub fn run_commands(args: &clap::ArgMatches) {
 					message!("Ragtimer with Random Dependency Graph path approach");
 					ragtimer_benchmark(
 						model,
+						&options,
 						cycle_length,
 						commute_depth,
 						RagtimerApproach::RandomDependencyGraph(num_traces),
@@ -100,7 +141,7 @@ pub fn run_commands(args: &clap::ArgMatches) {
 				output,
 				timeout
 			);
-			unroll_model(model_file, steps, bits, &output, check);
+			unroll_model(model_file, &options, steps, bits, &output, check);
 		}
 		Some(("bounds", sub_m)) => {
 			let model_file = sub_m.get_one::<String>("model").unwrap();
@@ -124,31 +165,86 @@ pub fn run_commands(args: &clap::ArgMatches) {
 				max_steps,
 				timeout
 			);
-			bound_model(model_file, bits, max_steps, trim);
+			bound_model(model_file, &options, bits, max_steps, trim);
+		}
+		Some(("convert", sub_m)) => {
+			let model_file = sub_m.get_one::<String>("model").unwrap();
+			let output = sub_m.get_one::<String>("output").unwrap();
+			let cap = sub_m
+				.get_one::<String>("cap")
+				.and_then(|s| s.parse::<VasValue>().ok())
+				.unwrap_or(DEFAULT_PRISM_CAP.parse::<VasValue>().unwrap());
+			let bits = sub_m
+				.get_one::<String>("bits")
+				.and_then(|s| s.parse::<u32>().ok())
+				.unwrap_or(DEFAULT_BOUNDER_BITS.parse::<u32>().unwrap());
+			let max_steps = sub_m
+				.get_one::<String>("max-steps")
+				.and_then(|s| s.parse::<u32>().ok())
+				.unwrap_or(DEFAULT_BOUNDER_STEPS.parse::<u32>().unwrap());
+			let bounds = if sub_m.get_flag("bounds") {
+				Some((bits, max_steps))
+			} else {
+				None
+			};
+			message!(
+				"Converting model: {} to PRISM, Output: {}.sm, {}",
+				model_file,
+				output,
+				if bounds.is_some() {
+					"Ranges: from bounder".to_string()
+				} else {
+					format!("Cap: {}", cap)
+				}
+			);
+			convert_model(model_file, &options, output, cap, bounds);
 		}
 		Some(("cycle-commute", sub_m)) => {
 			let model = sub_m.get_one::<String>("model").unwrap();
-			let trace = sub_m.get_one::<String>("trace").unwrap();
 			let cycle_length = sub_m
-				.get_one::<String>("max-cycle-length")
+				.get_one::<String>("cycle-length")
 				.and_then(|s| s.parse::<usize>().ok())
-				.unwrap();
+				.unwrap_or(DEFAULT_CYCLE_LENGTH.parse::<usize>().unwrap());
 			let commute_depth = sub_m
-				.get_one::<String>("max-commute-depth")
+				.get_one::<String>("commute-depth")
 				.and_then(|s| s.parse::<usize>().ok())
-				.unwrap();
+				.unwrap_or(DEFAULT_COMMUTE_DEPTH.parse::<usize>().unwrap());
 			let output = sub_m.get_one::<String>("output").unwrap();
 			let timeout = sub_m
 				.get_one::<String>("timeout")
 				.and_then(|s| s.parse::<usize>().ok())
 				.unwrap_or(DEFAULT_TIMEOUT_SECONDS.parse::<usize>().unwrap());
+			if let Some(explicit_files) = sub_m.get_one::<String>("import") {
+				message!(
+					"Running Cycle & Commute on model: {}, Explicit model: {}, Max Cycle Length: {}, Output: {}, Timeout: {}s",
+					model, explicit_files, cycle_length, output, timeout
+				);
+				cycle_commute_from_explicit(
+					model,
+					&options,
+					explicit_files,
+					cycle_length,
+					output,
+					sub_m.get_flag("check"),
+					sub_m.get_flag("target-sink"),
+				);
+				return;
+			}
+			let trace = sub_m.get_one::<String>("trace").unwrap();
 			message!(
 				"Running Cycle & Commute on model: {}, Trace: {}, Max Cycle Length: {}, Max Commute Depth: {}, Output: {}, Timeout: {}s",
 				model, trace, cycle_length, commute_depth, output, timeout
 			);
-			error!("Cycle & Commute is not yet implemented for reading traces from files.");
-			unimplemented!();
-			// Run cycle-commute here
+			cycle_commute_from_traces(
+				model,
+				&options,
+				trace,
+				commute_depth,
+				cycle_length,
+				output,
+				sub_m.get_flag("check"),
+				sub_m.get_flag("target-sink"),
+			);
 		}
 		Some(("dependency-graph", sub_m)) => {
 			let model_file = sub_m.get_one::<String>("model").unwrap();
@@ -164,7 +260,7 @@ pub fn run_commands(args: &clap::ArgMatches) {
 				timeout
 			);
 			// TODO: Put this into its own function in the dependency module
-			if let Ok(model) = AbstractVas::from_file(model_file) {
+			if let Ok(model) = AbstractVas::from_file(model_file, &options) {
 				message!("Successfully parsed model file: {}", model_file);
 				// Generate and display the dependency graph
 				let dependency_graph = match make_dependency_graph(&model) {
@@ -198,10 +294,48 @@ pub fn run_commands(args: &clap::ArgMatches) {
 				} else {
 					message!("Dependency graph written to file: {}", output_file);
 				}
+				// Write the model trimmed to the dependency graph
+				let trimmed_model = trim_model(&model, dependency_graph);
+				let trimmed_file = format!("{}.trimmed.crn", output);
+				if let Err(e) = write_crn_model(&trimmed_model, &trimmed_file, KeywordSet::Crn) {
+					error!("Error writing trimmed model: {}", e);
+				}
 			} else {
 				error!("Error parsing model file: {}", model_file);
 			}
 		}
+		Some(("fmt", sub_m)) => {
+			let model_file = sub_m.get_one::<String>("model").unwrap();
+			let check = sub_m.get_flag("check");
+			let keywords = sub_m
+				.get_one::<String>("keywords")
+				.and_then(|s| s.parse::<KeywordSet>().ok());
+			match format_model_file(model_file, keywords, check) {
+				Ok(true) => {
+					message!("{} is formatted", model_file);
+				}
+				Ok(false) if check => {
+					error!("{} is not formatted", model_file);
+					std::process::exit(1);
+				}
+				Ok(false) => {
+					message!("Formatted {}", model_file);
+				}
+				Err(e) => {
+					error!("{}", e);
+					std::process::exit(1);
+				}
+			}
+		}
+		Some(("lint", sub_m)) => {
+			let model_file = sub_m.get_one::<String>("model").unwrap();
+			let json = sub_m
+				.get_one::<String>("format")
+				.is_some_and(|f| f == "json");
+			if !lint_model(model_file, json, &options) {
+				std::process::exit(1);
+			}
+		}
 		Some(("ragtimer", sub_m)) => {
 			let model = sub_m.get_one::<String>("model").unwrap();
 			let approach: &String = sub_m.get_one::<String>("approach").unwrap();
@@ -222,6 +356,8 @@ pub fn run_commands(args: &clap::ArgMatches) {
 				.get_one::<String>("timeout")
 				.and_then(|s| s.parse::<usize>().ok())
 				.unwrap_or(DEFAULT_TIMEOUT_SECONDS.parse::<usize>().unwrap());
+			let check = sub_m.get_flag("check");
+			let target_sink = sub_m.get_flag("target-sink");
 			message!(
 				"Running Ragtimer on model: {}, Approach: {}, Traces: {}, Cycle Length: {}, Commute Depth: {}, Timeout: {}s",
 				model, approach, num_traces, cycle_length, commute_depth, timeout
@@ -234,10 +370,13 @@ pub fn run_commands(args: &clap::ArgMatches) {
 					magic_numbers.num_traces = num_traces;
 					ragtimer(
 						model,
+						&options,
 						RagtimerApproach::ReinforcementLearning(magic_numbers),
 						cycle_length,
 						commute_depth,
 						output,
+						check,
+						target_sink,
 					);
 				}
 				"random" => {
@@ -248,10 +387,13 @@ pub fn run_commands(args: &clap::ArgMatches) {
 					message!("Ragtimer with Shortest path approach");
 					ragtimer(
 						model,
+						&options,
 						RagtimerApproach::RandomDependencyGraph(num_traces),
 						cycle_length,
 						commute_depth,
 						output,
+						check,
+						target_sink,
 					);
 				}
 				_ => {
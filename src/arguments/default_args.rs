@@ -8,3 +8,5 @@ pub const DEFAULT_BMC_BITS: &str = "16";
 pub const DEFAULT_BMC_MAX_STEPS: &str = "100";
 pub const DEFAULT_BOUNDER_BITS: &str = "16";
 pub const DEFAULT_BENCHMARK_OUTPUT: &str = "output";
+pub const DEFAULT_PRISM_CAP: &str = "1000";
+pub const DEFAULT_LINT_FORMAT: &str = "text";
@@ -1,6 +1,7 @@
 use crate::builder::builder::Builder;
 use crate::builder::ragtimer::ragtimer::{RagtimerApproach, RagtimerBuilder};
 use crate::model::vas_model::AbstractVas;
+use crate::parser::parser::ModelOptions;
 use crate::*;
 use crate::{cycle_commute::commute::cycle_commute, model::vas_model::PrismVasModel};
 use chrono::Local;
@@ -42,6 +43,7 @@ const OUTPUT_WORD: &str = "model";
 /// plus a bash script to run PRISM on each generated model.
 pub fn ragtimer_benchmark(
 	model_file: &str,
+	options: &ModelOptions,
 	commute_depth: usize,
 	cycle_length: usize,
 	approach: RagtimerApproach,
@@ -116,7 +118,7 @@ pub fn ragtimer_benchmark(
 		.replace(".", "_");
 	let prop_src = Path::new(&model_file).with_extension("prop");
 
-	if let Ok(mut abstract_model) = AbstractVas::from_file(model_file) {
+	if let Ok(mut abstract_model) = AbstractVas::from_file(model_file, options) {
 		let approach_word = match approach {
 			RagtimerApproach::ReinforcementLearning(_) => "rl",
 			RagtimerApproach::RandomPathExploration => "rpe",
@@ -172,6 +174,7 @@ pub fn ragtimer_benchmark(
 			.map(|p| p.memory())
 			.unwrap_or(0);
 		ragtimer_builder = RagtimerBuilder::new(&abstract_model, Some(approach));
+		ragtimer_builder.set_model_query(model_file, options);
 		ragtimer_builder.build(&mut explicit_model);
 
 		ragtimer_state_count = explicit_model.states.len();
@@ -258,8 +261,8 @@ pub fn ragtimer_benchmark(
 		}
 
 		bash_command = format!(
-												"/usr/bin/time -v -o {}prism_time.txt prism -importmodel {}{}.tra,sta,lab {}{}.prop -ctmc > {}prism_output.txt",
-												bash_dir, bash_dir, OUTPUT_WORD, bash_dir, OUTPUT_WORD, bash_dir
+												"/usr/bin/time -v -o {}prism_time.txt prism -importmodel {}{}.tra,sta,lab {}{}.prop -{} > {}prism_output.txt",
+												bash_dir, bash_dir, OUTPUT_WORD, bash_dir, OUTPUT_WORD, abstract_model.m_type, bash_dir
 											);
 		writeln!(bash_file, "{}", bash_command)
 			.expect("Failed to write bash command to script file");
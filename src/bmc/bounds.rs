@@ -8,6 +8,7 @@ use crate::bmc::vas_bmc::AbstractVasBmc;
 use crate::dependency::trimmer::trim_model;
 use crate::model::vas_model::AbstractVas;
 use crate::model::vas_model::VasValue;
+use crate::parser::parser::ModelOptions;
 use crate::*;
 
 /// Struct to hold the BMC encoding components
@@ -139,7 +140,8 @@ impl BMCBounds {
 			let state_var = &state_vars[s];
 			let state_var_index = model.variable_names.iter().position(|x| x == s).unwrap();
 			debug_message!("Checking tight upper bound for {}", s);
-			let mut min_bound: VasValue = model.initial_states.clone()[0].vector[state_var_index];
+			// No run can stay below the smallest initial value
+			let mut min_bound: VasValue = model.initial_value_range(state_var_index).0;
 			let mut max_bound: VasValue = (1 << bits) - 1;
 			let mut bound: VasValue = (1 << bits) - 1;
 			// This loop does a binary search for the tightest upper bound
@@ -200,7 +202,8 @@ impl BMCBounds {
 				.position(|x| x == variable_name)
 				.unwrap();
 			debug_message!("Checking loose upper bound for {}", variable_name);
-			let mut min_bound: VasValue = model.initial_states.clone()[0].vector[state_var_index];
+			// Some run reaches the largest initial value
+			let mut min_bound: VasValue = model.initial_value_range(state_var_index).1;
 			let mut max_bound: VasValue = (1 << bits) - 1;
 			let mut bound: VasValue = 0;
 			// This loop does a binary search for the loosest upper bound
@@ -259,7 +262,8 @@ impl BMCBounds {
 			let state_var_index = model.variable_names.iter().position(|x| x == s).unwrap();
 			debug_message!("Checking tight lower bound for {}", s);
 			let mut min_bound: VasValue = 0;
-			let mut max_bound: VasValue = model.initial_states[0].vector[state_var_index];
+			// No run can stay above the largest initial value
+			let mut max_bound: VasValue = model.initial_value_range(state_var_index).1;
 			let mut bound: VasValue = 0;
 			// This loop does a binary search for the tightest lower bound
 			loop {
@@ -320,8 +324,9 @@ impl BMCBounds {
 			let state_var_index = model.variable_names.iter().position(|x| x == s).unwrap();
 			debug_message!("Checking loose lower bound for {}", s);
 			let mut min_bound: VasValue = 0;
-			let mut max_bound: VasValue = model.initial_states[0].vector[state_var_index];
-			let mut bound: VasValue = model.initial_states[0].vector[state_var_index];
+			// Some run reaches the smallest initial value
+			let mut max_bound: VasValue = model.initial_value_range(state_var_index).0;
+			let mut bound: VasValue = max_bound;
 
 			loop {
 				if max_bound == 0 {
@@ -366,13 +371,21 @@ impl BMCBounds {
 	}
 }
 
-pub fn bound_model(model_file: &str, bits: u32, max_steps: u32, trim: bool) {
+pub fn bound_model(
+	model_file: &str,
+	options: &ModelOptions,
+	bits: u32,
+	max_steps: u32,
+	trim: bool,
+) {
 	// Run the bounds checking
-	if let Ok(model) = AbstractVas::from_file(model_file) {
+	if let Ok(model) = AbstractVas::from_file(model_file, options) {
 		message!("Successfully parsed model file: {}", model_file);
-		if trim {
-			let dependency_graph = match crate::dependency::graph::make_dependency_graph(&model) {
-				Ok(Some(dg)) => dg,
+		// Targets the dependency graph cannot handle (e.g., linear combinations)
+		// fall back to bounding the original model.
+		let dependency_graph = if trim {
+			match crate::dependency::graph::make_dependency_graph(&model) {
+				Ok(Some(dg)) => Some(dg),
 				Ok(None) => {
 					error!(
 						"Failed to create dependency graph for model: {}",
@@ -381,13 +394,18 @@ pub fn bound_model(model_file: &str, bits: u32, max_steps: u32, trim: bool) {
 					return;
 				}
 				Err(e) => {
-					error!(
-						"Error creating dependency graph for model: {}: {}",
-						model_file, e
+					warning!(
+						"Cannot trim model {}: {}\nFalling back to the original model.",
+						model_file,
+						e
 					);
-					return;
+					None
 				}
-			};
+			}
+		} else {
+			None
+		};
+		if let Some(dependency_graph) = dependency_graph {
 			let trimmed_model = trim_model(&model, dependency_graph);
 			message!("Using trimmed model based on dependency graph.");
 			debug_message!("Trimmed Model: {}", trimmed_model.nice_print());
@@ -8,7 +8,11 @@ use z3::{
 use crate::{
 	bmc::{unroller::Unroller, vas_bmc::AbstractVasBmc},
 	logging::messages::*,
-	model::vas_model::AbstractVas,
+	model::{
+		vas_model::{AbstractVas, AllowedRelation, VasProperty, VasValue},
+		vas_target::VasTarget,
+	},
+	parser::parser::ModelOptions,
 };
 
 /// Struct to hold the BMC encoding components
@@ -19,6 +23,92 @@ pub struct BMCEncoding {
 	pub unroller: Unroller,
 }
 
+/// Encodes `lhs <relation> rhs` over bit vectors. Species counts use unsigned
+/// comparisons, matching the unsigned guards used for the transitions, while
+/// weighted sums (which may be negative) use signed comparisons.
+fn relation_formula(
+	relation: AllowedRelation,
+	lhs: &ast::BV,
+	rhs: &ast::BV,
+	signed: bool,
+) -> ast::Bool {
+	match (relation, signed) {
+		(AllowedRelation::Equal, _) => ast::Ast::eq(lhs, rhs),
+		(AllowedRelation::NotEqual, _) => ast::Ast::eq(lhs, rhs).not(),
+		(AllowedRelation::LessThan, false) => lhs.bvult(rhs),
+		(AllowedRelation::LessThanOrEqual, false) => lhs.bvule(rhs),
+		(AllowedRelation::GreaterThan, false) => lhs.bvugt(rhs),
+		(AllowedRelation::GreaterThanOrEqual, false) => lhs.bvuge(rhs),
+		(AllowedRelation::LessThan, true) => lhs.bvslt(rhs),
+		(AllowedRelation::LessThanOrEqual, true) => lhs.bvsle(rhs),
+		(AllowedRelation::GreaterThan, true) => lhs.bvsgt(rhs),
+		(AllowedRelation::GreaterThanOrEqual, true) => lhs.bvsge(rhs),
+	}
+}
+
+/// Encodes a linear-combination property. The species bit vectors are
+/// zero-extended to a width where the weighted sum cannot overflow,
+/// and the sum is compared as a signed value.
+fn linear_property_formula(
+	property: &VasProperty,
+	model_variables: &[String],
+	bmc_variables: &HashMap<String, ast::BV>,
+	bits: u32,
+) -> ast::Bool {
+	let coefficient_sum: VasValue = property
+		.linear_terms
+		.iter()
+		.map(|(_, coefficient)| coefficient.abs())
+		.sum();
+	let coefficient_bits = VasValue::BITS - coefficient_sum.leading_zeros();
+	let width = (bits + coefficient_bits + 1).max(64);
+	let sum = property
+		.linear_terms
+		.iter()
+		.map(|(index, coefficient)| {
+			bmc_variables[&model_variables[*index]]
+				.zero_ext(width - bits)
+				.bvmul(ast::BV::from_i64((*coefficient).try_into().unwrap(), width))
+		})
+		.reduce(|lhs, rhs| lhs.bvadd(&rhs))
+		.unwrap_or_else(|| ast::BV::from_i64(0, width));
+	relation_formula(
+		property.relation,
+		&sum,
+		&ast::BV::from_i64(property.target_value.try_into().unwrap(), width),
+		true,
+	)
+}
+
+/// Encodes a (possibly compound) target over the current-state bit vectors.
+fn target_formula(
+	target: &VasTarget,
+	model_variables: &[String],
+	bmc_variables: &HashMap<String, ast::BV>,
+	bits: u32,
+) -> ast::Bool {
+	match target {
+		VasTarget::Atom(property) if property.is_linear() => {
+			linear_property_formula(property, model_variables, bmc_variables, bits)
+		}
+		VasTarget::Atom(property) => relation_formula(
+			property.relation,
+			&bmc_variables[&model_variables[property.variable_index]],
+			&ast::BV::from_i64(property.target_value.try_into().unwrap(), bits),
+			false,
+		),
+		VasTarget::Not(inner) => target_formula(inner, model_variables, bmc_variables, bits).not(),
+		VasTarget::And(lhs, rhs) => ast::Bool::and(&[
+			&target_formula(lhs, model_variables, bmc_variables, bits),
+			&target_formula(rhs, model_variables, bmc_variables, bits),
+		]),
+		VasTarget::Or(lhs, rhs) => ast::Bool::or(&[
+			&target_formula(lhs, model_variables, bmc_variables, bits),
+			&target_formula(rhs, model_variables, bmc_variables, bits),
+		]),
+	}
+}
+
 /// Builds an encoding for an abstract VAS model for BMC.
 impl BMCEncoding {
 	/// Constructs a new BMCEncoding from the given context, config, and unroller.
@@ -28,30 +118,42 @@ impl BMCEncoding {
 		let model_variables = model.variable_names.clone();
 		let mut bmc_current_variables = HashMap::new();
 		let mut bmc_next_variables = HashMap::new();
-		// Load the initial constraints
-		let model_init = model.initial_states.clone();
-		let mut bmc_init_constraints = Vec::new();
-		// Encode the Z3 bit-vector variables for initial constraints and state variables
+		// Encode the Z3 bit-vector variables for the state variables
 		for i in 0..model_variables.len() {
 			let state_var = ast::BV::new_const(model_variables[i].clone(), bits);
 			let next_var = ast::BV::new_const(format!("{}_next", model_variables[i]), bits);
 			bmc_current_variables.insert(model_variables[i].clone(), state_var.clone());
 			bmc_next_variables.insert(model_variables[i].clone(), next_var.clone());
-			bmc_init_constraints.push(Ast::eq(
-				&state_var,
-				&ast::BV::from_i64(model_init[0].vector[i].try_into().unwrap(), bits),
-			));
 		}
 		debug_message!("Encoded variables for BMC:\n{:?}", bmc_current_variables);
-		// Build the initial formula by conjoining the constraints
-		let bmc_init_formula = ast::Bool::and(&bmc_init_constraints.iter().collect::<Vec<_>>());
+		// Build the initial formula as the disjunction of the initial states,
+		// each of which conjoins the values of the variables
+		let bmc_initial_states = model
+			.initial_states
+			.iter()
+			.map(|state| {
+				let constraints = model_variables
+					.iter()
+					.enumerate()
+					.map(|(i, name)| {
+						Ast::eq(
+							&bmc_current_variables[name],
+							ast::BV::from_i64(state.vector[i].try_into().unwrap(), bits),
+						)
+					})
+					.collect::<Vec<_>>();
+				ast::Bool::and(&constraints.iter().collect::<Vec<_>>())
+			})
+			.collect::<Vec<_>>();
+		let bmc_init_formula = ast::Bool::or(&bmc_initial_states.iter().collect::<Vec<_>>());
 		debug_message!("Encoded initial state for BMC:\n{:?}", bmc_init_formula);
 
 		// Encode the target formula
-		let model_target = model.target.clone();
-		let bmc_target_formula = ast::Ast::eq(
-			&bmc_current_variables[&model_variables[model_target.variable_index]],
-			&ast::BV::from_i64(model_target.target_value.try_into().unwrap(), bits),
+		let bmc_target_formula = target_formula(
+			&model.target,
+			&model_variables,
+			&bmc_current_variables,
+			bits,
 		);
 		// Encode the transitions one-by-one
 		let mut bmc_transition_constraints = Vec::new();
@@ -198,8 +300,15 @@ impl BMCEncoding {
 }
 
 /// Unrolls the model for BMC and outputs the desired encoding
-pub fn unroll_model(model_file: &str, steps: u32, bits: u32, output: &str, check: bool) {
-	if let Ok(model) = AbstractVas::from_file(model_file) {
+pub fn unroll_model(
+	model_file: &str,
+	options: &ModelOptions,
+	steps: u32,
+	bits: u32,
+	output: &str,
+	check: bool,
+) {
+	if let Ok(model) = AbstractVas::from_file(model_file, options) {
 		message!("Successfully parsed model file: {}", model_file);
 		debug_message!("Model:\n{}", model.nice_print());
 		let bmc_encoding = model.bmc_encoding(bits);
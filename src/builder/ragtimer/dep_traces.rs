@@ -7,10 +7,7 @@ use crate::{
 	},
 	dependency::graph::{make_dependency_graph, DependencyGraph},
 	logging::messages::{debug_message, error, message},
-	model::{
-		vas_model::{PrismVasModel, PrismVasState, VasTransition},
-		vas_trie::VasTrieNode,
-	},
+	model::vas_model::{PrismVasModel, VasStateVector, VasTransition},
 	trace::trace_trie::TraceTrieNode,
 };
 
@@ -20,24 +17,20 @@ use crate::{
 impl<'a> RagtimerBuilder<'a> {
 	/// Recursively builds dependency graph traces and adds them to the explicit model.
 	/// Effectively a depth-first search through the dependency graph.
-	fn generate_dep_trace(&self, allowed_transitions: &Vec<VasTransition>) -> Vec<usize> {
+	fn generate_dep_trace(
+		&self,
+		initial_state: &VasStateVector,
+		allowed_transitions: &Vec<VasTransition>,
+	) -> Vec<usize> {
 		let mut trace = Vec::new();
 		let vas_target = &self.abstract_model.target;
 
-		// Starting in the initial state, generate a trace
-		let mut current_state = self.abstract_model.initial_states[0].vector.clone();
+		// Starting in the given initial state, generate a trace
+		let mut current_state = initial_state.clone();
 		while trace.len() < MAX_TRACE_LENGTH {
 			// Check if we have reached the target state
-			if current_state.len() > vas_target.variable_index {
-				if current_state[vas_target.variable_index] == vas_target.target_value {
-					break;
-				}
-			} else {
-				error!(
-					"Current state length {} is less than target variable index {}",
-					current_state.len(),
-					vas_target.variable_index
-				);
+			if vas_target.is_satisfied(&current_state) {
+				break;
 			}
 			// Get available transitions
 			let available_transitions = self
@@ -76,19 +69,8 @@ impl<'a> RagtimerBuilder<'a> {
 		let mut trace_trie = TraceTrieNode::new();
 
 		// Set up state space storage structures
-		explicit_model.state_trie = VasTrieNode::new();
-		let current_state_id = 1;
-		let current_state = self.abstract_model.initial_states[0].vector.clone();
-		explicit_model
-			.state_trie
-			.insert_if_not_exists(&current_state, current_state_id);
-		explicit_model.add_state(PrismVasState {
-			state_id: current_state_id,
-			vector: current_state.clone(),
-			label: Some("init".to_string()),
-			used_rate: 0.0,
-			total_outgoing_rate: self.abstract_model.crn_total_outgoing_rate(&current_state),
-		});
+		let abstract_model = self.abstract_model;
+		let initial_ids = self.add_initial_states(explicit_model);
 
 		// If the dependency graph is not provided, we try to construct it from the abstract model.
 		let owned_dep_graph;
@@ -121,12 +103,19 @@ impl<'a> RagtimerBuilder<'a> {
 		println!("\nTRACE GENERATION PROGRESS:");
 		for i in 0..num_traces {
 			let mut trace;
+			let mut initial_index;
 			let mut trace_attempts = 0;
 			loop {
-				// Generate a single trace
-				trace = self.generate_dep_trace(&allowed_transitions);
+				// Generate a single trace from a sampled initial state
+				initial_index = abstract_model.sample_initial_index();
+				trace = self.generate_dep_trace(
+					&abstract_model.initial_states[initial_index].vector,
+					&allowed_transitions,
+				);
 				// If the trace already exists or is empty, we try to generate a new one.
-				if !trace_trie.exists_or_insert(&trace) && !trace.is_empty() {
+				// Traces are stored after the ID of the state they start in.
+				let stored_trace = [vec![initial_ids[initial_index]], trace.clone()].concat();
+				if !trace_trie.exists_or_insert(&stored_trace) && !trace.is_empty() {
 					break;
 				}
 				trace_attempts += 1;
@@ -135,7 +124,11 @@ impl<'a> RagtimerBuilder<'a> {
 				}
 			}
 			// Store explicit prism states and transitions for this trace
-			self.store_explicit_trace(explicit_model, &trace);
+			self.store_explicit_trace(
+				explicit_model,
+				&abstract_model.initial_states[initial_index].vector,
+				&trace,
+			);
 
 			// Print the trace generation progress every 100 traces
 			let percent_step = (num_traces as f64 / 100.0).ceil().max(1.0) as usize;
@@ -1,3 +1,4 @@
 pub mod dep_traces;
 pub mod ragtimer;
 pub mod rl_traces;
+pub mod time_bound;
@@ -1,13 +1,19 @@
 use crate::{
 	builder::{builder::Builder, ragtimer::rl_traces::default_magic_numbers},
+	checker::checker::check_explicit_model,
 	cycle_commute::commute::cycle_commute,
 	debug_message,
 	logging::messages::error,
 	message,
 	model::{
 		model::ProbabilityOrRate,
-		vas_model::{AbstractVas, PrismVasModel, PrismVasState, PrismVasTransition},
+		vas_model::{
+			AbstractVas, PrismVasModel, PrismVasState, PrismVasTransition, VasStateVector,
+		},
 	},
+	parser::parser::ModelOptions,
+	property::property::StateFormula,
+	validator::vas_validator::check_explicit_probabilities,
 	warning,
 };
 
@@ -42,6 +48,8 @@ pub(crate) struct RagtimerBuilder<'a> {
 	pub model_built: bool,
 	pub approach: RagtimerApproach,
 	pub traces_complete: usize,
+	// The time bound of the property, e.g., `T` in `F<=T`, if traces are scored within it
+	pub time_bound: Option<f64>,
 }
 
 impl<'a> Builder for RagtimerBuilder<'a> {
@@ -110,6 +118,7 @@ impl<'a> RagtimerBuilder<'a> {
 			model_built: false,
 			approach: RagtimerApproach::RandomPathExploration, // Placeholder will be set properly below
 			traces_complete: 0,
+			time_bound: None,
 		};
 		if let Some(m) = approach {
 			builder.approach = m;
@@ -119,14 +128,41 @@ impl<'a> RagtimerBuilder<'a> {
 		builder
 	}
 
+	/// Adds every initial state to the explicit model, labeled `init`, along with the
+	/// initial distribution. Returns the ID of each initial state in the explicit model.
+	pub(crate) fn add_initial_states(&self, explicit_model: &mut PrismVasModel) -> Vec<usize> {
+		explicit_model.initial_distribution = Vec::new();
+		let mut initial_ids = Vec::new();
+		for (state, weight) in self.abstract_model.initial_distribution() {
+			let state_id = match explicit_model.state_id(&state.vector) {
+				Some(existing_id) => existing_id,
+				None => explicit_model.add_state(PrismVasState {
+					state_id: explicit_model.states.len(),
+					vector: Some(state.vector.clone()),
+					labels: {
+						let mut labels = self.abstract_model.state_labels(&state.vector);
+						labels.insert(StateFormula::label("init"));
+						labels
+					},
+					used_rate: 0.0,
+					total_outgoing_rate: self.abstract_model.total_outgoing_rate(&state.vector),
+				}),
+			};
+			explicit_model.add_initial_probability(state_id, weight);
+			initial_ids.push(state_id);
+		}
+		initial_ids
+	}
+
 	/// Stores the explicit trace in the explicit model.
-	pub(super) fn store_explicit_trace(
+	pub(crate) fn store_explicit_trace(
 		&mut self,
 		explicit_model: &mut PrismVasModel,
+		initial_state: &VasStateVector,
 		trace: &Vec<usize>,
 	) {
 		// Start with the initial state
-		let mut current_state = self.abstract_model.initial_states[0].vector.clone();
+		let mut current_state = initial_state.clone();
 		let mut next_state = current_state.clone();
 		let mut current_state_id: usize = 0; // Start with the initial state ID
 		let mut next_state_id: usize = 0;
@@ -134,57 +170,31 @@ impl<'a> RagtimerBuilder<'a> {
 			if let Some(vas_transition) = self.abstract_model.get_transition_from_id(transition_id)
 			{
 				// Store the current state with correct absorbing rate
-				let available_state_id = explicit_model.states.len();
-				if let Some(existing_id) = explicit_model
-					.state_trie
-					.insert_if_not_exists(&current_state, available_state_id)
-				{
+				if let Some(existing_id) = explicit_model.state_id(&current_state) {
 					current_state_id = existing_id;
 				} else {
-					warning!("During exploration, current state {:?} does not already exist in the model, but it should. Adding it under ID {}", current_state, available_state_id);
-					current_state_id = available_state_id;
-					explicit_model.add_state(PrismVasState {
-						state_id: current_state_id,
-						vector: current_state.clone(),
-						label: if current_state.len() > self.abstract_model.target.variable_index
-							&& current_state[self.abstract_model.target.variable_index]
-								== self.abstract_model.target.target_value
-						{
-							Some("target".to_string())
-						} else {
-							None
-						},
+					warning!("During exploration, current state {:?} does not already exist in the model, but it should. Adding it under ID {}", current_state, explicit_model.states.len());
+					current_state_id = explicit_model.add_state(PrismVasState {
+						state_id: explicit_model.states.len(),
+						vector: Some(current_state.clone()),
+						labels: self.abstract_model.state_labels(&current_state),
 						used_rate: 0.0,
 						total_outgoing_rate: self
 							.abstract_model
-							.crn_total_outgoing_rate(&current_state),
+							.total_outgoing_rate(&current_state),
 					});
 				}
 				// Find the next state after applying the transition
 				next_state = current_state.clone() + vas_transition.update_vector.clone();
-				let available_state_id = explicit_model.states.len();
-				if let Some(existing_id) = explicit_model
-					.state_trie
-					.insert_if_not_exists(&next_state, available_state_id)
-				{
+				if let Some(existing_id) = explicit_model.state_id(&next_state) {
 					next_state_id = existing_id;
 				} else {
-					next_state_id = available_state_id;
-					explicit_model.add_state(PrismVasState {
-						state_id: next_state_id,
-						vector: next_state.clone(),
-						label: if next_state.len() > self.abstract_model.target.variable_index
-							&& next_state[self.abstract_model.target.variable_index]
-								== self.abstract_model.target.target_value
-						{
-							Some("target".to_string())
-						} else {
-							None
-						},
+					next_state_id = explicit_model.add_state(PrismVasState {
+						state_id: explicit_model.states.len(),
+						vector: Some(next_state.clone()),
+						labels: self.abstract_model.state_labels(&next_state),
 						used_rate: 0.0,
-						total_outgoing_rate: self
-							.abstract_model
-							.crn_total_outgoing_rate(&next_state),
+						total_outgoing_rate: self.abstract_model.total_outgoing_rate(&next_state),
 					});
 				}
 			} else {
@@ -203,14 +213,15 @@ impl<'a> RagtimerBuilder<'a> {
 				let transition_rate = if let Some(vas_transition) =
 					self.abstract_model.get_transition_from_id(transition_id)
 				{
-					vas_transition.get_sck_rate(&current_state)
+					self.abstract_model
+						.transition_rate(&current_state, vas_transition)
 				} else {
 					error!("Transition ID {} not found in model.", transition_id);
 					0.0
 				};
 				// debug_message!("ragtimer.rs store_explicit_trace()");
 				explicit_model.add_transition(PrismVasTransition {
-					transition_id,
+					transition_id: Some(transition_id),
 					from_state: current_state_id,
 					to_state: next_state_id,
 					rate: transition_rate,
@@ -222,15 +233,19 @@ impl<'a> RagtimerBuilder<'a> {
 	}
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn ragtimer(
 	model_file: &str,
+	options: &ModelOptions,
 	approach: RagtimerApproach,
 	max_cycle_length: usize,
 	max_commute_depth: usize,
 	output: &str,
+	check: bool,
+	target_sink: bool,
 ) {
 	// Attempt to parse the model file
-	if let Ok(mut abstract_model) = AbstractVas::from_file(model_file) {
+	if let Ok(mut abstract_model) = AbstractVas::from_file(model_file, options) {
 		let mut explicit_model = PrismVasModel::from_abstract_model(&abstract_model);
 		let approach = match approach {
 			RagtimerApproach::ReinforcementLearning(magic_numbers) => {
@@ -249,6 +264,7 @@ pub fn ragtimer(
 		};
 		// Run trace generation
 		let mut ragtimer_builder = RagtimerBuilder::new(&abstract_model, Some(approach));
+		ragtimer_builder.set_model_query(model_file, options);
 		ragtimer_builder.build(&mut explicit_model);
 		debug_message!("Traces added to explicit model with Ragtimer");
 		// Run cycle and commute
@@ -262,6 +278,12 @@ pub fn ragtimer(
 		// Finalize the explicit model by adding absorbing transitions
 		explicit_model.add_absorbing_transitions();
 		debug_message!("Absorbing transitions added to explicit model");
+		for error in check_explicit_probabilities(&explicit_model) {
+			warning!("{}", error);
+		}
+		if target_sink {
+			explicit_model = explicit_model.with_target_sink();
+		}
 		// Output the explicit model to PRISM files
 		explicit_model.print_explicit_prism_files(output);
 		message!(
@@ -270,6 +292,9 @@ pub fn ragtimer(
 			explicit_model.transitions.len()
 		);
 		message!("Ragtimer complete. Output written to {}", output);
+		if check {
+			check_explicit_model(&explicit_model, model_file, options);
+		}
 	} else {
 		error!("Failed to parse model file: {}", model_file);
 	}
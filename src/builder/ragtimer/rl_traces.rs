@@ -12,8 +12,7 @@ use crate::{
 	logging::messages::{debug_message, error, message},
 	model::{
 		model::ProbabilityOrRate,
-		vas_model::{PrismVasModel, PrismVasState},
-		vas_trie::VasTrieNode,
+		vas_model::{PrismVasModel, VasStateVector},
 	},
 	trace::trace_trie::TraceTrieNode,
 	warning,
@@ -54,7 +53,7 @@ impl<'a> RagtimerBuilder<'a> {
 			ReinforcementLearning(magic_numbers) => magic_numbers,
 			_ => panic!("RagtimerBuilder::add_rl_traces called with non-RL method"),
 		};
-		let num_dependencies = dependency_graph.get_transitions().len() as f64;
+		let num_dependencies = (dependency_graph.get_transitions().len() as f64).max(1.0);
 		debug_message!("Number of dependencies in graph: {}", num_dependencies);
 		let effective_dependency_reward = magic_numbers.dependency_reward / num_dependencies;
 		debug_message!(
@@ -183,28 +182,23 @@ impl<'a> RagtimerBuilder<'a> {
 	/// This function will be called multiple times to generate traces for the RL traces method.
 	fn generate_rl_trace(
 		&mut self,
+		initial_state: &VasStateVector,
 		rewards: &HashMap<usize, RewardValue>,
 	) -> (Vec<usize>, ProbabilityOrRate) {
 		let mut trace = Vec::new();
 		let mut trace_states = Vec::new();
-		let mut trace_probability = 1.0;
+		let mut path_probability = 1.0;
+		// The exit rate of each state the trace leaves, for the time-bounded probability
+		let mut exit_rates = Vec::new();
 		let vas_target = &self.abstract_model.target;
 
-		// Starting in the initial state, generate a trace
-		let mut current_state = self.abstract_model.initial_states[0].vector.clone();
+		// Starting in the given initial state, generate a trace
+		let mut current_state = initial_state.clone();
 		while trace.len() < MAX_TRACE_LENGTH {
 			trace_states.push(current_state.clone());
 			// Check if we have reached the target state
-			if current_state.len() > vas_target.variable_index {
-				if current_state[vas_target.variable_index] == vas_target.target_value {
-					break;
-				}
-			} else {
-				error!(
-					"Current state length {} is less than target variable index {}",
-					current_state.len(),
-					vas_target.variable_index
-				);
+			if vas_target.is_satisfied(&current_state) {
+				break;
 			}
 			// Get available transitions
 			let available_transitions = self
@@ -266,11 +260,12 @@ impl<'a> RagtimerBuilder<'a> {
 					if let Some(vas_transition) =
 						self.abstract_model.get_transition_from_id(transition)
 					{
-						current_state = current_state + vas_transition.update_vector.clone();
-						trace.push(transition);
-						trace_probability *= self
+						path_probability *= self
 							.abstract_model
 							.transition_probability(&current_state, &vas_transition);
+						exit_rates.push(self.abstract_model.total_outgoing_rate(&current_state));
+						current_state = current_state + vas_transition.update_vector.clone();
+						trace.push(transition);
 					} else {
 						error!("Transition ID {} not found in model.", transition);
 					}
@@ -279,6 +274,7 @@ impl<'a> RagtimerBuilder<'a> {
 			}
 		}
 
+		let trace_probability = self.timed_trace_probability(path_probability, &exit_rates);
 		(trace, trace_probability)
 	}
 
@@ -298,24 +294,20 @@ impl<'a> RagtimerBuilder<'a> {
 		let mut trace_probability_history: Vec<ProbabilityOrRate> = Vec::new();
 
 		// Set up state space storage structures
-		explicit_model.state_trie = VasTrieNode::new();
-		let current_state_id = 1;
-		let current_state = self.abstract_model.initial_states[0].vector.clone();
-		explicit_model
-			.state_trie
-			.insert_if_not_exists(&current_state, current_state_id);
-		explicit_model.add_state(PrismVasState {
-			state_id: current_state_id,
-			vector: current_state.clone(),
-			label: Some("init".to_string()),
-			used_rate: 0.0,
-			total_outgoing_rate: self.abstract_model.crn_total_outgoing_rate(&current_state),
-		});
+		let abstract_model = self.abstract_model;
+		let initial_ids = self.add_initial_states(explicit_model);
 
 		// If the dependency graph is not provided, we try to construct it from the abstract model.
 		let owned_dep_graph;
 		let dependency_graph_ref: &DependencyGraph = match dependency_graph {
 			Some(dep_graph) => dep_graph,
+			// Linear-combination targets, which the dependency graph cannot handle, are
+			// still explored, just without dependency rewards.
+			None if self.abstract_model.target.has_linear_atoms() => {
+				warning!("The dependency graph does not support linear-combination targets. Continuing without dependency rewards.");
+				owned_dep_graph = Some(DependencyGraph::empty());
+				owned_dep_graph.as_ref().unwrap()
+			}
 			None => {
 				let dep_graph_result = make_dependency_graph(&self.abstract_model);
 				match dep_graph_result {
@@ -345,12 +337,19 @@ impl<'a> RagtimerBuilder<'a> {
 		for i in 0..num_traces {
 			let mut trace;
 			let mut trace_probability;
+			let mut initial_index;
 			let mut trace_attempts = 0;
 			loop {
-				// Generate a single trace
-				(trace, trace_probability) = self.generate_rl_trace(&rewards);
+				// Generate a single trace from a sampled initial state
+				initial_index = abstract_model.sample_initial_index();
+				(trace, trace_probability) = self.generate_rl_trace(
+					&abstract_model.initial_states[initial_index].vector,
+					&rewards,
+				);
 				// If the trace already exists or is empty, we try to generate a new one.
-				if !trace_trie.exists_or_insert(&trace) && !trace.is_empty() {
+				// Traces are stored after the ID of the state they start in.
+				let stored_trace = [vec![initial_ids[initial_index]], trace.clone()].concat();
+				if !trace_trie.exists_or_insert(&stored_trace) && !trace.is_empty() {
 					break;
 				}
 				trace_attempts += 1;
@@ -360,7 +359,11 @@ impl<'a> RagtimerBuilder<'a> {
 			}
 			trace_probability_history.push(trace_probability);
 			// Store explicit prism states and transitions for this trace
-			self.store_explicit_trace(explicit_model, &trace);
+			self.store_explicit_trace(
+				explicit_model,
+				&abstract_model.initial_states[initial_index].vector,
+				&trace,
+			);
 			// Update the rewards based on the trace
 			self.update_rewards(&mut rewards, &trace, &trace_probability_history);
 			self.maintain_rewards(&mut rewards, dependency_graph_ref);
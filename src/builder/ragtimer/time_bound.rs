@@ -0,0 +1,184 @@
+use crate::{
+	builder::ragtimer::ragtimer::RagtimerBuilder,
+	logging::messages::message,
+	model::model::{ModelType, ProbabilityOrRate},
+	parser::{csl_parser::model_query, parser::ModelOptions},
+	property::property::PropertyQuery,
+	util::poisson::poisson_weights,
+	warning,
+};
+
+/// The Poisson mass we are willing to leave out when uniformizing a path
+const UNIFORMIZATION_EPSILON: f64 = 1e-10;
+
+/// The probability that a sum of exponential delays with the given rates is at
+/// most `time`, i.e., the CDF of the hypoexponential distribution. This is the
+/// probability of completing a path by `time` when `rates` are the exit rates of
+/// the states along it. The path is uniformized rather than using the closed form,
+/// which is unstable when two rates are close or equal.
+pub fn hypoexponential_cdf(rates: &[ProbabilityOrRate], time: f64) -> ProbabilityOrRate {
+	if rates.is_empty() {
+		return 1.0;
+	}
+	if time <= 0.0 || rates.iter().any(|rate| *rate <= 0.0) {
+		return 0.0;
+	}
+	// When the time bound is far beyond the expected duration, Cantelli's
+	// inequality bounds the probability of being late well below our precision
+	let mean: f64 = rates.iter().map(|rate| 1.0 / rate).sum();
+	let variance: f64 = rates.iter().map(|rate| 1.0 / (rate * rate)).sum();
+	if time > mean && variance / (variance + (time - mean).powi(2)) < UNIFORMIZATION_EPSILON {
+		return 1.0;
+	}
+	// Uniformize the path: in each step, leave the i-th state with probability rates[i] / q
+	let q = rates.iter().cloned().fold(0.0, f64::max);
+	let poisson = poisson_weights(q * time, UNIFORMIZATION_EPSILON);
+	let steps = rates.len();
+	if steps > poisson.right() {
+		return 0.0;
+	}
+	let stay: Vec<f64> = rates.iter().map(|rate| 1.0 - rate / q).collect();
+	let mut distribution = vec![0.0; steps + 1];
+	distribution[0] = 1.0;
+	let mut probability = 0.0;
+	for k in 0..=poisson.right() {
+		// Once the path is almost surely complete, every later step adds its full weight
+		if distribution[steps] > 1.0 - UNIFORMIZATION_EPSILON {
+			probability += (k..=poisson.right())
+				.map(|k| poisson.weight(k))
+				.sum::<f64>();
+			break;
+		}
+		probability += poisson.weight(k) * distribution[steps];
+		// Only the first k + 1 states can have been reached after k steps
+		for i in (0..steps.min(k + 1)).rev() {
+			let leaving = distribution[i] * (1.0 - stay[i]);
+			distribution[i + 1] += leaving;
+			distribution[i] -= leaving;
+		}
+	}
+	probability.min(1.0)
+}
+
+impl<'a> RagtimerBuilder<'a> {
+	/// Bounds the traces by the time bound of the query, e.g., `T` in `P=? [ F<=T "target" ]`
+	pub fn set_property_query(&mut self, query: &PropertyQuery) {
+		self.time_bound = query.property().and_then(|property| property.time_bound());
+	}
+
+	/// Bounds the traces by the time bound of the query the model is checked
+	/// against, if there is one (see `model_query`)
+	pub fn set_model_query(&mut self, model_file: &str, options: &ModelOptions) {
+		match model_query(model_file, options) {
+			Some(Ok(query)) => {
+				self.set_property_query(&query);
+				if let Some(time_bound) = self.time_bound {
+					message!(
+						"Scoring traces by their probability of reaching the target within {}",
+						time_bound
+					);
+				}
+			}
+			Some(Err(e)) => {
+				warning!("{}\nScoring traces without a time bound.", e);
+			}
+			None => {}
+		}
+	}
+
+	/// The probability of following a trace whose states have the given exit rates,
+	/// given the probability of its path in the embedded chain. With a time bound,
+	/// this is the probability of completing the path within the bound: in a CTMC
+	/// each state is left after an exponential delay, and in a DTMC each transition
+	/// takes one step.
+	pub(super) fn timed_trace_probability(
+		&self,
+		path_probability: ProbabilityOrRate,
+		exit_rates: &[ProbabilityOrRate],
+	) -> ProbabilityOrRate {
+		let Some(time_bound) = self.time_bound else {
+			return path_probability;
+		};
+		match self.abstract_model.m_type {
+			ModelType::ContinuousTime => {
+				path_probability * hypoexponential_cdf(exit_rates, time_bound)
+			}
+			ModelType::DiscreteTime => {
+				if exit_rates.len() as f64 <= time_bound {
+					path_probability
+				} else {
+					0.0
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::hypoexponential_cdf;
+
+	/// The CDF of the Erlang distribution with `n` phases of rate `rate`
+	fn erlang_cdf(n: usize, rate: f64, time: f64) -> f64 {
+		let x = rate * time;
+		let mut term = 1.0;
+		let mut sum = 0.0;
+		for k in 0..n {
+			if k > 0 {
+				term *= x / k as f64;
+			}
+			sum += term;
+		}
+		1.0 - (-x).exp() * sum
+	}
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!(
+			(actual - expected).abs() < 1e-8,
+			"{} != {}",
+			actual,
+			expected
+		);
+	}
+
+	#[test]
+	fn single_rate_is_exponential() {
+		for (rate, time) in [(1.0_f64, 0.5), (2.0, 1.0), (0.1, 30.0), (5.0, 0.01)] {
+			assert_close(
+				hypoexponential_cdf(&[rate], time),
+				1.0 - (-rate * time).exp(),
+			);
+		}
+	}
+
+	#[test]
+	fn equal_rates_are_erlang() {
+		for (rate, time) in [(1.0, 0.5), (1.0, 2.0), (3.0, 1.5)] {
+			assert_close(
+				hypoexponential_cdf(&[rate, rate], time),
+				erlang_cdf(2, rate, time),
+			);
+			assert_close(
+				hypoexponential_cdf(&[rate; 5], time),
+				erlang_cdf(5, rate, time),
+			);
+		}
+	}
+
+	#[test]
+	fn two_distinct_rates() {
+		for (a, b, time) in [(1.0_f64, 2.0, 1.0), (0.5, 3.0, 2.0), (4.0, 0.25, 5.0)] {
+			let expected = 1.0 - (b * (-a * time).exp() - a * (-b * time).exp()) / (b - a);
+			assert_close(hypoexponential_cdf(&[a, b], time), expected);
+			assert_close(hypoexponential_cdf(&[b, a], time), expected);
+		}
+	}
+
+	#[test]
+	fn degenerate_paths() {
+		assert_eq!(hypoexponential_cdf(&[], 1.0), 1.0);
+		assert_eq!(hypoexponential_cdf(&[1.0], 0.0), 0.0);
+		assert_eq!(hypoexponential_cdf(&[1.0, 0.0], 1.0), 0.0);
+		assert_close(hypoexponential_cdf(&[1.0, 2.0], 1000.0), 1.0);
+	}
+}
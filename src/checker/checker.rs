@@ -1,36 +1,277 @@
-use metaverify::*;
+use crate::{
+	builder::builder::Builder,
+	checker::{
+		reachability::{check_reachability, satisfying_states},
+		result::ModelCheckingResult,
+		steady_state::check_steady_state,
+	},
+	logging::messages::*,
+	model::{
+		model::{AbstractModel, ExplicitModel},
+		vas_model::PrismVasModel,
+	},
+	parser::{
+		csl_parser::{model_property_file, PropertyFile},
+		parser::ModelOptions,
+	},
+	property::property::{Property, PropertyQuery, StateFormula},
+	warning,
+};
 
-use crate::*;
-
-use model::*;
-use builder::*;
-
-#[trusted]
 pub(crate) trait Checker {
 	type AbstractModelType: AbstractModel;
 	type ExplicitModelType: ExplicitModel;
-	type BuilderType: Builder;
+	type BuilderType: Builder<
+		AbstractModelType = Self::AbstractModelType,
+		ExplicitModelType = Self::ExplicitModelType,
+		ResultType = Self::ResultType,
+	>;
 	type ResultType: Clone + Copy + PartialEq + Default;
 
 	// TODO
-	#[trusted]
-	fn builder(&self) -> &BuilderType;
-	#[trusted]
-	fn builder_mut(&self) -> &mut BuilderType;
+	fn builder(&self) -> &Self::BuilderType;
+	fn builder_mut(&mut self) -> &mut Self::BuilderType;
 	/// Checks the explicit model and returns a result
-	#[trusted]
-	fn check(&mut self, model: &ExplicitModelType) -> ResultType;
-	
+	fn check(&mut self, model: &Self::ExplicitModelType) -> Self::ResultType;
+
 	/// Builds the model and checks it
-	#[trusted]
-	fn build_and_check(&mut self) -> ResultType {
-		let mut bldr = self.builder_mut();
-		
-		let mut explicit_model: ExplicitModelType::default();
-		let mut finished = false;
-		while !finished {
-			// TODO	
+	fn build_and_check(&mut self) -> Self::ResultType {
+		let mut explicit_model = Self::ExplicitModelType::default();
+		loop {
+			self.builder_mut().build(&mut explicit_model);
+			let result = self.check(&explicit_model);
+			if self.builder_mut().finished(&result) {
+				return result;
+			}
+		}
+	}
+}
+
+/// Checks the explicit model built by a command with `--check` against the first
+/// property of the property file of the model (see `model_property_file`), and
+/// prints the result. Without a property file, the probability of eventually
+/// reaching the target is checked.
+pub(crate) fn check_explicit_model(
+	model: &PrismVasModel,
+	model_file: &str,
+	options: &ModelOptions,
+) -> ModelCheckingResult {
+	let property_file = match model_property_file(model_file, options) {
+		Some(Ok(property_file)) => Some(property_file),
+		Some(Err(e)) => {
+			warning!("{}\nChecking without the property file.", e);
+			None
+		}
+		None => None,
+	};
+	let Some((property_file, query)) = property_file.as_ref().and_then(|property_file| {
+		property_file
+			.query()
+			.ok()
+			.map(|query| (property_file, query))
+	}) else {
+		message!("Checking the probability of eventually reaching the target");
+		let everywhere = vec![true; model.states.len()];
+		let target = satisfying_states(model, &StateFormula::label("target"));
+		if !target.iter().any(|t| *t) {
+			warning!("No target state was found in the explicit model");
+		}
+		let result = check_reachability(model, &everywhere, &target, None);
+		message!("{}", result);
+		return result;
+	};
+	message!("Checking {}", query);
+	let result = match check_query(model, property_file, query) {
+		Ok(result) => result,
+		Err(e) => {
+			error!("{}", e);
+			return ModelCheckingResult::NoResult;
+		}
+	};
+	message!("{}", result);
+	if let (
+		PropertyQuery::BoundedProbability(_, relation, bound),
+		ModelCheckingResult::ProbabilityRange(pmin, pmax),
+	) = (query, result)
+	{
+		match (relation.holds(pmin, *bound), relation.holds(pmax, *bound)) {
+			(true, true) => {
+				message!("The bound P{}{} holds", relation, bound);
+			}
+			(false, false) => {
+				message!("The bound P{}{} does not hold", relation, bound);
+			}
+			_ => {
+				warning!(
+					"The bound P{}{} cannot be decided between Pmin and Pmax. Explore more states to decide it.",
+					relation,
+					bound
+				);
+			}
 		}
 	}
+	result
+}
+
+/// Checks a query of a property file against the explicit model. `U` paths may
+/// only pass through states where the left-hand side holds, and `G phi` holds
+/// with the probability that no state where `phi` does not hold is reached.
+fn check_query(
+	model: &PrismVasModel,
+	property_file: &PropertyFile,
+	query: &PropertyQuery,
+) -> Result<ModelCheckingResult, String> {
+	let states = |formula: &StateFormula| -> Result<Vec<bool>, String> {
+		let states = satisfying_states(model, &property_file.expand(formula)?);
+		Ok(states)
+	};
+	let goal = |formula: &StateFormula| -> Result<Vec<bool>, String> {
+		let goal = states(formula)?;
+		if !goal.iter().any(|g| *g) {
+			warning!(
+				"No state where {} holds was found in the explicit model",
+				formula
+			);
+		}
+		Ok(goal)
+	};
+	let everywhere = vec![true; model.states.len()];
+	let property = match query {
+		PropertyQuery::SteadyState(formula) => {
+			return Ok(check_steady_state(model, &property_file.expand(formula)?));
+		}
+		query => query.property().unwrap(),
+	};
+	Ok(match property {
+		Property::Finally(formula, time_bound) => {
+			check_reachability(model, &everywhere, &goal(formula)?, *time_bound)
+		}
+		Property::Until(lhs, rhs, time_bound) => {
+			check_reachability(model, &states(lhs)?, &goal(rhs)?, *time_bound)
+		}
+		Property::Globally(formula) => {
+			let violating = states(&StateFormula::Not(Box::new(formula.clone())))?;
+			match check_reachability(model, &everywhere, &violating, None) {
+				ModelCheckingResult::ProbabilityRange(pmin, pmax) => {
+					ModelCheckingResult::ProbabilityRange(1.0 - pmax, 1.0 - pmin)
+				}
+				result => result,
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeSet;
+
+	use nalgebra::DVector;
 
+	use super::check_query;
+	use crate::{
+		checker::result::ModelCheckingResult,
+		model::vas_model::{PrismVasModel, PrismVasState, PrismVasTransition},
+		parser::csl_parser::parse_properties,
+	};
+
+	/// A CTMC over the variable `X` that starts in the first of the states with the
+	/// given values of `X`. State 0 is the absorbing state, and the states with
+	/// values are numbered from 1.
+	fn model(values: &[i128], transitions: &[(usize, usize, f64)]) -> PrismVasModel {
+		let mut model = PrismVasModel::new();
+		model.variable_names = vec!["X".to_string()];
+		for value in values {
+			model.add_state(PrismVasState {
+				state_id: model.states.len(),
+				vector: Some(DVector::from_vec(vec![*value])),
+				labels: BTreeSet::new(),
+				used_rate: 0.0,
+				total_outgoing_rate: 0.0,
+			});
+		}
+		for &(from, _, rate) in transitions {
+			model.states[from].total_outgoing_rate += rate;
+		}
+		for &(from_state, to_state, rate) in transitions {
+			model.add_transition(PrismVasTransition {
+				transition_id: None,
+				from_state,
+				to_state,
+				rate,
+			});
+		}
+		model.add_initial_probability(1, 1.0);
+		model
+	}
+
+	/// The (Pmin, Pmax) of the first property of `source`
+	fn check(model: &PrismVasModel, source: &str) -> (f64, f64) {
+		let properties = parse_properties(source).unwrap();
+		match check_query(model, &properties, properties.query().unwrap()).unwrap() {
+			ModelCheckingResult::ProbabilityRange(pmin, pmax) => (pmin, pmax),
+			result => panic!("Unexpected result {}", result),
+		}
+	}
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!(
+			(actual - expected).abs() < 1e-9,
+			"{} != {}",
+			actual,
+			expected
+		);
+	}
+
+	#[test]
+	fn time_bounded_reachability_is_exponential() {
+		let rate: f64 = 2.0;
+		let model = model(&[0, 1], &[(1, 2, rate)]);
+		for time in [0.1, 0.5, 3.0] {
+			let (pmin, pmax) = check(&model, &format!("P=? [ F<={} X = 1 ]", time));
+			assert_close(pmin, 1.0 - (-rate * time).exp());
+			assert_close(pmax, pmin);
+		}
+		assert_eq!(check(&model, "P=? [ F X = 1 ]"), (1.0, 1.0));
+	}
+
+	#[test]
+	fn until_cuts_off_paths_leaving_the_left_operand() {
+		// From X = 0, the goal X = 2 is reached directly or through X = 1
+		let model = model(&[0, 1, 2], &[(1, 2, 1.0), (1, 3, 3.0), (2, 3, 5.0)]);
+		let (pmin, pmax) = check(&model, "P=? [ X != 1 U X = 2 ]");
+		assert_close(pmin, 0.75);
+		assert_close(pmax, 0.75);
+		assert_eq!(check(&model, "P=? [ true U X = 2 ]"), (1.0, 1.0));
+		// Within time 1, X = 2 is reached directly with probability 3/4 (1 - e^-4)
+		let (pmin, _) = check(&model, "P=? [ X != 1 U<=1 X = 2 ]");
+		assert_close(pmin, 0.75 * (1.0 - (-4.0_f64).exp()));
+	}
+
+	#[test]
+	fn globally_is_the_complement_of_finally() {
+		let model = model(&[0, 1, 2], &[(1, 2, 1.0), (1, 3, 3.0)]);
+		let (pmin, pmax) = check(&model, "P=? [ G X < 2 ]");
+		let (finally_min, finally_max) = check(&model, "P=? [ F !(X < 2) ]");
+		assert_close(pmin, 0.25);
+		assert_close(pmax, 0.25);
+		assert_close(pmin, 1.0 - finally_max);
+		assert_close(pmax, 1.0 - finally_min);
+	}
+
+	#[test]
+	fn pmax_includes_the_absorbing_mass() {
+		// A quarter of the probability leaves X = 0 for the unexplored states
+		let model = model(&[0, 1], &[(1, 2, 3.0), (1, 0, 1.0)]);
+		let (pmin, pmax) = check(&model, "P=? [ F X = 1 ]");
+		assert_close(pmin, 0.75);
+		assert_close(pmax, 1.0);
+		let time: f64 = 0.5;
+		let (pmin, pmax) = check(&model, &format!("P=? [ F<={} X = 1 ]", time));
+		assert_close(pmin, 0.75 * (1.0 - (-4.0 * time).exp()));
+		assert_close(pmax, 1.0 - (-4.0 * time).exp());
+		// `G` is bounded the other way: the unexplored states may violate it
+		let (pmin, pmax) = check(&model, "P=? [ G X = 0 ]");
+		assert_close(pmin, 0.0);
+		assert_close(pmax, 0.25);
+	}
 }
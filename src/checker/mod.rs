@@ -1,2 +1,5 @@
 pub(crate) mod checker;
+pub(crate) mod reachability;
 pub(crate) mod result;
+pub(crate) mod steady_state;
+pub(crate) mod uniformization;
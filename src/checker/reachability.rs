@@ -0,0 +1,236 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::{
+	checker::{
+		result::ModelCheckingResult,
+		uniformization::{step_bounded_distribution, transient_distribution},
+	},
+	logging::messages::*,
+	model::{
+		model::ModelType,
+		sparse_matrix::SparseMatrix,
+		vas_model::{PrismVasModel, VasState},
+	},
+	property::property::{Labeled, StateFormula},
+	warning,
+};
+
+/// The change in a reachability probability at which Gauss–Seidel is considered converged
+const TOLERANCE: f64 = 1e-12;
+/// Gauss–Seidel sweeps after which unbounded reachability gives up on converging
+const MAX_ITERATIONS: usize = 100_000;
+
+/// The states that can reach a state in `from` through states in `through`,
+/// including the states in `from` themselves
+fn backward_reachable(predecessors: &[Vec<usize>], from: &[bool], through: &[bool]) -> Vec<bool> {
+	let mut reached = from.to_vec();
+	let mut queue: VecDeque<usize> = (0..from.len()).filter(|s| from[*s]).collect();
+	while let Some(state) = queue.pop_front() {
+		for &predecessor in predecessors[state].iter() {
+			if !reached[predecessor] && through[predecessor] {
+				reached[predecessor] = true;
+				queue.push_back(predecessor);
+			}
+		}
+	}
+	reached
+}
+
+/// The states that reach `goal` with probability 0 (Prob0) and with probability 1
+/// (Prob1), found from the graph of the model alone
+fn prob0_prob1(matrix: &SparseMatrix, goal: &[bool]) -> (Vec<bool>, Vec<bool>) {
+	let predecessors = matrix.predecessors();
+	let everywhere = vec![true; matrix.state_count()];
+	let prob0: Vec<bool> = backward_reachable(&predecessors, goal, &everywhere)
+		.iter()
+		.map(|reaches| !reaches)
+		.collect();
+	// A state reaches the goal almost surely unless it can reach a Prob0 state
+	// without passing through the goal
+	let not_goal: Vec<bool> = goal.iter().map(|g| !g).collect();
+	let prob1 = backward_reachable(&predecessors, &prob0, &not_goal)
+		.iter()
+		.map(|reaches| !reaches)
+		.collect();
+	(prob0, prob1)
+}
+
+/// The probability of eventually reaching `goal` from each state, by Gauss–Seidel
+/// iteration on the embedded DTMC over the states that are neither Prob0 nor Prob1
+pub(crate) fn unbounded_reachability(matrix: &SparseMatrix, goal: &[bool]) -> Vec<f64> {
+	let (prob0, prob1) = prob0_prob1(matrix, goal);
+	let mut probabilities: Vec<f64> = prob1.iter().map(|p| if *p { 1.0 } else { 0.0 }).collect();
+	let maybe: Vec<usize> = (0..matrix.state_count())
+		.filter(|s| !prob0[*s] && !prob1[*s])
+		.collect();
+	debug_message!(
+		"Reachability: {} Prob0 states, {} Prob1 states, {} to solve",
+		prob0.iter().filter(|p| **p).count(),
+		prob1.iter().filter(|p| **p).count(),
+		maybe.len()
+	);
+	let embedded = matrix.embedded();
+	for iteration in 0..MAX_ITERATIONS {
+		let mut max_change: f64 = 0.0;
+		for &state in maybe.iter() {
+			let value: f64 = embedded
+				.row(state)
+				.map(|(to, probability)| probability * probabilities[to])
+				.sum();
+			max_change = max_change.max((value - probabilities[state]).abs());
+			probabilities[state] = value;
+		}
+		if max_change < TOLERANCE {
+			debug_message!("Gauss-Seidel converged after {} iterations", iteration + 1);
+			return probabilities;
+		}
+	}
+	warning!(
+		"Unbounded reachability did not converge within {} iterations",
+		MAX_ITERATIONS
+	);
+	probabilities
+}
+
+/// The states of the explicit model where `formula` holds, evaluated over the
+/// variables and labels of each state. The formula holds in no absorbing state,
+/// since the absorbing state stands for the unexplored states, and in other sinks
+/// without a vector only if their labels decide it, e.g., `"target"`.
+pub(crate) fn satisfying_states(model: &PrismVasModel, formula: &StateFormula) -> Vec<bool> {
+	let variable_names: Arc<[String]> = model.variable_names.clone().into();
+	let absorbing_label = StateFormula::label("absorbing");
+	model
+		.states
+		.iter()
+		.map(|state| {
+			if state.has_label(&absorbing_label) {
+				return false;
+			}
+			let Some(vector) = &state.vector else {
+				return formula.holds_by_labels(state).unwrap_or(false);
+			};
+			let mut labeled = VasState::with_variable_names(vector.clone(), variable_names.clone());
+			state
+				.labels
+				.iter()
+				.for_each(|label| labeled.add_label(label.clone()));
+			formula.holds_in(&labeled)
+		})
+		.collect()
+}
+
+/// Checks the probability of reaching a `goal` state through states where `through`
+/// holds, i.e., `through U goal`, within `time_bound` if there is one (a number of
+/// steps in a DTMC). The probability of reaching a goal state is the lower bound
+/// Pmin, and the mass in the absorbing state, which stands for the unexplored
+/// states, is the gap to Pmax.
+pub(crate) fn check_reachability(
+	model: &PrismVasModel,
+	through: &[bool],
+	goal: &[bool],
+	time_bound: Option<f64>,
+) -> ModelCheckingResult {
+	let absorbing_label = StateFormula::label("absorbing");
+	let is_absorbing: Vec<bool> = model
+		.states
+		.iter()
+		.map(|state| state.has_label(&absorbing_label))
+		.collect();
+	// The goal, the absorbing state, and the states that leave `through` are sinks
+	let sinks: Vec<bool> = (0..model.states.len())
+		.map(|s| goal[s] || is_absorbing[s] || !through[s])
+		.collect();
+	let goal_or_absorbing: Vec<bool> = (0..model.states.len())
+		.map(|s| goal[s] || is_absorbing[s])
+		.collect();
+	let matrix = model.matrix().with_absorbing(&sinks);
+	let mut initial = vec![0.0; model.states.len()];
+	for (id, probability) in model.initial_distribution.iter() {
+		initial[*id] += probability;
+	}
+	let mass = |probabilities: &[f64], states: &[bool]| -> f64 {
+		probabilities
+			.iter()
+			.zip(states.iter())
+			.filter(|(_, counted)| **counted)
+			.map(|(p, _)| p)
+			.sum()
+	};
+	let (pmin, pmax) = match (time_bound, model.m_type) {
+		(Some(time), ModelType::ContinuousTime) => {
+			let distribution = transient_distribution(&matrix, initial, time);
+			let pmin = mass(&distribution, goal);
+			(pmin, pmin + mass(&distribution, &is_absorbing))
+		}
+		(Some(steps), ModelType::DiscreteTime) => {
+			let distribution = step_bounded_distribution(&matrix, initial, steps.floor() as usize);
+			let pmin = mass(&distribution, goal);
+			(pmin, pmin + mass(&distribution, &is_absorbing))
+		}
+		(None, _) => {
+			// Weigh the probability of reaching the goal (or the absorbing state) from
+			// each initial state by the initial distribution
+			let weighted = |probabilities: Vec<f64>| -> f64 {
+				probabilities
+					.iter()
+					.zip(initial.iter())
+					.map(|(p, i)| p * i)
+					.sum()
+			};
+			let pmin = weighted(unbounded_reachability(&matrix, goal));
+			(
+				pmin,
+				weighted(unbounded_reachability(&matrix, &goal_or_absorbing)),
+			)
+		}
+	};
+	let pmin = pmin.clamp(0.0, 1.0);
+	ModelCheckingResult::ProbabilityRange(pmin, pmax.clamp(pmin, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{prob0_prob1, unbounded_reachability};
+	use crate::model::sparse_matrix::SparseMatrix;
+
+	/// A chain where state 0 moves to 1 or to the dead end 2, state 1 moves back
+	/// to 0 or to 4, and state 4 always moves on to the goal 3
+	fn chain() -> (SparseMatrix, Vec<bool>) {
+		let matrix = SparseMatrix::from_entries(
+			5,
+			[
+				(0, 1, 1.0),
+				(0, 2, 2.0),
+				(1, 0, 1.0),
+				(1, 4, 1.0),
+				(4, 3, 2.0),
+				(4, 4, 5.0),
+			],
+		);
+		(matrix, vec![false, false, false, true, false])
+	}
+
+	#[test]
+	fn prob0_and_prob1_states() {
+		let (matrix, goal) = chain();
+		let (prob0, prob1) = prob0_prob1(&matrix, &goal);
+		assert_eq!(prob0, vec![false, false, true, false, false]);
+		assert_eq!(prob1, vec![false, false, false, true, true]);
+	}
+
+	#[test]
+	fn gauss_seidel_solves_the_other_states() {
+		let (matrix, goal) = chain();
+		// p0 = p1 / 3 and p1 = (p0 + 1) / 2, so p0 = 1/5 and p1 = 3/5
+		let expected = [0.2, 0.6, 0.0, 1.0, 1.0];
+		let probabilities = unbounded_reachability(&matrix, &goal);
+		for (actual, expected) in probabilities.iter().zip(expected.iter()) {
+			assert!(
+				(actual - expected).abs() < 1e-9,
+				"{} != {}",
+				actual,
+				expected
+			);
+		}
+	}
+}
@@ -1,33 +1,47 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub(crate) enum ModelCheckingResult {
+	#[default]
 	NoResult, // checking has not yet been performed or was unable to occur
-	LowerBound(f64), // A lower bound (Pmin)
-	UpperBound(f64), // An upper bound (Pmax)
-	ExactProbability(f64), // The exact probability
+	LowerBound(f64),            // A lower bound (Pmin)
+	UpperBound(f64),            // An upper bound (Pmax)
+	ExactProbability(f64),      // The exact probability
 	ProbabilityRange(f64, f64), // A probabilistic range of Pmin to Pmax
-	VariableValueResult(i64), // A result representing a variable value
+	VariableValueResult(i64),   // A result representing a variable value
 }
+
 impl ModelCheckingResult {
-	// TODO: I would like to have these perform the model checking
-	// but idk if that makes sense
-	#[trusted]
-	fn new() -> Self {
-		unimplemented!();
-	}
-	#[trusted]
-	fn valid(&self) -> bool {
+	pub(crate) fn valid(&self) -> bool {
+		let probability = |p: &f64| (0.0..=1.0).contains(p);
 		match self {
-			NoResult => true,
+			ModelCheckingResult::NoResult => true,
 			// Probabilities must be in the range of 0.0-1.0
-			LowerBound(pmin) => pmin >= 0.0 && pmin <= 1.0,
-			UpperBound(pmax) => pmax >= 0.0 && pmax <= 1.0,
-			ExactProbability(p) => p >= 0.0 && p <= 1.0,
+			ModelCheckingResult::LowerBound(pmin) => probability(pmin),
+			ModelCheckingResult::UpperBound(pmax) => probability(pmax),
+			ModelCheckingResult::ExactProbability(p) => probability(p),
 			// With the range result we add the additional restriction
 			// of pmax >= pmin (in addition to both being valid probabilistic ranges
-			ProbabilityRange(pmin, pmax) => pmin >= 0.0 && pmin <= 1.0
-									  && pmax >= 0.0 && pmax <= 1.0
-									  && pmax >= pmin,
+			ModelCheckingResult::ProbabilityRange(pmin, pmax) => {
+				probability(pmin) && probability(pmax) && pmax >= pmin
+			}
 			// We do not try to validate other types of results
-			_ => true
+			_ => true,
+		}
+	}
+}
+
+impl fmt::Display for ModelCheckingResult {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ModelCheckingResult::NoResult => write!(f, "No result"),
+			ModelCheckingResult::LowerBound(pmin) => write!(f, "Pmin = {}", pmin),
+			ModelCheckingResult::UpperBound(pmax) => write!(f, "Pmax = {}", pmax),
+			ModelCheckingResult::ExactProbability(p) => write!(f, "P = {}", p),
+			ModelCheckingResult::ProbabilityRange(pmin, pmax) => {
+				write!(f, "Pmin = {}, Pmax = {}", pmin, pmax)
+			}
+			ModelCheckingResult::VariableValueResult(value) => write!(f, "Value = {}", value),
 		}
 	}
 }
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use crate::{
+	checker::{reachability::satisfying_states, result::ModelCheckingResult},
+	logging::messages::*,
+	model::{sparse_matrix::SparseMatrix, vas_model::PrismVasModel},
+	property::property::{Labeled, StateFormula},
+	warning,
+};
+
+/// The change in a stationary probability at which Gauss–Seidel is considered converged
+const TOLERANCE: f64 = 1e-12;
+/// Gauss–Seidel sweeps after which a stationary distribution gives up on converging
+const MAX_ITERATIONS: usize = 100_000;
+
+/// The strongly connected components of the graph of the model, by Tarjan's
+/// algorithm without recursion, so that long chains of states do not overflow the stack
+fn strongly_connected_components(matrix: &SparseMatrix) -> Vec<Vec<usize>> {
+	let n = matrix.state_count();
+	let mut index = vec![usize::MAX; n];
+	let mut low_link = vec![0; n];
+	let mut on_stack = vec![false; n];
+	let mut stack = Vec::new();
+	let mut components = Vec::new();
+	let mut next_index = 0;
+	for root in 0..n {
+		if index[root] != usize::MAX {
+			continue;
+		}
+		// Each frame is a state and the position of the next successor to visit
+		let mut frames = vec![(root, 0)];
+		index[root] = next_index;
+		low_link[root] = next_index;
+		next_index += 1;
+		stack.push(root);
+		on_stack[root] = true;
+		while let Some((state, position)) = frames.last_mut() {
+			let state = *state;
+			if let Some(successor) = matrix.row_columns(state).get(*position) {
+				*position += 1;
+				let successor = *successor;
+				if index[successor] == usize::MAX {
+					index[successor] = next_index;
+					low_link[successor] = next_index;
+					next_index += 1;
+					stack.push(successor);
+					on_stack[successor] = true;
+					frames.push((successor, 0));
+				} else if on_stack[successor] {
+					low_link[state] = low_link[state].min(index[successor]);
+				}
+				continue;
+			}
+			frames.pop();
+			if let Some((parent, _)) = frames.last() {
+				low_link[*parent] = low_link[*parent].min(low_link[state]);
+			}
+			if low_link[state] == index[state] {
+				let mut component = Vec::new();
+				while let Some(member) = stack.pop() {
+					on_stack[member] = false;
+					component.push(member);
+					if member == state {
+						break;
+					}
+				}
+				components.push(component);
+			}
+		}
+	}
+	components
+}
+
+/// The bottom strongly connected components, which no transition leaves
+fn bottom_components(matrix: &SparseMatrix) -> Vec<Vec<usize>> {
+	let mut component_of = vec![0; matrix.state_count()];
+	let components = strongly_connected_components(matrix);
+	for (id, component) in components.iter().enumerate() {
+		for &state in component.iter() {
+			component_of[state] = id;
+		}
+	}
+	components
+		.into_iter()
+		.enumerate()
+		.filter(|(id, component)| {
+			component.iter().all(|&state| {
+				matrix
+					.row_columns(state)
+					.iter()
+					.all(|to| component_of[*to] == *id)
+			})
+		})
+		.map(|(_, component)| component)
+		.collect()
+}
+
+/// The stationary distribution of a bottom component, in the order of its states,
+/// by Gauss–Seidel iteration on the balance equations: the probability flowing
+/// out of each state equals the probability flowing into it
+fn stationary_distribution(matrix: &SparseMatrix, component: &[usize]) -> Vec<f64> {
+	if component.len() == 1 {
+		return vec![1.0];
+	}
+	let positions: HashMap<usize, usize> = component
+		.iter()
+		.enumerate()
+		.map(|(position, state)| (*state, position))
+		.collect();
+	// The (from, rate) transitions into each state of the component
+	let mut incoming = vec![Vec::new(); component.len()];
+	for (from, &state) in component.iter().enumerate() {
+		for (to, rate) in matrix.off_diagonal_row(state) {
+			if let Some(to) = positions.get(&to) {
+				incoming[*to].push((from, rate));
+			}
+		}
+	}
+	let mut distribution = vec![1.0 / component.len() as f64; component.len()];
+	for iteration in 0..MAX_ITERATIONS {
+		let mut max_change: f64 = 0.0;
+		for (i, &state) in component.iter().enumerate() {
+			let value = incoming[i]
+				.iter()
+				.map(|(from, rate)| distribution[*from] * rate)
+				.sum::<f64>()
+				/ matrix.exit_rates()[state];
+			max_change = max_change.max((value - distribution[i]).abs());
+			distribution[i] = value;
+		}
+		let total: f64 = distribution.iter().sum();
+		distribution.iter_mut().for_each(|p| *p /= total);
+		if max_change < TOLERANCE {
+			debug_message!(
+				"Stationary distribution of {} states converged after {} iterations",
+				component.len(),
+				iteration + 1
+			);
+			return distribution;
+		}
+	}
+	warning!(
+		"The stationary distribution of {} states did not converge within {} iterations",
+		component.len(),
+		MAX_ITERATIONS
+	);
+	distribution
+}
+
+/// The expected number of visits to each state outside the bottom components,
+/// `v = initial + Q^T v` over the embedded DTMC `Q` restricted to those states,
+/// by Gauss–Seidel iteration. States in a bottom component get no visits.
+fn expected_visits(embedded: &SparseMatrix, in_bottom: &[bool], initial: &[f64]) -> Vec<f64> {
+	let transient: Vec<usize> = (0..embedded.state_count())
+		.filter(|s| !in_bottom[*s])
+		.collect();
+	// The (from, probability) transitions into each transient state from the others
+	let mut incoming = vec![Vec::new(); embedded.state_count()];
+	for &from in transient.iter() {
+		for (to, probability) in embedded.row(from) {
+			if !in_bottom[to] {
+				incoming[to].push((from, probability));
+			}
+		}
+	}
+	let mut visits = vec![0.0; embedded.state_count()];
+	for iteration in 0..MAX_ITERATIONS {
+		let mut max_change: f64 = 0.0;
+		for &state in transient.iter() {
+			let value = initial[state]
+				+ incoming[state]
+					.iter()
+					.map(|(from, probability)| visits[*from] * probability)
+					.sum::<f64>();
+			// States may be visited many times, so the change is relative
+			max_change = max_change.max((value - visits[state]).abs() / value.max(1.0));
+			visits[state] = value;
+		}
+		if max_change < TOLERANCE {
+			debug_message!(
+				"Expected visits to {} transient states converged after {} iterations",
+				transient.len(),
+				iteration + 1
+			);
+			return visits;
+		}
+	}
+	warning!(
+		"The expected visits to {} transient states did not converge within {} iterations",
+		transient.len(),
+		MAX_ITERATIONS
+	);
+	visits
+}
+
+/// The probability of ending up in each bottom component, all in one pass: the
+/// initial probability already in the component, plus the probability flowing
+/// into it from the expected visits to the other states (see `expected_visits`)
+fn absorption_probabilities(
+	matrix: &SparseMatrix,
+	components: &[Vec<usize>],
+	initial: &[f64],
+) -> Vec<f64> {
+	let mut component_of = vec![None; matrix.state_count()];
+	for (id, component) in components.iter().enumerate() {
+		component
+			.iter()
+			.for_each(|state| component_of[*state] = Some(id));
+	}
+	let in_bottom: Vec<bool> = component_of.iter().map(|id| id.is_some()).collect();
+	let embedded = matrix.embedded();
+	let visits = expected_visits(&embedded, &in_bottom, initial);
+	let mut reached = vec![0.0; components.len()];
+	for (from, id) in component_of.iter().enumerate() {
+		match id {
+			Some(id) => reached[*id] += initial[from],
+			None => {
+				for (to, probability) in embedded.row(from) {
+					if let Some(id) = component_of[to] {
+						reached[id] += visits[from] * probability;
+					}
+				}
+			}
+		}
+	}
+	reached
+}
+
+/// Checks the long-run probability of being in a state where `formula` holds, i.e.,
+/// `S=? [ formula ]`. Each bottom component of the model is weighed by the
+/// probability of reaching it. The absorbing state stands for the unexplored states,
+/// where the formula may or may not hold, so the probability of reaching it is the
+/// gap between Pmin and Pmax.
+pub(crate) fn check_steady_state(
+	model: &PrismVasModel,
+	formula: &StateFormula,
+) -> ModelCheckingResult {
+	let matrix = model.matrix();
+	let mut initial = vec![0.0; model.states.len()];
+	for (id, probability) in model.initial_distribution.iter() {
+		initial[*id] += probability;
+	}
+	let holds = satisfying_states(model, formula);
+	let absorbing_label = StateFormula::label("absorbing");
+	let components = bottom_components(matrix);
+	debug_message!("Found {} bottom components", components.len());
+	let absorption = absorption_probabilities(matrix, &components, &initial);
+	let mut pmin = 0.0;
+	let mut unexplored = 0.0;
+	for (component, &reached) in components.iter().zip(absorption.iter()) {
+		if reached == 0.0 {
+			continue;
+		}
+		if component
+			.iter()
+			.any(|state| model.states[*state].has_label(&absorbing_label))
+		{
+			unexplored += reached;
+			continue;
+		}
+		let distribution = stationary_distribution(matrix, component);
+		pmin += reached
+			* component
+				.iter()
+				.zip(distribution.iter())
+				.filter(|(state, _)| holds[**state])
+				.map(|(_, p)| p)
+				.sum::<f64>();
+	}
+	let pmin = f64::clamp(pmin, 0.0, 1.0);
+	ModelCheckingResult::ProbabilityRange(pmin, (pmin + unexplored).clamp(pmin, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeSet;
+
+	use nalgebra::DVector;
+
+	use super::check_steady_state;
+	use crate::{
+		checker::result::ModelCheckingResult,
+		model::vas_model::{PrismVasModel, PrismVasState, PrismVasTransition},
+		parser::csl_parser::parse_properties,
+		property::property::PropertyQuery,
+	};
+
+	/// A CTMC over the variable `X` that starts in the first of the states with the
+	/// given values of `X`. State 0 is the absorbing state, and the states with
+	/// values are numbered from 1.
+	fn model(values: &[i128], transitions: &[(usize, usize, f64)]) -> PrismVasModel {
+		let mut model = PrismVasModel::new();
+		model.variable_names = vec!["X".to_string()];
+		for value in values {
+			model.add_state(PrismVasState {
+				state_id: model.states.len(),
+				vector: Some(DVector::from_vec(vec![*value])),
+				labels: BTreeSet::new(),
+				used_rate: 0.0,
+				total_outgoing_rate: 0.0,
+			});
+		}
+		for &(from, _, rate) in transitions {
+			model.states[from].total_outgoing_rate += rate;
+		}
+		for &(from_state, to_state, rate) in transitions {
+			model.add_transition(PrismVasTransition {
+				transition_id: None,
+				from_state,
+				to_state,
+				rate,
+			});
+		}
+		model.add_initial_probability(1, 1.0);
+		model
+	}
+
+	/// The (Pmin, Pmax) of `S=? [ formula ]`
+	fn check(model: &PrismVasModel, formula: &str) -> (f64, f64) {
+		let properties = parse_properties(&format!("S=? [ {} ]", formula)).unwrap();
+		let PropertyQuery::SteadyState(formula) = properties.query().unwrap() else {
+			panic!("Expected a steady-state query");
+		};
+		match check_steady_state(model, formula) {
+			ModelCheckingResult::ProbabilityRange(pmin, pmax) => (pmin, pmax),
+			result => panic!("Unexpected result {}", result),
+		}
+	}
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!(
+			(actual - expected).abs() < 1e-9,
+			"{} != {}",
+			actual,
+			expected
+		);
+	}
+
+	/// From the transient state X = 0, which may loop through X = 4, the chain
+	/// ends up in the cycle between X = 1 and X = 3 with probability 1/4, where it
+	/// spends 2/3 of its time in X = 1, or else in the sink X = 2
+	const TRANSITIONS: [(usize, usize, f64); 6] = [
+		(1, 2, 1.0),
+		(1, 3, 3.0),
+		(1, 5, 2.0),
+		(5, 1, 1.0),
+		(2, 4, 1.0),
+		(4, 2, 2.0),
+	];
+
+	#[test]
+	fn bottom_components_are_weighed_by_absorption() {
+		let model = model(&[0, 1, 2, 3, 4], &TRANSITIONS);
+		let (pmin, pmax) = check(&model, "X = 1");
+		assert_close(pmin, 1.0 / 6.0);
+		assert_close(pmax, pmin);
+		let (pmin, _) = check(&model, "X >= 2");
+		assert_close(pmin, 0.75 + 0.25 / 3.0);
+		let (pmin, _) = check(&model, "X = 0 | X = 4");
+		assert_close(pmin, 0.0);
+	}
+
+	#[test]
+	fn absorbing_mass_widens_the_range() {
+		// Half of the probability leaves X = 0 for the unexplored states
+		let transitions: Vec<_> = TRANSITIONS.iter().copied().chain([(1, 0, 4.0)]).collect();
+		let model = model(&[0, 1, 2, 3, 4], &transitions);
+		let (pmin, pmax) = check(&model, "X = 1");
+		assert_close(pmin, 1.0 / 12.0);
+		assert_close(pmax, 1.0 / 12.0 + 0.5);
+	}
+}
@@ -0,0 +1,151 @@
+use crate::{
+	logging::messages::*, model::sparse_matrix::SparseMatrix, util::poisson::poisson_weights,
+};
+
+/// The Poisson mass left out of the time-bounded sum, and the change in the
+/// distribution at which it is considered steady
+const EPSILON: f64 = 1e-12;
+
+/// One step of the uniformized chain from `distribution`: every state is left
+/// with probability `exit rate / q` and otherwise kept
+fn uniformized_step(matrix: &SparseMatrix, distribution: &[f64], q: f64) -> Vec<f64> {
+	let mut next: Vec<f64> = distribution
+		.iter()
+		.zip(matrix.exit_rates().iter())
+		.map(|(p, exit_rate)| p * (1.0 - exit_rate / q))
+		.collect();
+	for (from, p) in distribution.iter().enumerate() {
+		if *p == 0.0 {
+			continue;
+		}
+		for (to, rate) in matrix.off_diagonal_row(from) {
+			next[to] += p * rate / q;
+		}
+	}
+	next
+}
+
+/// The largest difference between two distributions, used to detect convergence
+fn max_difference(a: &[f64], b: &[f64]) -> f64 {
+	a.iter()
+		.zip(b.iter())
+		.map(|(x, y)| (x - y).abs())
+		.fold(0.0, f64::max)
+}
+
+/// The distribution at the given time, by uniformization with Fox–Glynn weights:
+/// the sum over `k` of the probability of `k` jumps of rate `q` by `time` times
+/// the distribution after `k` steps of the uniformized chain
+pub(crate) fn transient_distribution(
+	matrix: &SparseMatrix,
+	initial: Vec<f64>,
+	time: f64,
+) -> Vec<f64> {
+	let q = matrix.exit_rates().iter().cloned().fold(0.0, f64::max);
+	if q == 0.0 || time <= 0.0 {
+		return initial;
+	}
+	let poisson = poisson_weights(q * time, EPSILON);
+	debug_message!(
+		"Uniformization rate {}, Fox-Glynn window [{}, {}]",
+		q,
+		poisson.left,
+		poisson.right()
+	);
+	let mut distribution = initial;
+	let mut result = vec![0.0; distribution.len()];
+	for k in 0..=poisson.right() {
+		let weight = poisson.weight(k);
+		result
+			.iter_mut()
+			.zip(distribution.iter())
+			.for_each(|(r, p)| *r += weight * p);
+		let next = uniformized_step(matrix, &distribution, q);
+		// Once the distribution is steady, the rest of the weights all apply to it
+		if max_difference(&next, &distribution) < EPSILON {
+			let remaining: f64 = (k + 1..=poisson.right()).map(|k| poisson.weight(k)).sum();
+			result
+				.iter_mut()
+				.zip(next.iter())
+				.for_each(|(r, p)| *r += remaining * p);
+			break;
+		}
+		distribution = next;
+	}
+	result
+}
+
+/// The distribution after the given number of steps of a DTMC
+pub(crate) fn step_bounded_distribution(
+	matrix: &SparseMatrix,
+	initial: Vec<f64>,
+	steps: usize,
+) -> Vec<f64> {
+	let mut distribution = initial;
+	for _ in 0..steps {
+		let next = uniformized_step(matrix, &distribution, 1.0);
+		if max_difference(&next, &distribution) < EPSILON {
+			return next;
+		}
+		distribution = next;
+	}
+	distribution
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{step_bounded_distribution, transient_distribution};
+	use crate::model::sparse_matrix::SparseMatrix;
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!(
+			(actual - expected).abs() < 1e-9,
+			"{} != {}",
+			actual,
+			expected
+		);
+	}
+
+	#[test]
+	fn transient_distribution_of_a_decay() {
+		let rate: f64 = 1.5;
+		let matrix = SparseMatrix::from_entries(2, [(0, 1, rate)]);
+		for time in [0.0, 0.2, 1.0, 10.0] {
+			let distribution = transient_distribution(&matrix, vec![1.0, 0.0], time);
+			assert_close(distribution[0], (-rate * time).exp());
+			assert_close(distribution[1], 1.0 - (-rate * time).exp());
+		}
+	}
+
+	#[test]
+	fn transient_distribution_of_a_two_state_cycle() {
+		// With rates a (0 to 1) and b (1 to 0), p0(t) = b/(a+b) + a/(a+b) e^-(a+b)t
+		let (a, b): (f64, f64) = (2.0, 0.5);
+		// The self-loop does not change the chain
+		let matrix = SparseMatrix::from_entries(2, [(0, 1, a), (1, 0, b), (0, 0, 7.0)]);
+		for time in [0.3, 1.0, 50.0] {
+			let distribution = transient_distribution(&matrix, vec![1.0, 0.0], time);
+			let p0 = b / (a + b) + a / (a + b) * (-(a + b) * time).exp();
+			assert_close(distribution[0], p0);
+			assert_close(distribution[0] + distribution[1], 1.0);
+		}
+	}
+
+	#[test]
+	fn step_bounded_distribution_of_a_dtmc() {
+		// From 0, move to 1 or 2 with probability 1/2 each; 1 moves to 2
+		let matrix = SparseMatrix::from_entries(3, [(0, 1, 0.5), (0, 2, 0.5), (1, 2, 1.0)]);
+		let expected = [
+			[1.0, 0.0, 0.0],
+			[0.0, 0.5, 0.5],
+			[0.0, 0.0, 1.0],
+			[0.0, 0.0, 1.0],
+		];
+		for (steps, expected) in expected.iter().enumerate() {
+			let distribution = step_bounded_distribution(&matrix, vec![1.0, 0.0, 0.0], steps);
+			for (actual, expected) in distribution.iter().zip(expected.iter()) {
+				assert_close(*actual, *expected);
+			}
+		}
+	}
+}
@@ -1,10 +1,16 @@
+use std::{fs, path::Path};
+
 use itertools::Itertools;
 
 use crate::{
+	builder::ragtimer::ragtimer::RagtimerBuilder,
+	checker::checker::check_explicit_model,
 	model::vas_model::{
 		AbstractVas, PrismVasModel, PrismVasState, PrismVasTransition, VasTransition,
 	},
+	parser::{explicit_file_reader::read_explicit_prism_files, parser::ModelOptions},
 	trace::trace_trie::TraceTrieNode,
+	validator::vas_validator::check_explicit_probabilities,
 	*,
 };
 
@@ -47,32 +53,40 @@ pub fn cycle_commute(
 		match current_node {
 			TraceTrieNode::LeafNode => {
 				let mut prism_trace: Vec<PrismVasTransition> = Vec::new();
-				let mut current_state = abstract_model.initial_states[0].vector.clone();
-				let mut current_state_id = 1;
-				for &transition_id in current_trace.iter() {
+				// Stored traces begin with the ID of the initial state they start in
+				let mut current_state_id = current_trace[0];
+				let Some(mut current_state) =
+					explicit_model.states[current_state_id].vector.clone()
+				else {
+					continue;
+				};
+				for &transition_id in current_trace[1..].iter() {
 					if let Some(transition) = abstract_model.get_transition_from_id(transition_id) {
 						let next_state =
 							(current_state.clone() + transition.update_vector.clone()).clone();
-						let mut next_state_id = explicit_model.states.len();
-						if let Some(existing_id) = explicit_model
-							.state_trie
-							.insert_if_not_exists(&next_state, next_state_id)
-						{
-							next_state_id = existing_id;
-						} else {
-							error!(
-								"Error: New state with vector {:?} (next_state_id: {}) should have already been added to the explicit model at this phase. Current state_id: {}, current_state: {:?}, transition: {} ({})",
-								next_state,
-								next_state_id,
-								current_state_id,
-								current_state,
-								transition_id,
-								abstract_model.get_transition_from_id(transition_id).unwrap().transition_name,
-							);
-						}
-						let prism_transition = explicit_model.transitions.iter().find(|t| {
-							t.from_state == current_state_id && t.to_state == next_state_id
-						});
+						let next_state_id = match explicit_model.state_id(&next_state) {
+							Some(existing_id) => existing_id,
+							None => {
+								error!(
+									"Error: New state with vector {:?} should have already been added to the explicit model at this phase. Current state_id: {}, current_state: {:?}, transition: {} ({})",
+									next_state,
+									current_state_id,
+									current_state,
+									transition_id,
+									transition.transition_name,
+								);
+								break;
+							}
+						};
+						let prism_transition = explicit_model
+							.transition_map
+							.get(&current_state_id)
+							.and_then(|to_states| {
+								to_states
+									.iter()
+									.find(|(to_state, _)| *to_state == next_state_id)
+							})
+							.map(|(_, index)| &explicit_model.transitions[*index]);
 						if let Some(prism_transition) = prism_transition {
 							prism_trace.push(prism_transition.clone());
 						} else {
@@ -154,7 +168,12 @@ fn commute(
 	}
 	// Get universally enabled transitions
 	// Clone the state vector to avoid holding an immutable borrow during mutation
-	let initial_state_vector = abstract_model.initial_states[0].vector.clone();
+	let Some(initial_state_id) = trace.first().map(|t| t.from_state) else {
+		return;
+	};
+	let Some(initial_state_vector) = explicit_model.states[initial_state_id].vector.clone() else {
+		return;
+	};
 	let mut current_state = initial_state_vector.clone(); // Start from the initial state
 													   // To do: maybe make this a hash set instead for faster lookups?
 	let mut enabled_transitions: Vec<&VasTransition> = abstract_model
@@ -191,10 +210,17 @@ fn commute(
 	parallel_traces.extend(universally_enabled_transitions.iter().map(|_| Vec::new()));
 	for (_, trace_transition) in trace.iter().enumerate() {
 		let state_id = trace_transition.from_state;
-		let state_vector = explicit_model.states[state_id].vector.clone();
-		let abstract_trace_transition = abstract_model
-			.get_transition_from_id(trace_transition.transition_id)
-			.unwrap();
+		// Sinks without a vector and transitions that do not come from the abstract model
+		// cannot be commuted
+		let Some(state_vector) = explicit_model.states[state_id].vector.clone() else {
+			continue;
+		};
+		let Some(abstract_trace_transition) = trace_transition
+			.transition_id
+			.and_then(|transition_id| abstract_model.get_transition_from_id(transition_id))
+		else {
+			continue;
+		};
 		for (commutable_index, commutable_transition) in
 			universally_enabled_transitions.iter().enumerate()
 		{
@@ -216,33 +242,20 @@ fn commute(
 				continue;
 			}
 			// Fire the commutable transition first
-			let mut vertical_state_id = explicit_model.states.len();
-			if let Some(existing_id) = explicit_model
-				.state_trie
-				.insert_if_not_exists(&vertical_state, vertical_state_id)
-			{
-				vertical_state_id = existing_id;
-				// debug_message!(
-				// 	"State {:?} already exists with ID {}",
-				// 	vertical_state.iter().collect::<Vec<_>>(),
-				// 	vertical_state_id
-				// );
-			} else {
-				// Create a new state
-				explicit_model.add_state(PrismVasState {
-					state_id: vertical_state_id,
-					vector: vertical_state.clone(),
-					label: None,
-					used_rate: 0.0,
-					total_outgoing_rate: abstract_model.crn_total_outgoing_rate(&vertical_state),
-				});
-				// debug_message!(
-				// 	"Added new state ID {} with vector {:?}",
-				// 	vertical_state_id,
-				// 	vertical_state.iter().collect::<Vec<_>>()
-				// );
-				*num_states_added += 1;
-			}
+			let vertical_state_id = match explicit_model.state_id(&vertical_state) {
+				Some(existing_id) => existing_id,
+				None => {
+					// Create a new state
+					*num_states_added += 1;
+					explicit_model.add_state(PrismVasState {
+						state_id: explicit_model.states.len(),
+						vector: Some(vertical_state.clone()),
+						labels: abstract_model.state_labels(&vertical_state),
+						used_rate: 0.0,
+						total_outgoing_rate: abstract_model.total_outgoing_rate(&vertical_state),
+					})
+				}
+			};
 			// Check if the transition already exists
 			let transition_exists =
 				explicit_model
@@ -256,10 +269,10 @@ fn commute(
 			if !transition_exists {
 				// Create the new transition
 				let new_transition = PrismVasTransition {
-					transition_id: commutable_transition.transition_id,
+					transition_id: Some(commutable_transition.transition_id),
 					from_state: state_id,
 					to_state: vertical_state_id,
-					rate: commutable_transition.get_sck_rate(&state_vector),
+					rate: abstract_model.transition_rate(&state_vector, commutable_transition),
 				};
 				explicit_model.add_transition(new_transition);
 			}
@@ -275,30 +288,28 @@ fn commute(
 			if horizontal_state.iter().any(|&x| x < 0) {
 				continue;
 			}
-			let mut horizontal_state_id = explicit_model.states.len();
-			if let Some(existing_id) = explicit_model
-				.state_trie
-				.insert_if_not_exists(&horizontal_state, horizontal_state_id)
-			{
-				horizontal_state_id = existing_id;
-			} else {
-				// Create a new state
-				explicit_model.add_state(PrismVasState {
-					state_id: horizontal_state_id,
-					vector: horizontal_state.clone(),
-					label: None,
-					used_rate: 0.0,
-					total_outgoing_rate: abstract_model.crn_total_outgoing_rate(&horizontal_state),
-				});
-				*num_states_added += 1;
-				if *num_states_added % 1000 == 0 {
-					debug_message!(
-						"C&C added {} states so far\t(total {} states)",
-						num_states_added,
-						explicit_model.states.len()
-					);
-				}
-			}
+			let horizontal_state_id =
+				if let Some(existing_id) = explicit_model.state_id(&horizontal_state) {
+					existing_id
+				} else {
+					// Create a new state
+					let horizontal_state_id = explicit_model.add_state(PrismVasState {
+						state_id: explicit_model.states.len(),
+						vector: Some(horizontal_state.clone()),
+						labels: abstract_model.state_labels(&horizontal_state),
+						used_rate: 0.0,
+						total_outgoing_rate: abstract_model.total_outgoing_rate(&horizontal_state),
+					});
+					*num_states_added += 1;
+					if *num_states_added % 1000 == 0 {
+						debug_message!(
+							"C&C added {} states so far\t(total {} states)",
+							num_states_added,
+							explicit_model.states.len()
+						);
+					}
+					horizontal_state_id
+				};
 			// Check if the transition already exists
 			let transition_exists = explicit_model
 				.transition_map
@@ -309,10 +320,10 @@ fn commute(
 						.any(|(to_state, _)| *to_state == horizontal_state_id)
 				});
 			let horizontal_new_transition = PrismVasTransition {
-				transition_id: abstract_trace_transition.transition_id,
+				transition_id: Some(abstract_trace_transition.transition_id),
 				from_state: vertical_state_id,
 				to_state: horizontal_state_id,
-				rate: abstract_trace_transition.get_sck_rate(&vertical_state),
+				rate: abstract_model.transition_rate(&vertical_state, abstract_trace_transition),
 			};
 			if !transition_exists {
 				// Create the new transition
@@ -375,7 +386,9 @@ fn add_cycles(
 				// Add the cycle to all states where it is enabled (i.e., where the current state + min_vector is non-negative)
 				// Right now, 1 is the index of the first real initial state. 0 is the absorbing state.
 				for state_id in 1..explicit_model.states.len() {
-					let state_vector = explicit_model.states[state_id].vector.clone();
+					let Some(state_vector) = explicit_model.states[state_id].vector.clone() else {
+						continue;
+					};
 					// Check if the cycle is enabled at this state (state_vector + min_vector >= 0)
 					// For each permutation of the cycle, try to fire the transitions in order
 					for perm in &cycle_permutations {
@@ -407,24 +420,21 @@ fn add_cycles(
 							let next_state =
 								current_state.clone() + transition.update_vector.clone();
 							// Insert or get the state ID
-							let mut next_state_id = explicit_model.states.len();
-							if let Some(existing_id) = explicit_model
-								.state_trie
-								.insert_if_not_exists(&next_state, next_state_id)
-							{
-								next_state_id = existing_id;
-							} else {
-								// Compute total outgoing rate for the new state
-								explicit_model.add_state(PrismVasState {
-									state_id: next_state_id,
-									vector: next_state.clone(),
-									label: None,
-									used_rate: 0.0,
-									total_outgoing_rate: abstract_model
-										.crn_total_outgoing_rate(&next_state),
-								});
-								*num_states_added += 1;
-							}
+							let next_state_id = match explicit_model.state_id(&next_state) {
+								Some(existing_id) => existing_id,
+								None => {
+									// Compute total outgoing rate for the new state
+									*num_states_added += 1;
+									explicit_model.add_state(PrismVasState {
+										state_id: explicit_model.states.len(),
+										vector: Some(next_state.clone()),
+										labels: abstract_model.state_labels(&next_state),
+										used_rate: 0.0,
+										total_outgoing_rate: abstract_model
+											.total_outgoing_rate(&next_state),
+									})
+								}
+							};
 							// Add transition if not already present
 							let transition_exists = explicit_model
 								.transition_map
@@ -437,10 +447,11 @@ fn add_cycles(
 							if !transition_exists {
 								// Create the new transition
 								let new_transition = PrismVasTransition {
-									transition_id: explicit_model.transitions.len(),
+									transition_id: Some(transition.transition_id),
 									from_state: current_state_id,
 									to_state: next_state_id,
-									rate: transition.get_sck_rate(&current_state),
+									rate: abstract_model
+										.transition_rate(&current_state, transition),
 								};
 								explicit_model.add_transition(new_transition);
 							}
@@ -458,3 +469,125 @@ fn add_cycles(
 		}
 	}
 }
+
+/// Builds an explicit state space from the given traces and expands it with Cycle & Commute.
+/// `traces` is either a file with one trace per line or a single trace, where a trace is a
+/// tab-separated list of transition names starting in the first initial state of the model.
+#[allow(clippy::too_many_arguments)]
+pub fn cycle_commute_from_traces(
+	model_file: &str,
+	options: &ModelOptions,
+	traces: &str,
+	max_commute_depth: usize,
+	max_cycle_length: usize,
+	output: &str,
+	check: bool,
+	target_sink: bool,
+) {
+	let Ok(mut abstract_model) = AbstractVas::from_file(model_file, options) else {
+		error!("Failed to parse model file: {}", model_file);
+		return;
+	};
+	let traces: Vec<String> = if Path::new(traces).is_file() {
+		match fs::read_to_string(traces) {
+			Ok(contents) => contents.lines().map(|line| line.to_string()).collect(),
+			Err(e) => {
+				error!("Could not read trace file {}: {}", traces, e);
+				return;
+			}
+		}
+	} else {
+		vec![traces.to_string()]
+	};
+	let mut explicit_model = PrismVasModel::from_abstract_model(&abstract_model);
+	let mut builder = RagtimerBuilder::new(&abstract_model, None);
+	builder.set_model_query(model_file, options);
+	let initial_ids = builder.add_initial_states(&mut explicit_model);
+	let initial_state = abstract_model.initial_states[0].vector.clone();
+	let mut trace_trie = TraceTrieNode::new();
+	for line in traces.iter().filter(|line| !line.trim().is_empty()) {
+		let mut trace = Vec::new();
+		for name in line.split('\t').map(|name| name.trim()) {
+			match abstract_model.get_transition_from_name(name) {
+				Some(transition) => trace.push(transition.transition_id),
+				None => {
+					error!("Unknown transition '{}' in trace: {}", name, line);
+					return;
+				}
+			}
+		}
+		builder.store_explicit_trace(&mut explicit_model, &initial_state, &trace);
+		trace_trie.exists_or_insert(&[vec![initial_ids[0]], trace].concat());
+	}
+	message!("Read {} traces", builder.traces_complete);
+	explicit_model.trace_trie = trace_trie;
+	cycle_commute(
+		&mut abstract_model,
+		&mut explicit_model,
+		max_commute_depth,
+		max_cycle_length,
+	);
+	explicit_model.add_absorbing_transitions();
+	for error in check_explicit_probabilities(&explicit_model) {
+		warning!("{}", error);
+	}
+	if target_sink {
+		explicit_model = explicit_model.with_target_sink();
+	}
+	explicit_model.print_explicit_prism_files(output);
+	message!(
+		"Your explicit model has been built with {} states and {} transitions.",
+		explicit_model.states.len(),
+		explicit_model.transitions.len()
+	);
+	if check {
+		check_explicit_model(&explicit_model, model_file, options);
+	}
+}
+
+/// Reads an explicit state space written as `<explicit_files>.tra,sta,lab`, e.g., by a
+/// previous run, and expands it with cycles. Commuting needs the traces the state space
+/// was built from, which the files do not keep.
+pub fn cycle_commute_from_explicit(
+	model_file: &str,
+	options: &ModelOptions,
+	explicit_files: &str,
+	max_cycle_length: usize,
+	output: &str,
+	check: bool,
+	target_sink: bool,
+) {
+	let Ok(mut abstract_model) = AbstractVas::from_file(model_file, options) else {
+		error!("Failed to parse model file: {}", model_file);
+		return;
+	};
+	let mut explicit_model = match read_explicit_prism_files(&abstract_model, explicit_files) {
+		Ok(model) => model,
+		Err(e) => {
+			error!("{}", e);
+			return;
+		}
+	};
+	cycle_commute(
+		&mut abstract_model,
+		&mut explicit_model,
+		0,
+		max_cycle_length,
+	);
+	explicit_model.add_absorbing_transitions();
+	for error in check_explicit_probabilities(&explicit_model) {
+		warning!("{}", error);
+	}
+	if target_sink {
+		explicit_model = explicit_model.with_target_sink();
+	}
+	explicit_model.print_explicit_prism_files(output);
+	message!(
+		"Your explicit model has been built with {} states and {} transitions.",
+		explicit_model.states.len(),
+		explicit_model.transitions.len()
+	);
+	if check {
+		check_explicit_model(&explicit_model, model_file, options);
+	}
+}
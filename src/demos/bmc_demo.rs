@@ -2,6 +2,7 @@ use crate::bmc::vas_bmc::AbstractVasBmc;
 use crate::dependency;
 use crate::dependency::graph::make_dependency_graph;
 use crate::model::vas_model::AbstractVas;
+use crate::parser::parser::ModelOptions;
 use crate::*;
 
 use std::fs;
@@ -52,7 +53,8 @@ pub fn bmc_demo(crn_model_directory: &Path, bits: u32, max_steps: u32, backward:
 		// Parse each model file
 		message!("Model: {}", m);
 		let model_path = crn_model_directory.join(&m);
-		let parsed_model = AbstractVas::from_file(model_path.to_str().unwrap());
+		let parsed_model =
+			AbstractVas::from_file(model_path.to_str().unwrap(), &ModelOptions::default());
 		if parsed_model.is_ok() {
 			let model = parsed_model.unwrap();
 			message!("Finished parsing model: {}", m);
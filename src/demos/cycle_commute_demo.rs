@@ -2,6 +2,7 @@ use crate::builder::builder::Builder;
 use crate::builder::ragtimer::ragtimer::RagtimerBuilder;
 use crate::cycle_commute::commute::cycle_commute;
 use crate::model::vas_model::{AbstractVas, PrismVasModel};
+use crate::parser::parser::ModelOptions;
 use crate::*;
 
 /// This function runs the cycle commute demo for a given model and trace file.
@@ -16,7 +17,7 @@ pub fn cycle_commute_demo(
 	max_commute_depth: usize,
 	max_cycle_length: usize,
 ) {
-	if let Ok(mut abstract_model) = AbstractVas::from_file(model_file) {
+	if let Ok(mut abstract_model) = AbstractVas::from_file(model_file, &ModelOptions::default()) {
 		debug_message!("Model Parsed");
 		let mut explicit_model = PrismVasModel::from_abstract_model(&abstract_model);
 		debug_message!("Explicit Model Built");
@@ -2,7 +2,10 @@ use nalgebra::DVector;
 
 use crate::{
 	logging::messages::*,
-	model::vas_model::{self, AbstractVas, VasProperty, VasState, VasTransition, VasValue},
+	model::vas_model::{
+		self, AbstractVas, AllowedRelation, RateConvention, VasProperty, VasState, VasTransition,
+		VasValue,
+	},
 };
 
 /// Temporary constants for debugging.
@@ -22,10 +25,10 @@ pub struct GraphNode {
 	upstream_targets: Vec<VasProperty>,
 }
 
-/// A dependency graph containing only a root node.
+/// A dependency graph containing one root node per atomic target.
 #[derive(Clone)]
 pub(crate) struct DependencyGraph {
-	root: Box<GraphNode>,
+	roots: Vec<Box<GraphNode>>,
 }
 
 /// This trait provides methods for building and manipulating the dependency graph.
@@ -49,16 +52,18 @@ impl GraphNode {
 		let mut child_init = VasState::new(
 			&self.node_init.vector + (&self.transition.update_vector * self.executions),
 		);
-		// Compute the adjustment vector: if update_vector[i] + enabled_bounds[i] != 0, subtract enabled_bounds[i] from child_init.vector[i]
+		// Compute the adjustment vector: species that this transition produces must also be
+		// present in the amount it consumes, so for catalysts (consumed and produced, with no
+		// net update) the consumed copies are subtracted from child_init.vector[i]
 		let adjustment = self
 			.transition
-			.update_vector
+			.produce_vector
 			.iter()
-			.zip(self.transition.enabled_bounds.iter())
+			.zip(self.transition.consume_vector.iter())
 			.map(
-				|(update, bound)| {
-					if *update + *bound != 0 {
-						-(*bound)
+				|(produced, consumed)| {
+					if *produced != 0 {
+						-(*consumed)
 					} else {
 						0
 					}
@@ -90,7 +95,9 @@ impl GraphNode {
 				if reqd != 0 {
 					Some(VasProperty {
 						variable_index: prop.variable_index,
+						relation: AllowedRelation::Equal,
 						target_value: reqd,
+						linear_terms: Vec::new(),
 					})
 				} else {
 					None
@@ -103,7 +110,9 @@ impl GraphNode {
 			if child_init.vector[i] < 0 {
 				negative_targets.push(VasProperty {
 					variable_index: i,
+					relation: AllowedRelation::Equal,
 					target_value: -child_init.vector[i],
+					linear_terms: Vec::new(),
 				});
 			}
 		}
@@ -133,7 +142,9 @@ impl GraphNode {
 					{
 						this_child_targets.push(VasProperty {
 							variable_index: target.variable_index,
+							relation: AllowedRelation::Equal,
 							target_value: target.target_value,
+							linear_terms: Vec::new(),
 						});
 						executions = (target.target_value
 							/ trans.update_vector[target.variable_index])
@@ -214,38 +225,24 @@ impl GraphNode {
 
 /// Checks if a given property is satisfied in the current state.
 fn property_sat(prop: &VasProperty, state: &VasState) -> Result<bool, String> {
-	if state.vector.len() < prop.variable_index {
+	if state.vector.len() <= prop.variable_index {
 		return Err(format!(
 			"Error: Index out of bounds for state vector: {} >= {}",
 			prop.variable_index,
 			state.vector.len()
 		));
 	}
-	if state.vector[prop.variable_index] == prop.target_value {
-		return Ok(true);
-	}
-	return Ok(false);
+	Ok(prop.is_satisfied(&state.vector))
 }
 
-/// Top-level function to create a dependency graph from an abstract VAS model.
-pub fn make_dependency_graph(
-	vas: &vas_model::AbstractVas,
-) -> Result<Option<DependencyGraph>, String> {
-	message!("Building a dependency graph.");
-	// check if target is satisfied in the initial state; if not, build a root node.
-	let initial_state = VasState::new(vas.initial_states[0].vector.clone());
-	let initially_sat = property_sat(&vas.target, &initial_state);
-	if initially_sat == Ok(true) {
-		return Err(String::from("Error: Initial state satisfies the target property. Probability is 1 and this analysis is pointless."));
-	} else if initially_sat.is_err() {
-		return Err(String::from(
-			"Error: Cannot check initial state against target property.",
-		));
-	}
+/// Builds the artificial root node for a single atomic target and
+/// recursively builds the graph beneath it.
+fn build_root(vas: &AbstractVas, target: &VasProperty, initial_state: &VasState) -> Box<GraphNode> {
 	// figure out the executions on the artificial root node
-	let target_variable = vas.target.variable_index;
-	let initial_value = vas.initial_states[0].vector[target_variable];
-	let target_value = vas.target.target_value;
+	let target_variable = target.variable_index;
+	let initial_value = initial_state.vector[target_variable];
+	// The graph is built toward the nearest count that satisfies the target relation
+	let target_value = target.closest_satisfying_value(initial_value);
 	let target_difference = if (initial_value) < target_value {
 		target_value - (initial_value)
 	} else {
@@ -254,40 +251,90 @@ pub fn make_dependency_graph(
 	let decrement = (initial_value) > target_value;
 	// TODO: Handle stoichiometry greater than one.
 	// Build a new root (abstract transition) node
-	let mut dependency_graph = DependencyGraph {
-		root: {
-			Box::new(GraphNode {
-				transition: VasTransition {
-					transition_id: usize::MAX,
-					transition_name: "ARTIFICIAL".to_string(),
-					update_vector: DVector::zeros(vas.variable_names.len()),
-					enabled_bounds: DVector::zeros(vas.variable_names.len()),
-					rate_const: 0.0,
-					custom_rate_fn: None, // make the artificial transition here
-				},
-				children: Vec::new(),
-				parents: Vec::new(),
-				executions: target_difference,
-				enabled: false,
-				node_init: initial_state.clone(),
-				node_target: Vec::from([VasProperty {
-					variable_index: target_variable,
-					target_value: target_difference,
-				}]),
-				decrement,
-				upstream_targets: Vec::new(),
-			})
+	let mut root = Box::new(GraphNode {
+		transition: VasTransition {
+			transition_id: usize::MAX,
+			transition_name: "ARTIFICIAL".to_string(),
+			consume_vector: DVector::zeros(vas.variable_names.len()),
+			produce_vector: DVector::zeros(vas.variable_names.len()),
+			update_vector: DVector::zeros(vas.variable_names.len()),
+			enabled_bounds: DVector::zeros(vas.variable_names.len()),
+			rate_const: 0.0,
+			custom_rate_fn: None, // make the artificial transition here
+			rate_convention: RateConvention::default(),
 		},
-	};
+		children: Vec::new(),
+		parents: Vec::new(),
+		executions: target_difference,
+		enabled: false,
+		node_init: initial_state.clone(),
+		node_target: Vec::from([VasProperty {
+			variable_index: target_variable,
+			relation: AllowedRelation::Equal,
+			target_value: target_difference,
+			linear_terms: Vec::new(),
+		}]),
+		decrement,
+		upstream_targets: Vec::new(),
+	});
 	// handle the case where it is desired to decrease a value to reach a target
-	if dependency_graph.root.decrement {
-		if let Some(first_target) = dependency_graph.root.node_target.first_mut() {
-			first_target.target_value -=
-				dependency_graph.root.node_init.vector[first_target.variable_index];
+	if root.decrement {
+		if let Some(first_target) = root.node_target.first_mut() {
+			first_target.target_value -= root.node_init.vector[first_target.variable_index];
 		}
 	}
 	// Start building the graph from the root node.
-	let _ = dependency_graph.root.rec_build_graph(vas, 1);
+	let _ = root.rec_build_graph(vas, 1);
+	root
+}
+
+/// Top-level function to create a dependency graph from an abstract VAS model.
+/// Compound targets are put in negation normal form and one root is built for
+/// every atom that does not already hold in the initial state. For disjunctions
+/// this over-approximates the transitions needed, which is safe for trimming.
+/// With several initial states, the graph covers the roots of all of them.
+pub fn make_dependency_graph(
+	vas: &vas_model::AbstractVas,
+) -> Result<Option<DependencyGraph>, String> {
+	message!("Building a dependency graph.");
+	// check if target is satisfied in the initial states; if not, build the root nodes.
+	if vas
+		.initial_states
+		.iter()
+		.all(|state| vas.target.is_satisfied(&state.vector))
+	{
+		return Err(String::from("Error: Initial state satisfies the target property. Probability is 1 and this analysis is pointless."));
+	}
+	let normalized_target = vas.target.negation_normal_form();
+	let mut roots = Vec::new();
+	if vas.target.has_linear_atoms() {
+		return Err(format!(
+			"Error: The dependency graph does not support linear-combination targets ({}). Use a single-species target instead.",
+			vas.target.to_string_with_names(&vas.variable_names)
+		));
+	}
+	for state in vas.initial_states.iter() {
+		let initial_state = VasState::new(state.vector.clone());
+		if vas.target.is_satisfied(&initial_state.vector) {
+			debug_message!(
+				"Initial state {:?} satisfies the target, so it needs no roots.",
+				initial_state.vector.iter().collect::<Vec<_>>()
+			);
+			continue;
+		}
+		for atom in normalized_target.atoms() {
+			match property_sat(atom, &initial_state) {
+				Ok(true) => continue,
+				Ok(false) => roots.push(build_root(vas, atom, &initial_state)),
+				Err(_) => {
+					return Err(String::from(
+						"Error: Cannot check initial state against target property.",
+					))
+				}
+			}
+		}
+	}
+	let dependency_graph = DependencyGraph { roots };
 
 	message!("Dependency graph built.");
 
@@ -297,6 +344,11 @@ pub fn make_dependency_graph(
 /// These methods provide functionality to print the dependency graph in various formats.
 /// TODO: These should be unified into a single printout and a single JSON format.
 impl DependencyGraph {
+	/// A dependency graph with no roots, for targets that cannot be analyzed.
+	pub fn empty() -> Self {
+		DependencyGraph { roots: Vec::new() }
+	}
+
 	/// Prints the dependency graph in its original format.
 	/// This uses println! instead of message to simplify Beckey's work.
 	pub fn original_print(&self, vas: &AbstractVas) -> String {
@@ -332,7 +384,9 @@ impl DependencyGraph {
 		}
 
 		let mut output = String::new();
-		print_node(vas, &self.root, 0, &mut output);
+		for root in &self.roots {
+			print_node(vas, root, 0, &mut output);
+		}
 		output
 	}
 	/// Pretty prints the dependency graph in a human-readable format.
@@ -368,7 +422,9 @@ impl DependencyGraph {
 				print_node(vas, child, depth + 1);
 			}
 		}
-		print_node(vas, &self.root, 0);
+		for root in &self.roots {
+			print_node(vas, root, 0);
+		}
 	}
 
 	/// Prints a simple representation of the dependency graph.
@@ -388,7 +444,9 @@ impl DependencyGraph {
 				print_node(vas, child, depth + 1);
 			}
 		}
-		print_node(vas, &self.root, 0);
+		for root in &self.roots {
+			print_node(vas, root, 0);
+		}
 		message!("===================\n");
 	}
 
@@ -415,26 +473,35 @@ impl DependencyGraph {
 				print_node(vas, child, depth + 1, output);
 			}
 		}
-		print_node(vas, &self.root, 0, &mut output);
+		for root in &self.roots {
+			print_node(vas, root, 0, &mut output);
+		}
 		output
 	}
 
 	/// Gives a vector of all the transitions in the dependency graph.
+	/// Transitions shared between several roots are only listed once.
 	pub fn get_transitions(&self) -> Vec<VasTransition> {
 		let mut transitions = Vec::new();
 		fn traverse(node: &GraphNode, transitions: &mut Vec<VasTransition>) {
-			if node.transition.transition_name != "ARTIFICIAL" {
+			if node.transition.transition_name != "ARTIFICIAL"
+				&& transitions
+					.iter()
+					.all(|t: &VasTransition| t.transition_id != node.transition.transition_id)
+			{
 				transitions.push(node.transition.clone());
 			}
 			for child in &node.children {
 				traverse(child, transitions);
 			}
 		}
-		traverse(&self.root, &mut transitions);
+		for root in &self.roots {
+			traverse(root, &mut transitions);
+		}
 		transitions
 	}
 
-	/// Returns the distance from the root node to the given transition
+	/// Returns the shortest distance from any root node to the given transition
 	pub fn distance_to_root(&self, transition_name: &str) -> Option<usize> {
 		fn traverse(node: &GraphNode, transition_name: &str, depth: usize) -> Option<usize> {
 			if node.transition.transition_name == transition_name {
@@ -447,6 +514,9 @@ impl DependencyGraph {
 			}
 			None
 		}
-		traverse(&self.root, transition_name, 0)
+		self.roots
+			.iter()
+			.filter_map(|root| traverse(root, transition_name, 0))
+			.min()
 	}
 }
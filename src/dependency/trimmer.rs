@@ -1,8 +1,6 @@
-use nalgebra::DVector;
-
 use crate::{
 	logging::messages::*,
-	model::vas_model::{AbstractVas, VasProperty, VasState, VasTransition, VasValue},
+	model::vas_model::{AbstractVas, VasState, VasStateVector, VasTransition},
 };
 
 use super::graph::DependencyGraph;
@@ -11,15 +9,21 @@ use super::graph::DependencyGraph;
 /// determined by the dependency graph.
 pub fn trim_model(model: &AbstractVas, dg: DependencyGraph) -> AbstractVas {
 	let mut variable_names = Vec::<String>::new();
-	let mut initial_state = Vec::<VasValue>::new();
 	let mut transitions = Vec::<VasTransition>::new();
 	let dg_transitions = dg.get_transitions();
-	// Collect exactly the set of variables that are used in the dependency graph
+	let target_variables = model
+		.target
+		.atoms()
+		.iter()
+		.flat_map(|atom| atom.variable_indices())
+		.collect::<Vec<_>>();
+	// Collect exactly the set of variables that are used in the dependency graph,
+	// always keeping the variables that the target refers to
 	for i in 0..model.variable_names.len() {
-		let mut is_used = false;
+		let mut is_used = target_variables.contains(&i);
 		debug_message!("{}: ", model.variable_names[i]);
 		for t in dg_transitions.iter() {
-			if t.update_vector[i] != 0 || t.enabled_bounds[i] != 0 {
+			if t.consume_vector[i] != 0 || t.produce_vector[i] != 0 || t.enabled_bounds[i] != 0 {
 				is_used = true;
 				debug_message!("used by transition {}", t.transition_name);
 				break;
@@ -27,63 +31,62 @@ pub fn trim_model(model: &AbstractVas, dg: DependencyGraph) -> AbstractVas {
 		}
 		if is_used {
 			variable_names.push(model.variable_names[i].clone());
-			initial_state.push(model.initial_states[0].vector[i].try_into().unwrap());
 		} else {
 			debug_message!("unused");
 		}
 	}
 	// Collect the transitions that are used in the dependency graph,
 	// and filter the update vector and enabled bounds to only include the used variables
+	let used_variables = |vector: &VasStateVector| -> VasStateVector {
+		vector
+			.iter()
+			.enumerate()
+			.filter_map(|(i, &x)| {
+				if variable_names.contains(&model.variable_names[i]) {
+					Some(x)
+				} else {
+					None
+				}
+			})
+			.collect::<Vec<_>>()
+			.into()
+	};
 	for t in dg_transitions {
 		transitions.push(VasTransition {
 			transition_name: t.transition_name,
 			transition_id: t.transition_id,
-			update_vector: t
-				.update_vector
-				.iter()
-				.enumerate()
-				.filter_map(|(i, &x)| {
-					if variable_names.contains(&model.variable_names[i]) {
-						Some(x)
-					} else {
-						None
-					}
-				})
-				.collect::<Vec<_>>()
-				.into(),
-			enabled_bounds: t
-				.enabled_bounds
-				.iter()
-				.enumerate()
-				.filter_map(|(i, &x)| {
-					if variable_names.contains(&model.variable_names[i]) {
-						Some(x)
-					} else {
-						None
-					}
-				})
-				.collect::<Vec<_>>()
-				.into(),
+			consume_vector: used_variables(&t.consume_vector),
+			produce_vector: used_variables(&t.produce_vector),
+			update_vector: used_variables(&t.update_vector),
+			enabled_bounds: used_variables(&t.enabled_bounds),
 			rate_const: t.rate_const,
 			custom_rate_fn: t.custom_rate_fn,
+			rate_convention: t.rate_convention,
 		});
 	}
 	// Update the target property to match the trimmed model
-	let target_index = variable_names
+	let target = model
+		.target
+		.remap_variables(&|index| {
+			variable_names
+				.iter()
+				.position(|x| x == &model.variable_names[index])
+		})
+		.unwrap_or_else(|| model.target.clone());
+	// Every initial state keeps its weight, even if trimming makes it equal to another
+	let initial_states = model
+		.initial_states
 		.iter()
-		.position(|x| x == &model.variable_names[model.target.variable_index])
-		.unwrap_or(0);
-	let target = VasProperty {
-		variable_index: target_index,
-		target_value: model.target.target_value,
-	};
-	// Create the trimmed model with the collected variables, initial state, and transitions
-	let trimmed_model = AbstractVas {
-		variable_names: variable_names.into_boxed_slice(),
-		initial_states: [VasState::new(DVector::from_vec(initial_state))].into(),
-		transitions: transitions,
-		m_type: model.m_type,
-		target: target,
-	};
+		.map(|state| VasState::new(used_variables(&state.vector)))
+		.collect();
+	// Create the trimmed model with the collected variables, initial states, and transitions
+	let mut trimmed_model = AbstractVas::new(
+		variable_names.into_boxed_slice(),
+		initial_states,
+		transitions,
+		target,
+	);
+	trimmed_model.initial_weights = model.initial_weights.clone();
+	trimmed_model.m_type = model.m_type;
 	trimmed_model
 }
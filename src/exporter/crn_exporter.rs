@@ -0,0 +1,563 @@
+use std::fmt;
+
+use crate::{
+	logging::messages::*,
+	model::{
+		model::ModelType,
+		vas_model::{AbstractVas, RateConvention, VasStateVector},
+	},
+	parser::{
+		parameters::refers_to_variables,
+		parser::{ModelOptions, ModelParseError},
+		vas_file_reader::{
+			build_model_from_lines, ALL_TERMS, DECREASE_TERMS, INCLUDE_TERMS, INCREASE_TERMS,
+			INITIAL_TERMS, KINETICS_TERMS, MODEL_TYPE_TERMS, PARAMETER_TERMS, RATE_TERMS,
+			SHARE_TERMS, TARGET_TERMS, TRANSITION_TERMS, VARIABLE_TERMS,
+		},
+	},
+};
+
+/// The indentation of the lines inside a reaction or an initial block
+const INDENT: &str = "    ";
+
+/// The keywords used when writing a model file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum KeywordSet {
+	/// `species`, `reaction`, `consume` and `produce`
+	#[default]
+	Crn,
+	/// `variable`, `transition`, `decrease` and `increase`
+	Vas,
+}
+
+impl KeywordSet {
+	fn variable(&self) -> &'static str {
+		match self {
+			KeywordSet::Crn => "species",
+			KeywordSet::Vas => "variable",
+		}
+	}
+
+	fn transition(&self) -> &'static str {
+		match self {
+			KeywordSet::Crn => "reaction",
+			KeywordSet::Vas => "transition",
+		}
+	}
+
+	fn decrease(&self) -> &'static str {
+		match self {
+			KeywordSet::Crn => "consume",
+			KeywordSet::Vas => "decrease",
+		}
+	}
+
+	fn increase(&self) -> &'static str {
+		match self {
+			KeywordSet::Crn => "produce",
+			KeywordSet::Vas => "increase",
+		}
+	}
+
+	/// The keyword set of a model file, following its first declaration
+	fn of_source(lines: &[String]) -> Self {
+		lines
+			.iter()
+			.filter_map(|line| line.split_whitespace().next())
+			.find(|word| VARIABLE_TERMS.contains(word) || TRANSITION_TERMS.contains(word))
+			.map_or(KeywordSet::default(), |word| match word {
+				"species" | "reaction" => KeywordSet::Crn,
+				_ => KeywordSet::Vas,
+			})
+	}
+}
+
+impl std::str::FromStr for KeywordSet {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"crn" => Ok(KeywordSet::Crn),
+			"vas" => Ok(KeywordSet::Vas),
+			_ => Err(format!(
+				"Unknown keyword set `{}` (expected `crn` or `vas`)",
+				s
+			)),
+		}
+	}
+}
+
+/// A transition of a `CrnDocument`
+struct DocumentTransition {
+	name: String,
+	// `(variable, count)` pairs, in the order the variables are declared
+	decrease: Vec<(String, String)>,
+	increase: Vec<(String, String)>,
+	// `const` for rate constants, `rate` for rate laws over the species
+	rate_keyword: &'static str,
+	rate: String,
+}
+
+/// An `initial` block of a `CrnDocument`
+struct DocumentInitialState {
+	// Left out for the default weight of 1
+	weight: Option<String>,
+	// `(variable, value)` pairs, in the order the variables are declared
+	values: Vec<(String, String)>,
+}
+
+/// A model file in canonical form. Values are kept as they are written,
+/// so parameters and expressions survive formatting.
+pub(crate) struct CrnDocument {
+	keywords: KeywordSet,
+	// `dtmc`, or left out for the default `ctmc`
+	model_type: Option<String>,
+	parameters: Vec<(String, String)>,
+	// `include` declarations, kept as written after the keyword
+	includes: Vec<String>,
+	// `(PREFIX.VARIABLE, NAME)` pairs of shared variables
+	shares: Vec<(String, String)>,
+	// `(name, initial value)` pairs
+	variables: Vec<(String, String)>,
+	initial_states: Vec<DocumentInitialState>,
+	kinetics: Option<String>,
+	targets: Vec<String>,
+	transitions: Vec<DocumentTransition>,
+}
+
+impl CrnDocument {
+	/// The document for a model. Custom rate functions cannot be written,
+	/// since only their compiled form is kept.
+	pub fn from_model(model: &AbstractVas, keywords: KeywordSet) -> Result<Self, String> {
+		let names = &model.variable_names;
+		let counts = |vector: &VasStateVector| {
+			names
+				.iter()
+				.zip(vector.iter())
+				.filter(|(_, count)| **count != 0)
+				.map(|(name, count)| (name.clone(), count.to_string()))
+				.collect::<Vec<_>>()
+		};
+		let transitions = model
+			.transitions
+			.iter()
+			.map(|transition| {
+				if transition.custom_rate_fn.is_some() {
+					return Err(format!(
+						"Transition {} has a custom rate function, which cannot be written to a model file",
+						transition.transition_name
+					));
+				}
+				Ok(DocumentTransition {
+					name: transition.transition_name.clone(),
+					decrease: counts(&transition.consume_vector),
+					increase: counts(&transition.produce_vector),
+					rate_keyword: "const",
+					rate: format!("{:?}", transition.rate_const),
+				})
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+		// Variables are declared with the values of the first initial state, so
+		// with several initial states each block lists only where it differs
+		let first_state = &model.initial_states[0].vector;
+		let initial_states = if model.initial_states.len() > 1 {
+			model
+				.initial_distribution()
+				.map(|(state, weight)| DocumentInitialState {
+					weight: Some(format!("{:?}", weight)),
+					values: names
+						.iter()
+						.enumerate()
+						.filter(|(i, _)| state.vector[*i] != first_state[*i])
+						.map(|(i, name)| (name.clone(), state.vector[i].to_string()))
+						.collect(),
+				})
+				.collect()
+		} else {
+			Vec::new()
+		};
+		Ok(Self {
+			keywords,
+			model_type: match model.m_type {
+				ModelType::ContinuousTime => None,
+				m_type => Some(m_type.to_string()),
+			},
+			parameters: Vec::new(),
+			includes: Vec::new(),
+			shares: Vec::new(),
+			variables: names
+				.iter()
+				.zip(first_state.iter())
+				.map(|(name, value)| (name.clone(), value.to_string()))
+				.collect(),
+			initial_states,
+			kinetics: match model.rate_convention() {
+				RateConvention::Stochastic => None,
+				convention => Some(convention.to_string()),
+			},
+			targets: vec![model.target.to_string_with_names(names)],
+			transitions,
+		})
+	}
+
+	/// The document for the lines of a model file, which must parse without errors.
+	/// If no keyword set is given, the file's own is kept.
+	pub fn from_source(lines: &[String], keywords: Option<KeywordSet>) -> Self {
+		let mut document = Self {
+			keywords: keywords.unwrap_or_else(|| KeywordSet::of_source(lines)),
+			model_type: None,
+			parameters: Vec::new(),
+			includes: Vec::new(),
+			shares: Vec::new(),
+			variables: Vec::new(),
+			initial_states: Vec::new(),
+			kinetics: None,
+			targets: Vec::new(),
+			transitions: Vec::new(),
+		};
+		let variable_names: Vec<String> = lines
+			.iter()
+			.map(|line| line.split_whitespace().collect::<Vec<_>>())
+			.filter(|words| words.len() >= 2 && VARIABLE_TERMS.contains(&words[0]))
+			.map(|words| words[1].to_string())
+			.collect();
+		// Whether the lines belong to an `initial` block
+		let mut in_initial_block = false;
+		for line in lines {
+			let words: Vec<&str> = line.split_whitespace().collect();
+			let Some(first_word) = words.first() else {
+				continue;
+			};
+			// Expressions are written with single spaces between their words
+			let rest = |from: usize| words[from.min(words.len())..].join(" ");
+			if ALL_TERMS.iter().any(|terms| terms.contains(first_word)) {
+				in_initial_block = false;
+			} else if in_initial_block {
+				if let Some(initial_state) = document.initial_states.last_mut() {
+					initial_state.values.push((first_word.to_string(), rest(1)));
+				}
+				continue;
+			}
+			if INITIAL_TERMS.contains(first_word) {
+				in_initial_block = true;
+				document.initial_states.push(DocumentInitialState {
+					weight: (words.len() >= 2).then(|| rest(1)),
+					values: Vec::new(),
+				});
+			} else if PARAMETER_TERMS.contains(first_word) {
+				if let Some((name, expression)) = rest(1).split_once('=') {
+					document
+						.parameters
+						.push((name.trim().to_string(), expression.trim().to_string()));
+				}
+			} else if INCLUDE_TERMS.contains(first_word) {
+				// The quoted path is kept exactly as written
+				let declaration = line
+					.trim()
+					.split_once(char::is_whitespace)
+					.map_or("", |(_, rest)| rest.trim());
+				let include = declaration
+					.strip_prefix('"')
+					.and_then(|rest| rest.split_once('"'))
+					.map_or(declaration.to_string(), |(path, rest)| {
+						let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+						format!("\"{}\" {}", path, rest)
+					});
+				document.includes.push(include);
+			} else if SHARE_TERMS.contains(first_word) {
+				if let Some((shared, name)) = rest(1).split_once('=') {
+					document
+						.shares
+						.push((shared.trim().to_string(), name.trim().to_string()));
+				}
+			} else if VARIABLE_TERMS.contains(first_word) {
+				let init = if words.len() >= 4 {
+					rest(3)
+				} else {
+					"0".to_string()
+				};
+				document.variables.push((words[1].to_string(), init));
+			} else if TRANSITION_TERMS.contains(first_word) {
+				document.transitions.push(DocumentTransition {
+					name: words[1].to_string(),
+					decrease: Vec::new(),
+					increase: Vec::new(),
+					rate_keyword: "const",
+					rate: String::new(),
+				});
+			} else if TARGET_TERMS.contains(first_word) {
+				document.targets.push(rest(1));
+			} else if MODEL_TYPE_TERMS.contains(first_word) {
+				// The default model type is left out
+				if *first_word != "ctmc" {
+					document.model_type = Some(first_word.to_string());
+				}
+			} else if KINETICS_TERMS.contains(first_word) {
+				// The default convention is left out
+				if words.get(1) != Some(&"stochastic") {
+					document.kinetics = Some(rest(1));
+				}
+			} else if let Some(transition) = document.transitions.last_mut() {
+				let count = if words.len() >= 3 {
+					rest(2)
+				} else {
+					"1".to_string()
+				};
+				if DECREASE_TERMS.contains(first_word) {
+					transition.decrease.push((words[1].to_string(), count));
+				} else if INCREASE_TERMS.contains(first_word) {
+					transition.increase.push((words[1].to_string(), count));
+				} else if RATE_TERMS.contains(first_word) {
+					transition.rate = rest(1);
+					transition.rate_keyword =
+						if refers_to_variables(&transition.rate, &variable_names) {
+							"rate"
+						} else {
+							"const"
+						};
+				}
+			}
+		}
+		// Updates and initial values follow the order the variables are declared in
+		let position = |name: &String| variable_names.iter().position(|n| n == name);
+		for transition in document.transitions.iter_mut() {
+			for updates in [&mut transition.decrease, &mut transition.increase] {
+				updates.sort_by_key(|(name, _)| position(name));
+			}
+		}
+		for initial_state in document.initial_states.iter_mut() {
+			initial_state.values.sort_by_key(|(name, _)| position(name));
+		}
+		document
+	}
+}
+
+impl fmt::Display for CrnDocument {
+	/// Writes the model file: model type, parameters, includes, shares, variables,
+	/// initial states, kinetics, targets and then the transitions, with counts of 1 omitted
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some(model_type) = &self.model_type {
+			writeln!(f, "{}", model_type)?;
+		}
+		for (name, expression) in &self.parameters {
+			writeln!(f, "param {} = {}", name, expression)?;
+		}
+		for include in &self.includes {
+			writeln!(f, "include {}", include)?;
+		}
+		for (shared, name) in &self.shares {
+			writeln!(f, "share {} = {}", shared, name)?;
+		}
+		for (name, init) in &self.variables {
+			writeln!(f, "{} {} init {}", self.keywords.variable(), name, init)?;
+		}
+		for initial_state in &self.initial_states {
+			match &initial_state.weight {
+				Some(weight) => writeln!(f, "initial {}", weight)?,
+				None => writeln!(f, "initial")?,
+			}
+			for (name, value) in &initial_state.values {
+				writeln!(f, "{}{} {}", INDENT, name, value)?;
+			}
+		}
+		if let Some(kinetics) = &self.kinetics {
+			writeln!(f, "kinetics {}", kinetics)?;
+		}
+		for target in &self.targets {
+			writeln!(f, "target {}", target)?;
+		}
+		for transition in &self.transitions {
+			writeln!(f, "{} {}", self.keywords.transition(), transition.name)?;
+			for (keyword, updates) in [
+				(self.keywords.decrease(), &transition.decrease),
+				(self.keywords.increase(), &transition.increase),
+			] {
+				for (name, count) in updates {
+					match count.as_str() {
+						"1" => writeln!(f, "{}{} {}", INDENT, keyword, name)?,
+						_ => writeln!(f, "{}{} {} {}", INDENT, keyword, name, count)?,
+					}
+				}
+			}
+			writeln!(
+				f,
+				"{}{} {}",
+				INDENT, transition.rate_keyword, transition.rate
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// Writes the model as a model file (`.crn`), e.g., after trimming or
+/// importing it from another format
+pub fn write_crn_model(
+	model: &AbstractVas,
+	output: &str,
+	keywords: KeywordSet,
+) -> Result<(), String> {
+	let document = CrnDocument::from_model(model, keywords)?;
+	std::fs::write(output, document.to_string())
+		.map_err(|e| format!("Error writing {}: {}", output, e))?;
+	message!("Model written to: {}", output);
+	Ok(())
+}
+
+/// Parses the lines of a model file, joining the parse errors into one message
+fn parse_lines(lines: &[String], filename: &str) -> Result<AbstractVas, String> {
+	build_model_from_lines(lines.iter().cloned(), filename, &ModelOptions::default()).map_err(
+		|errors| {
+			errors
+				.iter()
+				.map(|err| format!("{}", err as &dyn ModelParseError))
+				.collect::<Vec<_>>()
+				.join("\n")
+		},
+	)
+}
+
+/// Rewrites a model file in canonical form. With `check`, the file is left
+/// unchanged. Returns whether the file was already formatted.
+pub fn format_model_file(
+	filename: &str,
+	keywords: Option<KeywordSet>,
+	check: bool,
+) -> Result<bool, String> {
+	let source = std::fs::read_to_string(filename)
+		.map_err(|e| format!("Error reading {}: {}", filename, e))?;
+	let lines: Vec<String> = source.lines().map(|line| line.to_string()).collect();
+	let model = parse_lines(&lines, filename)?;
+	let formatted = CrnDocument::from_source(&lines, keywords).to_string();
+	// Formatting must not change the model
+	let formatted_lines: Vec<String> = formatted.lines().map(|line| line.to_string()).collect();
+	let formatted_model = parse_lines(&formatted_lines, filename)?;
+	if formatted_model.nice_print() != model.nice_print() {
+		return Err(format!(
+			"Formatting {} would change the model, so it was left unchanged",
+			filename
+		));
+	}
+	let is_formatted = formatted == source;
+	if !check && !is_formatted {
+		std::fs::write(filename, &formatted)
+			.map_err(|e| format!("Error writing {}: {}", filename, e))?;
+	}
+	Ok(is_formatted)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::{parse_lines, CrnDocument, KeywordSet};
+	use crate::model::vas_model::AbstractVas;
+
+	/// The `.crn` and `.vas` models bundled with the repository
+	fn bundled_model_files() -> Vec<String> {
+		let mut files = Vec::new();
+		for directory in ["models", "benchmark_models", "toy_model"] {
+			let directory = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), directory);
+			for model_directory in fs::read_dir(&directory).unwrap() {
+				let model_directory = model_directory.unwrap().path();
+				if !model_directory.is_dir() {
+					continue;
+				}
+				for file in fs::read_dir(model_directory).unwrap() {
+					let path = file.unwrap().path();
+					if matches!(
+						path.extension().and_then(|e| e.to_str()),
+						Some("crn" | "vas")
+					) {
+						files.push(path.to_string_lossy().to_string());
+					}
+				}
+			}
+		}
+		files.sort();
+		assert!(!files.is_empty());
+		files
+	}
+
+	fn lines_of(text: &str) -> Vec<String> {
+		text.lines().map(|line| line.to_string()).collect()
+	}
+
+	/// Checks that two models have the same variables, initial distribution,
+	/// target, kinetics and transitions
+	fn assert_same_model(expected: &AbstractVas, actual: &AbstractVas, filename: &str) {
+		assert_eq!(
+			expected.variable_names, actual.variable_names,
+			"{}",
+			filename
+		);
+		assert_eq!(expected.m_type, actual.m_type, "{}", filename);
+		assert_eq!(
+			expected.rate_convention(),
+			actual.rate_convention(),
+			"{}",
+			filename
+		);
+		let initial = |model: &AbstractVas| {
+			model
+				.initial_distribution()
+				.map(|(state, weight)| (state.vector.clone(), weight))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(initial(expected), initial(actual), "{}", filename);
+		assert_eq!(
+			expected
+				.target
+				.to_string_with_names(&expected.variable_names),
+			actual.target.to_string_with_names(&actual.variable_names),
+			"{}",
+			filename
+		);
+		assert_eq!(
+			expected.transitions.len(),
+			actual.transitions.len(),
+			"{}",
+			filename
+		);
+		for (e, a) in expected.transitions.iter().zip(actual.transitions.iter()) {
+			assert_eq!(e.transition_name, a.transition_name, "{}", filename);
+			assert_eq!(e.consume_vector, a.consume_vector, "{}", filename);
+			assert_eq!(e.produce_vector, a.produce_vector, "{}", filename);
+			assert_eq!(e.rate_const, a.rate_const, "{}", filename);
+			assert_eq!(
+				e.custom_rate_fn.is_some(),
+				a.custom_rate_fn.is_some(),
+				"{}",
+				filename
+			);
+		}
+	}
+
+	#[test]
+	fn formatted_source_round_trips() {
+		for filename in bundled_model_files() {
+			let lines = lines_of(&fs::read_to_string(&filename).unwrap());
+			let model = parse_lines(&lines, &filename).unwrap();
+			let formatted = CrnDocument::from_source(&lines, None).to_string();
+			let reparsed = parse_lines(&lines_of(&formatted), &filename).unwrap();
+			assert_same_model(&model, &reparsed, &filename);
+			// Formatting is idempotent
+			let reformatted = CrnDocument::from_source(&lines_of(&formatted), None).to_string();
+			assert_eq!(formatted, reformatted, "{}", filename);
+		}
+	}
+
+	#[test]
+	fn written_model_round_trips() {
+		for filename in bundled_model_files() {
+			let lines = lines_of(&fs::read_to_string(&filename).unwrap());
+			let model = parse_lines(&lines, &filename).unwrap();
+			for keywords in [KeywordSet::Crn, KeywordSet::Vas] {
+				let written = CrnDocument::from_model(&model, keywords)
+					.unwrap()
+					.to_string();
+				let reparsed = parse_lines(&lines_of(&written), &filename).unwrap();
+				assert_same_model(&model, &reparsed, &filename);
+			}
+		}
+	}
+}
@@ -0,0 +1,2 @@
+pub mod crn_exporter;
+pub mod prism_exporter;
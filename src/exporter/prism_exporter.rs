@@ -0,0 +1,343 @@
+use crate::{
+	bmc::{bounds::BMCBounds, vas_bmc::AbstractVasBmc},
+	logging::messages::*,
+	model::{
+		model::ModelType,
+		vas_model::{AbstractVas, RateConvention, VasState, VasValue},
+	},
+	parser::parser::ModelOptions,
+	warning,
+};
+
+/// Makes a name usable as a PRISM identifier
+fn prism_identifier(name: &str) -> String {
+	let identifier: String = name
+		.chars()
+		.map(|c| {
+			if c.is_alphanumeric() || c == '_' {
+				c
+			} else {
+				'_'
+			}
+		})
+		.collect();
+	if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+		format!("_{}", identifier)
+	} else {
+		identifier
+	}
+}
+
+/// The propensity factor of a species consumed `bound` times, e.g.,
+/// `(X*(X-1)/2)` for two copies under the stochastic convention
+fn propensity_factor(name: &str, bound: VasValue, rate_convention: RateConvention) -> String {
+	if bound == 1 {
+		return name.to_string();
+	}
+	let falling = (0..bound)
+		.map(|i| match i {
+			0 => name.to_string(),
+			_ => format!("({}-{})", name, i),
+		})
+		.collect::<Vec<_>>()
+		.join("*");
+	match rate_convention {
+		RateConvention::Stochastic => {
+			let factorial: VasValue = (1..=bound).product();
+			format!("({}/{})", falling, factorial)
+		}
+		RateConvention::Deterministic => format!("({})", falling),
+	}
+}
+
+/// Variable ranges `(lower, upper)` from the tight bounds found by the bounder.
+/// Variables the bounder found no upper bound for are capped at `cap`, as in
+/// `ranges_from_cap`. Ranges are widened to include the initial values if needed.
+pub fn ranges_from_bounds(
+	model: &AbstractVas,
+	bounds: &BMCBounds,
+	cap: VasValue,
+) -> Vec<(VasValue, VasValue)> {
+	model
+		.variable_names
+		.iter()
+		.enumerate()
+		.map(|(i, name)| {
+			let (initial_min, initial_max) = model.initial_value_range(i);
+			let lower = bounds.lb_tight.get(name).copied().unwrap_or(0);
+			let upper = match bounds.ub_tight.get(name) {
+				Some(upper) => *upper,
+				None => {
+					warning!(
+						"The bounder found no upper bound for {}. Capping it at {}.",
+						name,
+						cap
+					);
+					cap
+				}
+			};
+			(lower.min(initial_min), upper.max(initial_max))
+		})
+		.collect()
+}
+
+/// Variable ranges `[0..cap]`, widened to include the initial values if needed
+pub fn ranges_from_cap(model: &AbstractVas, cap: VasValue) -> Vec<(VasValue, VasValue)> {
+	(0..model.variable_names.len())
+		.map(|i| (0, cap.max(model.initial_value_range(i).1)))
+		.collect()
+}
+
+/// The PRISM expression that holds exactly in an initial state, e.g., `X=5 & Y=0`
+fn initial_state_predicate(names: &[String], state: &VasState) -> String {
+	names
+		.iter()
+		.zip(state.vector.iter())
+		.map(|(name, value)| format!("{}={}", name, value))
+		.collect::<Vec<_>>()
+		.join(" & ")
+}
+
+/// Writes the model as a PRISM CTMC or DTMC with a single module.
+/// CTMC rates are mass-action expressions matching `VasTransition::get_sck_rate`
+/// under the model's rate convention. DTMC weights are normalized over the
+/// enabled transitions in a single command, as in `AbstractVas::transition_rate`.
+/// Guards keep every update inside the variable ranges, truncating the state space there.
+pub fn prism_model_string(
+	model: &AbstractVas,
+	ranges: &[(VasValue, VasValue)],
+) -> Result<String, String> {
+	let names: Vec<String> = model
+		.variable_names
+		.iter()
+		.map(|name| prism_identifier(name))
+		.collect();
+	let mut output = String::new();
+	output.push_str("// Generated by the STAMINA toolset\n\n");
+	output.push_str(&format!("{}\n\n", model.m_type));
+
+	// Rate constants
+	for transition in &model.transitions {
+		if transition.custom_rate_fn.is_some() {
+			return Err(format!(
+				"Transition {} has a custom rate, which cannot be exported to PRISM",
+				transition.transition_name
+			));
+		}
+		output.push_str(&format!(
+			"const double k_{} = {:?};\n",
+			prism_identifier(&transition.transition_name),
+			transition.rate_const
+		));
+	}
+
+	// Variables, initialized in the declaration if there is one initial state
+	let single_initial_state = model.initial_states.len() == 1;
+	let module_start = output.len();
+	output.push_str("\nmodule crn\n\n");
+	for (i, name) in names.iter().enumerate() {
+		let (lower, upper) = ranges[i];
+		if single_initial_state {
+			output.push_str(&format!(
+				"\t{} : [{}..{}] init {};\n",
+				name, lower, upper, model.initial_states[0].vector[i]
+			));
+		} else {
+			output.push_str(&format!("\t{} : [{}..{}];\n", name, lower, upper));
+		}
+	}
+	output.push('\n');
+
+	// Commands, with the guard and weight of each transition kept for a DTMC
+	let mut dtmc_branches = Vec::new();
+	for transition in &model.transitions {
+		let identifier = prism_identifier(&transition.transition_name);
+		let mut guards = Vec::new();
+		let mut updates = Vec::new();
+		let mut clamped_updates = Vec::new();
+		for (i, name) in names.iter().enumerate() {
+			let (lower, upper) = ranges[i];
+			let update = transition.update_vector[i];
+			// Enabled bound, tightened so the update cannot leave the range
+			let minimum = transition.enabled_bounds[i].max(lower - update.min(0));
+			if minimum > lower {
+				guards.push(format!("{} >= {}", name, minimum));
+			}
+			if update > 0 {
+				guards.push(format!("{} <= {}", name, upper - update));
+				updates.push(format!("({}' = {} + {})", name, name, update));
+				clamped_updates.push(format!(
+					"({}' = min({} + {}, {}))",
+					name, name, update, upper
+				));
+			} else if update < 0 {
+				updates.push(format!("({}' = {} - {})", name, name, -update));
+				clamped_updates.push(format!(
+					"({}' = max({} - {}, {}))",
+					name, name, -update, lower
+				));
+			}
+		}
+		let guard = if guards.is_empty() {
+			"true".to_string()
+		} else {
+			guards.join(" & ")
+		};
+		if model.m_type == ModelType::DiscreteTime {
+			// A disabled branch has probability 0, but its update must still stay in range
+			dtmc_branches.push((
+				format!("({} ? k_{} : 0)", guard, identifier),
+				if clamped_updates.is_empty() {
+					"true".to_string()
+				} else {
+					clamped_updates.join(" & ")
+				},
+			));
+			continue;
+		}
+		let rate = std::iter::once(format!("k_{}", identifier))
+			.chain(
+				names
+					.iter()
+					.zip(transition.consume_vector.iter())
+					.filter(|(_, consumed)| **consumed != 0)
+					.map(|(name, consumed)| {
+						propensity_factor(name, *consumed, transition.rate_convention)
+					}),
+			)
+			.collect::<Vec<_>>()
+			.join(" * ");
+		output.push_str(&format!(
+			"\t// {}\n",
+			transition.reaction_string(&model.variable_names)
+		));
+		output.push_str(&format!(
+			"\t[{}] {} -> {} : {};\n",
+			identifier,
+			guard,
+			rate,
+			if updates.is_empty() {
+				"true".to_string()
+			} else {
+				updates.join(" & ")
+			}
+		));
+	}
+	if !dtmc_branches.is_empty() {
+		let total_weight = dtmc_branches
+			.iter()
+			.map(|(weight, _)| weight.clone())
+			.collect::<Vec<_>>()
+			.join(" + ");
+		let branches = dtmc_branches
+			.iter()
+			.map(|(weight, update)| format!("{} / total_weight : {}", weight, update))
+			.collect::<Vec<_>>();
+		output.push_str(
+			"\t// Each transition is taken with its weight over the total enabled weight\n",
+		);
+		output.push_str(&format!(
+			"\t[] total_weight > 0 ->\n\t\t{};\n",
+			branches.join("\n\t\t+ ")
+		));
+		output.insert_str(
+			module_start,
+			&format!("\nformula total_weight = {};\n", total_weight),
+		);
+	}
+	output.push_str("\nendmodule\n");
+
+	// Several initial states are listed in an `init` block
+	if !single_initial_state {
+		let predicates = model
+			.initial_states
+			.iter()
+			.map(|state| format!("({})", initial_state_predicate(&names, state)))
+			.collect::<Vec<_>>();
+		output.push_str(&format!(
+			"\ninit\n\t{}\nendinit\n",
+			predicates.join(" |\n\t")
+		));
+	}
+	Ok(output)
+}
+
+/// The target as a PRISM label, with a reachability query over it.
+/// With several initial states, the query weights the probability from each
+/// initial state by the initial distribution.
+pub fn prism_property_string(model: &AbstractVas) -> String {
+	let names: Vec<String> = model
+		.variable_names
+		.iter()
+		.map(|name| prism_identifier(name))
+		.collect();
+	let target = model.target.to_string_with_names(&names);
+	let mut output = format!("label \"target\" = {};\n", target);
+	if model.initial_states.len() == 1 {
+		output.push_str("\nP=? [ F \"target\" ]\n");
+		return output;
+	}
+	let mut terms = Vec::new();
+	for (i, (state, weight)) in model.initial_distribution().enumerate() {
+		output.push_str(&format!(
+			"label \"init_{}\" = {};\n",
+			i,
+			initial_state_predicate(&names, state)
+		));
+		terms.push(format!(
+			"{:?} * filter(state, P=? [ F \"target\" ], \"init_{}\")",
+			weight, i
+		));
+	}
+	output.push_str(&format!("\n{}\n", terms.join(" + ")));
+	output
+}
+
+/// Writes `<output>.sm` and `<output>.prop` for the model
+pub fn write_prism_model(
+	model: &AbstractVas,
+	ranges: &[(VasValue, VasValue)],
+	output: &str,
+) -> Result<(), String> {
+	let model_file = format!("{}.sm", output);
+	let property_file = format!("{}.prop", output);
+	std::fs::write(&model_file, prism_model_string(model, ranges)?)
+		.map_err(|e| format!("Error writing {}: {}", model_file, e))?;
+	std::fs::write(&property_file, prism_property_string(model))
+		.map_err(|e| format!("Error writing {}: {}", property_file, e))?;
+	message!("PRISM model written to: {}", model_file);
+	message!("PRISM property written to: {}", property_file);
+	Ok(())
+}
+
+/// Converts a model file to PRISM. If `bounds` is given as `(bits, max_steps)`,
+/// variable ranges come from the bounder; otherwise (or for variables the bounder
+/// cannot bound) they are capped at `cap`.
+pub fn convert_model(
+	model_file: &str,
+	options: &ModelOptions,
+	output: &str,
+	cap: VasValue,
+	bounds: Option<(u32, u32)>,
+) {
+	let model = match AbstractVas::from_file(model_file, options) {
+		Ok(model) => model,
+		Err(_) => {
+			error!("Error parsing model file: {}", model_file);
+			return;
+		}
+	};
+	message!("Successfully parsed model file: {}", model_file);
+	let ranges = match bounds {
+		Some((bits, max_steps)) => {
+			message!("Computing variable ranges with the bounder.");
+			let bmc_encoding = model.bmc_encoding(bits);
+			let variable_bounds = model.variable_bounds(&bmc_encoding, bits, max_steps, false);
+			ranges_from_bounds(&model, &variable_bounds, cap)
+		}
+		None => ranges_from_cap(&model, cap),
+	};
+	if let Err(e) = write_prism_model(&model, &ranges, output) {
+		error!("{}", e);
+	}
+}
@@ -4,9 +4,11 @@ mod arguments;
 mod benchmarks;
 mod bmc;
 mod builder;
+mod checker;
 mod cycle_commute;
 mod demos;
 mod dependency;
+mod exporter;
 mod logging;
 mod model;
 mod parser;
@@ -23,6 +25,16 @@ fn main() {
 	// Parse command line arguments
 	let args = arguments::arg_parser::parse_args();
 
+	// JSON output is meant for other programs, so it is printed alone
+	let json_output = args
+		.subcommand()
+		.and_then(|(_, sub_m)| sub_m.try_get_one::<String>("format").ok().flatten())
+		.is_some_and(|format| format == "json");
+	if json_output {
+		arguments::cmd_executor::run_commands(&args);
+		return;
+	}
+
 	// Print welcome message
 	println!(
 		"
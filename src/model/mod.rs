@@ -1,4 +1,5 @@
 // pub mod parser;
 pub mod model;
+pub mod sparse_matrix;
 pub mod vas_model;
-pub mod vas_trie;
+pub mod vas_target;
@@ -71,6 +71,30 @@ pub(crate) enum ModelType {
 	DiscreteTime,
 }
 
+impl std::str::FromStr for ModelType {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"ctmc" => Ok(ModelType::ContinuousTime),
+			"dtmc" => Ok(ModelType::DiscreteTime),
+			_ => Err(format!(
+				"Unknown model type `{}` (expected `ctmc` or `dtmc`)",
+				s
+			)),
+		}
+	}
+}
+
+impl std::fmt::Display for ModelType {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ModelType::ContinuousTime => write!(f, "ctmc"),
+			ModelType::DiscreteTime => write!(f, "dtmc"),
+		}
+	}
+}
+
 pub(crate) trait AbstractModel {
 	type StateType: State;
 	type TransitionType: Transition;
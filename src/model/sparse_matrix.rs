@@ -0,0 +1,204 @@
+use crate::model::model::ProbabilityOrRate;
+
+/// A square matrix of rates (CTMC) or probabilities (DTMC) between states, in
+/// compressed sparse row (CSR) form: the entries of row `i` are at
+/// `row_starts[i]..row_starts[i + 1]` in `columns` and `values`, sorted by column.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SparseMatrix {
+	row_starts: Vec<usize>,
+	columns: Vec<usize>,
+	values: Vec<ProbabilityOrRate>,
+	// The total rate or probability of leaving each state, without self-loops
+	exit_rates: Vec<ProbabilityOrRate>,
+}
+
+impl SparseMatrix {
+	/// Builds the matrix for `state_count` states from (from, to, value) entries in
+	/// any order. Entries for the same pair of states are added together.
+	pub fn from_entries(
+		state_count: usize,
+		entries: impl IntoIterator<Item = (usize, usize, ProbabilityOrRate)>,
+	) -> Self {
+		let mut rows: Vec<Vec<(usize, ProbabilityOrRate)>> = vec![Vec::new(); state_count];
+		for (from, to, value) in entries {
+			rows[from].push((to, value));
+		}
+		let mut row_starts = Vec::with_capacity(state_count + 1);
+		let mut columns = Vec::new();
+		let mut values: Vec<ProbabilityOrRate> = Vec::new();
+		row_starts.push(0);
+		for row in rows.iter_mut() {
+			row.sort_by_key(|(to, _)| *to);
+			let row_start = columns.len();
+			for &(to, value) in row.iter() {
+				if columns.len() > row_start && columns.last() == Some(&to) {
+					*values.last_mut().unwrap() += value;
+				} else {
+					columns.push(to);
+					values.push(value);
+				}
+			}
+			row_starts.push(columns.len());
+		}
+		let mut matrix = Self {
+			row_starts,
+			columns,
+			values,
+			exit_rates: Vec::new(),
+		};
+		matrix.exit_rates = (0..state_count)
+			.map(|state| matrix.off_diagonal_row(state).map(|(_, value)| value).sum())
+			.collect();
+		matrix
+	}
+
+	/// The number of states, i.e., of rows
+	pub fn state_count(&self) -> usize {
+		self.row_starts.len().saturating_sub(1)
+	}
+
+	/// The number of stored entries
+	pub fn entry_count(&self) -> usize {
+		self.values.len()
+	}
+
+	/// The (to, value) entries of a row, sorted by `to`
+	pub fn row(&self, state: usize) -> impl Iterator<Item = (usize, ProbabilityOrRate)> + '_ {
+		let range = self.row_starts[state]..self.row_starts[state + 1];
+		self.columns[range.clone()]
+			.iter()
+			.copied()
+			.zip(self.values[range].iter().copied())
+	}
+
+	/// The columns of the entries of a row, i.e., the states it leads to
+	pub fn row_columns(&self, state: usize) -> &[usize] {
+		&self.columns[self.row_starts[state]..self.row_starts[state + 1]]
+	}
+
+	/// The entries of a row without its self-loop, i.e., the ways of leaving the state
+	pub fn off_diagonal_row(
+		&self,
+		state: usize,
+	) -> impl Iterator<Item = (usize, ProbabilityOrRate)> + '_ {
+		self.row(state).filter(move |(to, _)| *to != state)
+	}
+
+	/// All (from, to, value) entries, row by row
+	pub fn entries(&self) -> impl Iterator<Item = (usize, usize, ProbabilityOrRate)> + '_ {
+		(0..self.state_count())
+			.flat_map(move |from| self.row(from).map(move |(to, value)| (from, to, value)))
+	}
+
+	/// The total rate (or probability) of leaving each state, without self-loops
+	pub fn exit_rates(&self) -> &[ProbabilityOrRate] {
+		&self.exit_rates
+	}
+
+	/// The embedded DTMC: each row without its self-loop, divided by its exit rate,
+	/// so that it holds the probability of each next state. States that cannot be
+	/// left have empty rows.
+	pub fn embedded(&self) -> Self {
+		let entries = self
+			.entries()
+			.filter(|(from, to, _)| from != to && self.exit_rates[*from] > 0.0)
+			.map(|(from, to, value)| (from, to, value / self.exit_rates[from]));
+		Self::from_entries(self.state_count(), entries)
+	}
+
+	/// The matrix with the given states made absorbing, i.e., with their rows emptied
+	pub fn with_absorbing(&self, absorbing: &[bool]) -> Self {
+		Self::from_entries(
+			self.state_count(),
+			self.entries().filter(|(from, _, _)| !absorbing[*from]),
+		)
+	}
+
+	/// The states with a transition into each state, not counting self-loops
+	pub fn predecessors(&self) -> Vec<Vec<usize>> {
+		let mut predecessors = vec![Vec::new(); self.state_count()];
+		for (from, to, value) in self.entries() {
+			if from != to && value > 0.0 {
+				predecessors[to].push(from);
+			}
+		}
+		predecessors
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::SparseMatrix;
+
+	fn matrix() -> SparseMatrix {
+		SparseMatrix::from_entries(
+			4,
+			[
+				(1, 2, 1.0),
+				(0, 2, 3.0),
+				(0, 1, 1.0),
+				(0, 2, 1.0),
+				(0, 0, 4.0),
+				(2, 0, 0.0),
+			],
+		)
+	}
+
+	#[test]
+	fn from_entries_sorts_and_adds_up_rows() {
+		let matrix = matrix();
+		assert_eq!(matrix.state_count(), 4);
+		assert_eq!(matrix.entry_count(), 5);
+		assert_eq!(
+			matrix.row(0).collect::<Vec<_>>(),
+			vec![(0, 4.0), (1, 1.0), (2, 4.0)]
+		);
+		assert_eq!(matrix.row_columns(0), &[0, 1, 2]);
+		assert_eq!(
+			matrix.off_diagonal_row(0).collect::<Vec<_>>(),
+			vec![(1, 1.0), (2, 4.0)]
+		);
+		assert_eq!(matrix.row(3).count(), 0);
+		assert_eq!(matrix.exit_rates(), &[5.0, 1.0, 0.0, 0.0]);
+		assert_eq!(
+			matrix.entries().collect::<Vec<_>>(),
+			vec![
+				(0, 0, 4.0),
+				(0, 1, 1.0),
+				(0, 2, 4.0),
+				(1, 2, 1.0),
+				(2, 0, 0.0)
+			]
+		);
+	}
+
+	#[test]
+	fn embedded_divides_by_the_exit_rate() {
+		let embedded = matrix().embedded();
+		assert_eq!(
+			embedded.row(0).collect::<Vec<_>>(),
+			vec![(1, 0.2), (2, 0.8)]
+		);
+		assert_eq!(embedded.row(1).collect::<Vec<_>>(), vec![(2, 1.0)]);
+		// States that cannot be left have empty rows
+		assert_eq!(embedded.row(2).count(), 0);
+		assert_eq!(embedded.row(3).count(), 0);
+	}
+
+	#[test]
+	fn with_absorbing_empties_rows() {
+		let matrix = matrix().with_absorbing(&[true, false, false, false]);
+		assert_eq!(matrix.state_count(), 4);
+		assert_eq!(matrix.row(0).count(), 0);
+		assert_eq!(matrix.exit_rates()[0], 0.0);
+		assert_eq!(matrix.row(1).collect::<Vec<_>>(), vec![(2, 1.0)]);
+	}
+
+	#[test]
+	fn predecessors_skip_self_loops_and_zero_entries() {
+		assert_eq!(
+			matrix().predecessors(),
+			vec![vec![], vec![0], vec![0, 1], vec![]]
+		);
+	}
+}
@@ -1,21 +1,29 @@
 use std::{
-	collections::{BTreeSet, HashMap},
+	cell::RefCell,
+	collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap},
 	fmt,
 	fs::File,
 	io::stdout,
+	sync::{Arc, OnceLock},
 };
 
 use crate::{
 	logging::messages::*,
-	model::{model::ExplicitModel, vas_trie::VasTrieNode},
-	parser::vas_file_reader,
-	property::property,
+	model::{model::ExplicitModel, sparse_matrix::SparseMatrix, vas_target::VasTarget},
+	parser::{
+		parser::{ModelOptions, ModelParseError, Parser},
+		prism_parser::PrismParser,
+		sbml_parser::SbmlParser,
+		vas_file_reader,
+	},
+	property::property::{self, Labeled, StateFormula},
 	trace::trace_trie::TraceTrieNode,
 	validator::vas_validator::validate_vas,
 	warning,
 };
 
 use nalgebra::DVector;
+use rand::Rng;
 use std::io::Write;
 
 use super::model::{AbstractModel, ModelType, ProbabilityOrRate, State, Transition};
@@ -31,13 +39,34 @@ struct StateLabel {
 	// Add fields as needed
 }
 
+/// A named label and the formula that defines it, e.g., `"target"` and `S1 >= 30`
+pub(crate) type LabelDefinition = (StateFormula, StateFormula);
+
+/// The variable names and label definitions shared by the labeled states of a model
+type Labeling = (Arc<[String]>, Arc<[LabelDefinition]>);
+
 /// A state in a Vector Addition System (VAS)
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub(crate) struct VasState {
 	// The state values
 	pub(crate) vector: VasStateVector,
-	// The labelset for this state
+	// The labels assigned to this state, e.g., `"init"`
 	labels: Option<BTreeSet<property::StateFormula>>,
+	// The variable names, needed to look up values by name
+	variable_names: Option<Arc<[String]>>,
+	// The state values as evalexpr values, in the order of the variable names.
+	// Counts are floats so that expressions such as `S/2` do not truncate.
+	values: Vec<evalexpr::Value>,
+	// Labels defined by formulas, which are evaluated when first asked for
+	label_definitions: Option<Arc<[LabelDefinition]>>,
+	// Whether each defined label holds, cached once it is evaluated
+	label_cache: RefCell<BTreeMap<StateFormula, bool>>,
+}
+
+impl PartialEq for VasState {
+	fn eq(&self, other: &Self) -> bool {
+		self.vector == other.vector && self.labels == other.labels
+	}
 }
 
 impl VasState {
@@ -48,61 +77,147 @@ impl VasState {
 		Self {
 			vector,
 			labels: None,
+			variable_names: None,
+			values: Vec::new(),
+			label_definitions: None,
+			label_cache: RefCell::new(BTreeMap::new()),
+		}
+	}
+
+	/// Creates a new VasState whose values can be looked up by variable name,
+	/// e.g., when evaluating an expression over the state
+	pub fn with_variable_names(vector: VasStateVector, variable_names: Arc<[String]>) -> Self {
+		let values = vector
+			.iter()
+			.map(|&value| evalexpr::Value::Float(value as f64))
+			.collect();
+		Self {
+			vector,
+			labels: None,
+			variable_names: Some(variable_names),
+			values,
+			label_definitions: None,
+			label_cache: RefCell::new(BTreeMap::new()),
+		}
+	}
+
+	/// Creates a new VasState that also has the labels defined by formulas
+	/// over its variables, e.g., `"target"`
+	pub fn with_label_definitions(
+		vector: VasStateVector,
+		variable_names: Arc<[String]>,
+		label_definitions: Arc<[LabelDefinition]>,
+	) -> Self {
+		let mut state = Self::with_variable_names(vector, variable_names);
+		state.label_definitions = Some(label_definitions);
+		state
+	}
+
+	/// Assigns a label to the state, e.g., `"init"`
+	pub fn add_label(&mut self, label: StateFormula) {
+		self.labels.get_or_insert_with(BTreeSet::new).insert(label);
+	}
+
+	/// Whether a defined label holds, evaluating its formula the first time
+	fn defined_label_holds(&self, label: &StateFormula) -> bool {
+		if let Some(holds) = self.label_cache.borrow().get(label) {
+			return *holds;
 		}
+		let Some((_, definition)) = self
+			.label_definitions
+			.iter()
+			.flat_map(|definitions| definitions.iter())
+			.find(|(name, _)| name == label)
+		else {
+			return false;
+		};
+		let holds = definition.holds_in(self);
+		self.label_cache.borrow_mut().insert(label.clone(), holds);
+		holds
+	}
+
+	/// The index of a variable, if the state knows its variable names
+	fn variable_index(&self, var_name: &str) -> Option<usize> {
+		self.variable_names
+			.as_ref()?
+			.iter()
+			.position(|name| name == var_name)
 	}
 }
 
 impl property::Labeled for VasState {
 	type LabelType = property::StateFormula;
 
+	/// The assigned labels, then the defined labels that hold
 	fn labels(&self) -> impl Iterator<Item = &property::StateFormula> {
+		let defined = self
+			.label_definitions
+			.iter()
+			.flat_map(|definitions| definitions.iter())
+			.map(|(name, _)| name)
+			.filter(|name| self.defined_label_holds(name));
 		self.labels
 			.as_ref()
 			.map(|labels| labels.iter())
 			.into_iter()
 			.flatten()
+			.chain(defined)
 	}
 
 	fn has_label(&self, label: &Self::LabelType) -> bool {
 		self.labels
 			.as_ref()
 			.map_or(false, |labels| labels.contains(label))
+			|| self.defined_label_holds(label)
 	}
 }
 
 impl evalexpr::Context for VasState {
 	type NumericTypes = evalexpr::DefaultNumericTypes; // Use the default numeric types provided by evalexpr
 
-	fn get_value(&self, _identifier: &str) -> Option<&evalexpr::Value<Self::NumericTypes>> {
-		todo!()
+	/// The count of the variable with this name (requires the variable names)
+	fn get_value(&self, identifier: &str) -> Option<&evalexpr::Value<Self::NumericTypes>> {
+		self.variable_index(identifier)
+			.and_then(|index| self.values.get(index))
 	}
 
+	/// States define no functions of their own
 	fn call_function(
 		&self,
-		_identifier: &str,
+		identifier: &str,
 		_argument: &evalexpr::Value<Self::NumericTypes>,
 	) -> evalexpr::error::EvalexprResultValue<Self::NumericTypes> {
-		todo!()
+		Err(evalexpr::EvalexprError::FunctionIdentifierNotFound(
+			identifier.to_string(),
+		))
 	}
 
 	fn are_builtin_functions_disabled(&self) -> bool {
-		todo!()
+		false
 	}
 
 	fn set_builtin_functions_disabled(
 		&mut self,
-		_disabled: bool,
+		disabled: bool,
 	) -> evalexpr::EvalexprResult<(), Self::NumericTypes> {
-		todo!()
+		if disabled {
+			Err(evalexpr::EvalexprError::BuiltinFunctionsCannotBeDisabled)
+		} else {
+			Ok(())
+		}
 	}
-	// Implement required methods for evalexpr::Context
 }
 
 impl State for VasState {
 	type VariableValueType = u64;
 
-	fn valuate(&self, _var_name: &str) -> Self::VariableValueType {
-		todo!()
+	/// The count of the variable with this name. Panics if the state was
+	/// created without variable names or the name is unknown.
+	fn valuate(&self, var_name: &str) -> Self::VariableValueType {
+		match self.variable_index(var_name) {
+			Some(index) => self.vector[index] as u64,
+			None => panic!("Variable `{}` is not defined in this state", var_name),
+		}
 	}
 }
 
@@ -111,7 +226,11 @@ impl State for VasState {
 pub(crate) struct VasTransition {
 	pub(crate) transition_id: usize,
 	pub(crate) transition_name: String,
-	// The update vector
+	// The number of copies of each species consumed (the reactants)
+	pub(crate) consume_vector: VasStateVector,
+	// The number of copies of each species produced (the products)
+	pub(crate) produce_vector: VasStateVector,
+	// The update vector (produce_vector - consume_vector)
 	pub(crate) update_vector: VasStateVector,
 	// The minimum elementwise count for a transition to be enabled
 	pub(crate) enabled_bounds: VasStateVector,
@@ -122,8 +241,68 @@ pub(crate) struct VasTransition {
 	// rate_probability_at). The override must be stored in static
 	// memory for now (may change this later).
 	pub(crate) custom_rate_fn: Option<CustomRateFn>,
+	// How the rate constant relates to the propensity (set per model)
+	pub(crate) rate_convention: RateConvention,
+}
+
+/// The convention relating a mass-action rate constant to the propensity of
+/// a reaction that consumes `n` copies of a species with count `x`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum RateConvention {
+	/// The stochastic rate constant, multiplied by the number of ways to
+	/// choose the reactants, `x choose n` (as in the SSA and PRISM models).
+	#[default]
+	Stochastic,
+	/// The deterministic rate constant, multiplied by `x (x-1) ... (x-n+1)`,
+	/// i.e., the law `k x^n` written with distinct molecules (as in SBML).
+	Deterministic,
 }
 
+impl RateConvention {
+	/// The factor contributed by a species with count `count` of which
+	/// `consumed` copies take part in the reaction
+	pub fn species_factor(&self, count: VasValue, consumed: VasValue) -> ProbabilityOrRate {
+		if count < consumed {
+			return 0.0;
+		}
+		(0..consumed).fold(1.0, |factor, i| {
+			let falling = factor * (count - i) as ProbabilityOrRate;
+			match self {
+				RateConvention::Stochastic => falling / (i + 1) as ProbabilityOrRate,
+				RateConvention::Deterministic => falling,
+			}
+		})
+	}
+}
+
+impl std::str::FromStr for RateConvention {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"stochastic" => Ok(RateConvention::Stochastic),
+			"deterministic" => Ok(RateConvention::Deterministic),
+			_ => Err(format!(
+				"Unknown kinetics `{}` (expected `stochastic` or `deterministic`)",
+				s
+			)),
+		}
+	}
+}
+
+impl fmt::Display for RateConvention {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RateConvention::Stochastic => write!(f, "stochastic"),
+			RateConvention::Deterministic => write!(f, "deterministic"),
+		}
+	}
+}
+
+/// A rate function of the state, as stored in a `CustomRateFn`
+pub(crate) type RateFn =
+	std::sync::Arc<dyn Fn(&VasState) -> ProbabilityOrRate + Send + Sync + 'static>;
+
 #[derive(Clone)]
 pub(crate) struct CustomRateFn(
 	std::sync::Arc<dyn Fn(&VasState) -> ProbabilityOrRate + Send + Sync + 'static>,
@@ -151,10 +330,7 @@ impl CustomRateFn {
 }
 
 impl VasTransition {
-	pub fn set_custom_rate_fn(
-		&mut self,
-		rate_fn: std::sync::Arc<dyn Fn(&VasState) -> ProbabilityOrRate + Send + Sync + 'static>,
-	) {
+	pub fn set_custom_rate_fn(&mut self, rate_fn: RateFn) {
 		self.custom_rate_fn = Some(CustomRateFn(rate_fn));
 	}
 
@@ -165,42 +341,128 @@ impl VasTransition {
 		decrement: Box<[VasValue]>,
 		rate_const: ProbabilityOrRate,
 	) -> Self {
+		let consume_vector = DVector::from_iterator(decrement.len(), decrement);
+		let produce_vector = DVector::from_iterator(increment.len(), increment);
 		Self {
 			transition_id,
 			transition_name,
-			// update_vector: DVector::from_data(increment) - DVector::from_data(decrement),
-			update_vector: DVector::from_iterator(
-				increment.len(),
-				increment
-					.iter()
-					.zip(decrement.iter())
-					.map(|(inc, dec)| *inc - *dec),
-			),
-			enabled_bounds: DVector::from_iterator(decrement.len(), decrement),
+			update_vector: &produce_vector - &consume_vector,
+			enabled_bounds: consume_vector.clone(),
+			consume_vector,
+			produce_vector,
 			rate_const,
 			custom_rate_fn: None,
+			rate_convention: RateConvention::default(),
+		}
+	}
+
+	/// This transition in a larger model with `num_variables` variables, where
+	/// variable `i` of this transition's model becomes variable `index_map[i]`.
+	/// A custom rate is still evaluated over the variables of the original model.
+	pub fn embedded(
+		&self,
+		transition_id: usize,
+		transition_name: String,
+		num_variables: usize,
+		index_map: &[usize],
+	) -> Self {
+		let embed = |vector: &VasStateVector| {
+			let mut embedded = VasStateVector::zeros(num_variables);
+			for (i, value) in vector.iter().enumerate() {
+				// Shared variables may receive updates from more than one variable
+				embedded[index_map[i]] += value;
+			}
+			embedded
+		};
+		let mut transition = Self {
+			transition_id,
+			transition_name,
+			consume_vector: embed(&self.consume_vector),
+			produce_vector: embed(&self.produce_vector),
+			update_vector: embed(&self.update_vector),
+			enabled_bounds: embed(&self.enabled_bounds),
+			rate_const: self.rate_const,
+			custom_rate_fn: None,
+			rate_convention: self.rate_convention,
+		};
+		if let Some(CustomRateFn(rate_fn)) = &self.custom_rate_fn {
+			let rate_fn = rate_fn.clone();
+			let index_map: Vec<usize> = index_map.to_vec();
+			transition.set_custom_rate_fn(std::sync::Arc::new(move |state: &VasState| {
+				let projected = index_map
+					.iter()
+					.map(|&i| state.vector[i])
+					.collect::<Vec<_>>();
+				rate_fn(&VasState::new(DVector::from_vec(projected)))
+			}));
 		}
+		transition
+	}
+
+	/// Species that are both consumed and produced, e.g., enzymes
+	pub fn catalysts(&self) -> Vec<usize> {
+		(0..self.consume_vector.len())
+			.filter(|&i| self.consume_vector[i] > 0 && self.produce_vector[i] > 0)
+			.collect()
 	}
 
-	/// Calculates the SCK rate of the transition.
-	/// This function is temporary and intended only for quick C&C result generation ---
-	/// it will eventually be replaced by a system-wide more-powerful rate calculation
-	/// that allows for more complex rate calculations.
+	/// The reaction as a string, e.g., `2 X + E -> Y + E`
+	pub fn reaction_string(&self, variable_names: &[String]) -> String {
+		let side = |vector: &VasStateVector| {
+			let terms = vector
+				.iter()
+				.zip(variable_names.iter())
+				.filter(|(count, _)| **count > 0)
+				.map(|(count, name)| match *count {
+					1 => name.clone(),
+					_ => format!("{} {}", count, name),
+				})
+				.collect::<Vec<_>>();
+			if terms.is_empty() {
+				"0".to_string()
+			} else {
+				terms.join(" + ")
+			}
+		};
+		format!(
+			"{} -> {}",
+			side(&self.consume_vector),
+			side(&self.produce_vector)
+		)
+	}
+
+	/// Calculates the SCK rate (the mass-action propensity) of the transition.
+	/// Each species consumed contributes a combinatorial factor in
+	/// its count, as set by the model's rate convention. This is the single
+	/// rate calculation used by `rate_probability_at` and the builders.
 	pub fn get_sck_rate(&self, state: &VasStateVector) -> ProbabilityOrRate {
+		if let Some(rate_fn) = &self.custom_rate_fn {
+			return (rate_fn.0)(&VasState::new(state.clone()));
+		}
 		self.rate_const
 			* self
-				.enabled_bounds
+				.consume_vector
 				.iter()
 				.zip(state.iter())
-				.filter(|(bound, _)| **bound != 0)
-				.map(|(_, &s)| s as ProbabilityOrRate)
+				.filter(|(consumed, _)| **consumed != 0)
+				.map(|(&consumed, &s)| self.rate_convention.species_factor(s, consumed))
 				.product::<ProbabilityOrRate>()
 	}
 
+	/// The weight of the transition in a discrete-time model: its custom rate
+	/// law, or else its rate constant, without any mass-action factors.
+	pub fn get_weight(&self, state: &VasStateVector) -> ProbabilityOrRate {
+		if let Some(rate_fn) = &self.custom_rate_fn {
+			return (rate_fn.0)(&VasState::new(state.clone()));
+		}
+		self.rate_const
+	}
+
 	/// Check to see if our state is above every bound in the enabled
 	/// bound. We use try-fold to short circuit and return false if we
 	/// encounter at least one value that does not satisfy.
 	/// This function is used with a plain state vector rather than object.
+	/// A transition with a custom rate is also disabled where its rate is not positive.
 	pub fn enabled_vector(&self, state: &VasStateVector) -> bool {
 		self.enabled_bounds
 			.iter()
@@ -213,6 +475,10 @@ impl VasTransition {
 				}
 			})
 			.is_some()
+			&& self
+				.custom_rate_fn
+				.as_ref()
+				.is_none_or(|rate_fn| (rate_fn.0)(&VasState::new(state.clone())) > 0.0)
 	}
 }
 
@@ -236,6 +502,10 @@ impl Transition for VasTransition {
 				}
 			})
 			.is_some()
+			&& self
+				.custom_rate_fn
+				.as_ref()
+				.is_none_or(|rate_fn| (rate_fn.0)(state) > 0.0)
 	}
 
 	fn rate_probability_at(&self, state: &VasState) -> Option<ProbabilityOrRate> {
@@ -246,17 +516,7 @@ impl Transition for VasTransition {
 			} else {
 				// Compute the transition rate using the same equation that
 				// is used for the chemical kinetics equation
-				self.rate_const
-					* self
-						.update_vector
-						.zip_fold(&state.vector, 1.0, |acc, state_i, update_i| {
-							if (update_i as ProbabilityOrRate) <= 0.0 {
-								acc * (state_i as ProbabilityOrRate)
-									.powf(-(update_i as ProbabilityOrRate))
-							} else {
-								acc
-							}
-						})
+				self.get_sck_rate(&state.vector)
 			};
 			Some(rate)
 		} else {
@@ -267,10 +527,14 @@ impl Transition for VasTransition {
 	fn next_state(&self, state: &VasState) -> Option<Self::StateType> {
 		let enabled = self.enabled(state);
 		if enabled {
-			Some(VasState {
-				vector: &state.vector + &self.update_vector.map(|val| val),
-				labels: state.labels.clone(),
-			})
+			let vector = &state.vector + &self.update_vector.map(|val| val);
+			let mut next_state = match &state.variable_names {
+				Some(names) => VasState::with_variable_names(vector, names.clone()),
+				None => VasState::new(vector),
+			};
+			next_state.labels = state.labels.clone();
+			next_state.label_definitions = state.label_definitions.clone();
+			Some(next_state)
 		} else {
 			None
 		}
@@ -293,16 +557,79 @@ impl Transition for VasTransition {
 #[derive(Clone, Debug)]
 pub struct VasProperty {
 	pub(crate) variable_index: usize,
+	pub(crate) relation: AllowedRelation,
 	pub(crate) target_value: VasValue,
+	/// Weighted species counts `(variable_index, coefficient)` for targets such as
+	/// `S1 + 2*S2 >= 40`. Empty when the property compares only `variable_index`.
+	pub(crate) linear_terms: Vec<(usize, VasValue)>,
+}
+
+impl VasProperty {
+	/// Whether the property compares a weighted sum of species rather than a single species.
+	pub fn is_linear(&self) -> bool {
+		!self.linear_terms.is_empty()
+	}
+
+	/// The indices of all variables the property refers to.
+	pub fn variable_indices(&self) -> Vec<usize> {
+		if self.is_linear() {
+			self.linear_terms.iter().map(|(index, _)| *index).collect()
+		} else {
+			vec![self.variable_index]
+		}
+	}
+
+	/// Evaluates the left-hand side of the property in the given state vector.
+	/// Returns None if a variable index is out of bounds for the state.
+	pub fn evaluate(&self, state: &VasStateVector) -> Option<VasValue> {
+		if self.is_linear() {
+			self.linear_terms
+				.iter()
+				.map(|(index, coefficient)| state.get(*index).map(|value| coefficient * value))
+				.sum()
+		} else {
+			state.get(self.variable_index).copied()
+		}
+	}
+
+	/// Checks whether the property holds in the given state vector.
+	/// Returns false if a variable index is out of bounds for the state.
+	pub fn is_satisfied(&self, state: &VasStateVector) -> bool {
+		self.evaluate(state)
+			.is_some_and(|value| self.relation.holds(value, self.target_value))
+	}
+
+	/// Finds the value of the target variable closest to `initial_value` that
+	/// satisfies the property. For `!=` we step upward from the initial value.
+	pub fn closest_satisfying_value(&self, initial_value: VasValue) -> VasValue {
+		match self.relation {
+			AllowedRelation::Equal => self.target_value,
+			AllowedRelation::NotEqual => {
+				if initial_value == self.target_value {
+					initial_value + 1
+				} else {
+					initial_value
+				}
+			}
+			AllowedRelation::LessThan => initial_value.min(self.target_value - 1),
+			AllowedRelation::LessThanOrEqual => initial_value.min(self.target_value),
+			AllowedRelation::GreaterThan => initial_value.max(self.target_value + 1),
+			AllowedRelation::GreaterThanOrEqual => initial_value.max(self.target_value),
+		}
+	}
 }
 
 /// The data for an abstract Vector Addition System
 pub(crate) struct AbstractVas {
 	pub(crate) variable_names: Box<[String]>,
 	pub(crate) initial_states: Vec<VasState>,
+	// The probability of starting in each initial state, summing to 1
+	pub(crate) initial_weights: Vec<ProbabilityOrRate>,
 	pub(crate) transitions: Vec<VasTransition>,
 	pub(crate) m_type: ModelType,
-	pub(crate) target: VasProperty,
+	pub(crate) target: VasTarget,
+	// The variable names and label definitions shared by labeled states, built on first use
+	labeling: OnceLock<Labeling>,
 	// pub(crate) z3_context: Option<z3::Context>, // Removed because z3::Context and z3::Config do not implement Clone
 }
 
@@ -327,62 +654,222 @@ impl AbstractModel for AbstractVas {
 	}
 }
 
+/// The comparison operators allowed in a target property
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AllowedRelation {
 	Equal,
+	NotEqual,
 	LessThan,
+	LessThanOrEqual,
 	GreaterThan,
+	GreaterThanOrEqual,
+}
+
+impl AllowedRelation {
+	/// Evaluates `lhs <relation> rhs`
+	pub fn holds<T: PartialOrd>(&self, lhs: T, rhs: T) -> bool {
+		match self {
+			AllowedRelation::Equal => lhs == rhs,
+			AllowedRelation::NotEqual => lhs != rhs,
+			AllowedRelation::LessThan => lhs < rhs,
+			AllowedRelation::LessThanOrEqual => lhs <= rhs,
+			AllowedRelation::GreaterThan => lhs > rhs,
+			AllowedRelation::GreaterThanOrEqual => lhs >= rhs,
+		}
+	}
+
+	/// The relation that holds exactly when this one does not
+	pub fn negate(&self) -> AllowedRelation {
+		match self {
+			AllowedRelation::Equal => AllowedRelation::NotEqual,
+			AllowedRelation::NotEqual => AllowedRelation::Equal,
+			AllowedRelation::LessThan => AllowedRelation::GreaterThanOrEqual,
+			AllowedRelation::LessThanOrEqual => AllowedRelation::GreaterThan,
+			AllowedRelation::GreaterThan => AllowedRelation::LessThanOrEqual,
+			AllowedRelation::GreaterThanOrEqual => AllowedRelation::LessThan,
+		}
+	}
+
+	/// The relation with its operands swapped, i.e., `a < b` becomes `b > a`
+	pub fn flip(&self) -> AllowedRelation {
+		match self {
+			AllowedRelation::Equal => AllowedRelation::Equal,
+			AllowedRelation::NotEqual => AllowedRelation::NotEqual,
+			AllowedRelation::LessThan => AllowedRelation::GreaterThan,
+			AllowedRelation::LessThanOrEqual => AllowedRelation::GreaterThanOrEqual,
+			AllowedRelation::GreaterThan => AllowedRelation::LessThan,
+			AllowedRelation::GreaterThanOrEqual => AllowedRelation::LessThanOrEqual,
+		}
+	}
+}
+
+impl std::str::FromStr for AllowedRelation {
+	type Err = String;
+
+	/// Parses a comparison operator as written in a model file
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"=" | "==" => Ok(AllowedRelation::Equal),
+			"!=" => Ok(AllowedRelation::NotEqual),
+			"<" => Ok(AllowedRelation::LessThan),
+			"<=" => Ok(AllowedRelation::LessThanOrEqual),
+			">" => Ok(AllowedRelation::GreaterThan),
+			">=" => Ok(AllowedRelation::GreaterThanOrEqual),
+			_ => Err(format!("Unknown relation `{}`", s)),
+		}
+	}
 }
 
 impl fmt::Display for AllowedRelation {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let relation_str = match self {
 			AllowedRelation::Equal => "=",
+			AllowedRelation::NotEqual => "!=",
 			AllowedRelation::LessThan => "<",
+			AllowedRelation::LessThanOrEqual => "<=",
 			AllowedRelation::GreaterThan => ">",
+			AllowedRelation::GreaterThanOrEqual => ">=",
 		};
 		write!(f, "{}", relation_str)
 	}
 }
 
-// TODO: May need to allow discrete/continuous time models
-// for now we will just use continuous time models
-
 impl AbstractVas {
 	pub fn new(
 		variable_names: Box<[String]>,
 		initial_states: Vec<VasState>,
 		transitions: Vec<VasTransition>,
-		target: VasProperty,
+		target: VasTarget,
 	) -> Self {
+		// The initial states are equally likely until weights are set
+		let initial_weights =
+			vec![1.0 / initial_states.len() as ProbabilityOrRate; initial_states.len()];
 		Self {
 			variable_names,
 			initial_states,
+			initial_weights,
 			transitions,
 			m_type: ModelType::ContinuousTime,
 			target,
+			labeling: OnceLock::new(),
 			// z3_context: None, // z3_context is not initialized here
 		}
 	}
 
-	/// Calls a parser to get a VAS model from a file
+	/// Sets the weights of the initial states, which are normalized into a
+	/// probability distribution
+	pub fn set_initial_weights(&mut self, weights: Vec<ProbabilityOrRate>) -> Result<(), String> {
+		if weights.len() != self.initial_states.len() {
+			return Err(format!(
+				"Expected {} initial weights, got {}",
+				self.initial_states.len(),
+				weights.len()
+			));
+		}
+		if weights.iter().any(|w| !w.is_finite() || *w <= 0.0) {
+			return Err("Initial weights must be positive".to_string());
+		}
+		let total: ProbabilityOrRate = weights.iter().sum();
+		self.initial_weights = weights.iter().map(|w| w / total).collect();
+		Ok(())
+	}
+
+	/// The initial states with the probability of starting in each
+	pub fn initial_distribution(&self) -> impl Iterator<Item = (&VasState, ProbabilityOrRate)> {
+		self.initial_states
+			.iter()
+			.zip(self.initial_weights.iter().copied())
+	}
+
+	/// Samples the index of an initial state from the initial distribution
+	pub fn sample_initial_index(&self) -> usize {
+		let mut remaining: ProbabilityOrRate = rand::rng().random();
+		for (i, weight) in self.initial_weights.iter().enumerate() {
+			if remaining < *weight {
+				return i;
+			}
+			remaining -= weight;
+		}
+		// Rounding may leave a sliver of probability past the last state
+		self.initial_states.len() - 1
+	}
+
+	/// The smallest and largest value of a variable over the initial states
+	pub fn initial_value_range(&self, variable_index: usize) -> (VasValue, VasValue) {
+		let values = self
+			.initial_states
+			.iter()
+			.map(|state| state.vector[variable_index]);
+		(values.clone().min().unwrap_or(0), values.max().unwrap_or(0))
+	}
+
+	/// Sets whether the model is a CTMC, whose transitions have rates, or a
+	/// DTMC, whose transitions have weights normalized in each state
+	pub fn set_model_type(&mut self, m_type: ModelType) {
+		self.m_type = m_type;
+	}
 
-	pub fn from_file(filename: &str) -> Result<Self, String> {
-		match vas_file_reader::build_model(filename) {
+	/// Sets the rate convention of every transition in the model
+	pub fn set_rate_convention(&mut self, rate_convention: RateConvention) {
+		for transition in self.transitions.iter_mut() {
+			transition.rate_convention = rate_convention;
+		}
+	}
+
+	/// The rate convention of the model's transitions
+	pub fn rate_convention(&self) -> RateConvention {
+		self.transitions
+			.first()
+			.map(|t| t.rate_convention)
+			.unwrap_or_default()
+	}
+
+	/// Calls a parser to get a VAS model from a file, collecting the parse errors.
+	/// Files ending in `.sm` are read as PRISM models, `.xml` or `.sbml` as SBML
+	/// models, and all others as CRN/VAS models.
+	pub fn parse_file(
+		filename: &str,
+		options: &ModelOptions,
+	) -> Result<Self, Vec<Box<dyn ModelParseError>>> {
+		if filename.ends_with(".sm") {
+			PrismParser::parse(filename, options)
+				.map_err(|err| vec![Box::new(err) as Box<dyn ModelParseError>])
+		} else if filename.ends_with(".xml") || filename.ends_with(".sbml") {
+			SbmlParser::parse(filename, options)
+				.map_err(|err| vec![Box::new(err) as Box<dyn ModelParseError>])
+		} else {
+			vas_file_reader::build_model(filename, options).map_err(|errors| {
+				errors
+					.into_iter()
+					.map(|err| Box::new(err) as Box<dyn ModelParseError>)
+					.collect()
+			})
+		}
+	}
+
+	/// Calls a parser to get a VAS model from a file, reporting any parse errors
+	pub fn from_file(filename: &str, options: &ModelOptions) -> Result<Self, String> {
+		match Self::parse_file(filename, options) {
 			Ok(model) => {
 				debug_message!("Parsing gave OK result");
 				Ok(model)
 			}
-			Err(err) => {
-				error!("ERROR DURING PARSING: {}", err);
-				Err(err.to_string())
+			Err(errors) => {
+				let err = errors
+					.iter()
+					.map(|err| format!("{}", err.as_ref()))
+					.collect::<Vec<_>>()
+					.join("\n");
+				error!("ERROR DURING PARSING:\n{}", err);
+				Err(err)
 			}
 		}
 	}
 
 	/// Runs the validator on the model and its property
 
-	pub fn validate_model(&self, property: VasProperty) -> Result<String, String> {
-		let result = validate_vas(self, &property);
+	pub fn validate_model(&self, target: VasTarget) -> Result<String, String> {
+		let result = validate_vas(self, &target);
 		result
 	}
 
@@ -409,6 +896,7 @@ impl AbstractVas {
 		output.push_str(&format!("VasModel:"));
 		output.push_str(&format!("Variables: {:?}", self.variable_names));
 		output.push_str(&format!("Initial States: {:?}", self.initial_states));
+		output.push_str(&format!("Initial Weights: {:?}", self.initial_weights));
 		output.push_str(&format!("Transitions: {:?}", self.transitions));
 		output
 	}
@@ -426,19 +914,25 @@ impl AbstractVas {
 			.for_each(|name| output.push_str(&format!("\t{}", name)));
 		output.push_str("\n");
 		output.push_str("Initial States:\n");
-		for state in self.initial_states.clone() {
+		for (state, weight) in self.initial_distribution() {
 			state
 				.vector
 				.iter()
 				.for_each(|name| output.push_str(&format!("\t{}", name)));
+			output.push_str(&format!("\t(p = {})\n", weight));
 		}
-		output.push_str("\n");
+		output.push_str(&format!("Model Type:\t{}\n", self.m_type));
+		output.push_str(&format!("Kinetics:\t{}\n", self.rate_convention()));
 		output.push_str("Transitions:\n");
 		for transition in self.transitions.clone() {
 			output.push_str(&format!(
 				"\t{}\t{}\n",
 				transition.transition_id, transition.transition_name
 			));
+			output.push_str(&format!(
+				"\t\tReaction:\t{}\n",
+				transition.reaction_string(&self.variable_names)
+			));
 			output.push_str("\t\tUpdate:\t[");
 			transition
 				.update_vector
@@ -453,13 +947,9 @@ impl AbstractVas {
 		}
 		output.push_str("Target:\n");
 		output.push_str(&format!(
-			"\tVariable: {}\n",
-			self.variable_names
-				.get(self.target.variable_index)
-				.map(|s| s.as_str())
-				.unwrap_or("Unknown")
+			"\t{}\n",
+			self.target.to_string_with_names(&self.variable_names)
 		));
-		output.push_str(&format!("\tTarget Value: {}\n", self.target.target_value));
 		output.push_str("==========================================\n");
 		output.push_str("               END VAS MODEL              \n");
 		output.push_str("==========================================\n");
@@ -471,12 +961,7 @@ impl AbstractVas {
 		let available_transitions = self
 			.transitions
 			.iter()
-			.filter(|t| {
-				t.enabled_bounds
-					.iter()
-					.zip(current_state.iter())
-					.all(|(bound, &val)| val >= (*bound).try_into().unwrap())
-			})
+			.filter(|t| t.enabled_vector(current_state))
 			.map(|t| t.transition_id)
 			.collect();
 		// debug_message!("Available transitions for {:?}: {:?}", current_state, available_transitions);
@@ -491,17 +976,50 @@ impl AbstractVas {
 	) -> Vec<usize> {
 		let available_transitions = subset
 			.iter()
-			.filter(|t| {
-				t.enabled_bounds
-					.iter()
-					.zip(current_state.iter())
-					.all(|(bound, &val)| val >= (*bound).try_into().unwrap())
-			})
+			.filter(|t| t.enabled_vector(current_state))
 			.map(|t| t.transition_id)
 			.collect();
 		available_transitions
 	}
 
+	/// The labels that can hold in a state of the model, i.e., `"target"`
+	/// defined by the model's target
+	fn labeling(&self) -> &Labeling {
+		self.labeling.get_or_init(|| {
+			let definitions = match self.target.to_state_formula(&self.variable_names) {
+				Ok(formula) => vec![(StateFormula::label("target"), formula)],
+				Err(e) => {
+					warning!("Cannot label target states: {}", e);
+					Vec::new()
+				}
+			};
+			(self.variable_names.clone().into(), definitions.into())
+		})
+	}
+
+	/// A state of the model that is labeled by the model's label definitions
+	pub fn labeled_state(&self, vector: VasStateVector) -> VasState {
+		let (variable_names, definitions) = self.labeling();
+		VasState::with_label_definitions(vector, variable_names.clone(), definitions.clone())
+	}
+
+	/// The labels of a state in an explicit model, e.g., `"target"` if the
+	/// model's target holds in it. Targets that compare single species are
+	/// checked on the vector directly.
+	pub fn state_labels(&self, vector: &VasStateVector) -> BTreeSet<StateFormula> {
+		if !self.target.has_linear_atoms() {
+			return if self.target.is_satisfied(vector) {
+				BTreeSet::from([StateFormula::label("target")])
+			} else {
+				BTreeSet::new()
+			};
+		}
+		self.labeled_state(vector.clone())
+			.labels()
+			.cloned()
+			.collect()
+	}
+
 	/// Calculates the transition probability for a given transition in the context
 	/// of the current state under the SCK assumption for CRN models.
 	pub fn crn_total_outgoing_rate(&self, current_state: &VasStateVector) -> ProbabilityOrRate {
@@ -525,13 +1043,61 @@ impl AbstractVas {
 		total_outgoing_rate
 	}
 
+	/// The sum of the weights of the transitions enabled in a state of a DTMC
+	pub fn total_outgoing_weight(&self, current_state: &VasStateVector) -> ProbabilityOrRate {
+		self.get_available_transitions(current_state)
+			.iter()
+			.filter_map(|t| self.get_transition_from_id(*t))
+			.map(|t| t.get_weight(current_state))
+			.sum()
+	}
+
+	/// The rate of a transition in a state of a CTMC, or its probability in a
+	/// state of a DTMC, where enabled weights are normalized to sum to 1.
+	/// A transition that is not enabled has rate 0.
+	pub fn transition_rate(
+		&self,
+		current_state: &VasStateVector,
+		transition: &VasTransition,
+	) -> ProbabilityOrRate {
+		if !transition.enabled_vector(current_state) {
+			return 0.0;
+		}
+		match self.m_type {
+			ModelType::ContinuousTime => transition.get_sck_rate(current_state),
+			ModelType::DiscreteTime => {
+				let total_weight = self.total_outgoing_weight(current_state);
+				if total_weight > 0.0 {
+					transition.get_weight(current_state) / total_weight
+				} else {
+					0.0
+				}
+			}
+		}
+	}
+
+	/// The total outgoing rate of a state of a CTMC, or the total outgoing
+	/// probability of a state of a DTMC (1 unless no transition is enabled).
+	pub fn total_outgoing_rate(&self, current_state: &VasStateVector) -> ProbabilityOrRate {
+		match self.m_type {
+			ModelType::ContinuousTime => self.crn_total_outgoing_rate(current_state),
+			ModelType::DiscreteTime => {
+				if self.total_outgoing_weight(current_state) > 0.0 {
+					1.0
+				} else {
+					0.0
+				}
+			}
+		}
+	}
+
 	/// Calculates the probability for a transition given the current state
 	pub fn transition_probability(
 		&self,
 		current_state: &VasStateVector,
 		transition: &VasTransition,
 	) -> ProbabilityOrRate {
-		let total_outgoing_rate = self.crn_total_outgoing_rate(current_state);
+		let total_outgoing_rate = self.total_outgoing_rate(current_state);
 		if total_outgoing_rate == 0.0 {
 			warning!(
 				"No outgoing transitions from state {}, returning 0 probability. Transition {}",
@@ -547,11 +1113,8 @@ impl AbstractVas {
 			);
 			return 0.0; // No outgoing transitions, return 0 probability
 		}
-		if let Some(transition_rate) = transition.rate_probability_at(&VasState {
-			vector: current_state.clone(),
-			labels: None,
-		}) {
-			transition_rate / total_outgoing_rate
+		if transition.enabled_vector(current_state) {
+			self.transition_rate(current_state, transition) / total_outgoing_rate
 		} else {
 			0.0 // Transition not enabled, return 0 probability
 		}
@@ -561,7 +1124,7 @@ impl AbstractVas {
 #[derive(Clone)]
 /// Transition data for Prism export of a VAS
 pub(crate) struct PrismVasTransition {
-	pub(crate) transition_id: usize,
+	pub(crate) transition_id: Option<usize>, // The abstract transition, if the transition comes from one
 	pub(crate) from_state: usize,
 	pub(crate) to_state: usize,
 	pub(crate) rate: ProbabilityOrRate,
@@ -571,12 +1134,31 @@ pub(crate) struct PrismVasTransition {
 #[derive(Clone)]
 pub(crate) struct PrismVasState {
 	pub(crate) state_id: usize,
-	pub(crate) vector: DVector<i128>,
-	pub(crate) label: Option<String>, // Optional label for the state, useful for sink states
-	pub(crate) used_rate: ProbabilityOrRate, // Optional total outgoing rate for the state
+	pub(crate) vector: Option<DVector<i128>>, // None for sinks that stand for other states, e.g., the absorbing state
+	pub(crate) labels: BTreeSet<StateFormula>, // Labels of the state, e.g., `"init"` or `"absorbing"`
+	pub(crate) used_rate: ProbabilityOrRate,  // Optional total outgoing rate for the state
 	pub(crate) total_outgoing_rate: ProbabilityOrRate, // Optional total outgoing rate for the state
 }
 
+impl PrismVasState {
+	/// The entries of the state vector, which sinks that stand for other states do not have
+	pub fn entries(&self) -> &[i128] {
+		self.vector.as_ref().map_or(&[], |vector| vector.as_slice())
+	}
+}
+
+impl Labeled for PrismVasState {
+	type LabelType = StateFormula;
+
+	fn labels(&self) -> impl Iterator<Item = &StateFormula> {
+		self.labels.iter()
+	}
+
+	fn has_label(&self, label: &StateFormula) -> bool {
+		self.labels.contains(label)
+	}
+}
+
 /// The data for an explicit Prism export of a VAS
 // TODO: Do we want to have a target stored here?
 #[derive(Clone)]
@@ -585,34 +1167,38 @@ pub(crate) struct PrismVasModel {
 	pub(crate) states: Vec<PrismVasState>,
 	pub(crate) transitions: Vec<PrismVasTransition>,
 	pub(crate) m_type: ModelType,
-	pub(crate) state_trie: VasTrieNode, // Optional trie for storing states, if needed
+	pub(crate) state_index: HashMap<VasStateVector, usize>, // State vector to state ID lookup, kept by `add_state`
 	pub(crate) trace_trie: TraceTrieNode, // Optional trie for storing traces, if needed
 	pub(crate) transition_map: HashMap<usize, Vec<(usize, usize)>>, // Quick transition from-(to, transition id) lookup
+	pub(crate) initial_distribution: Vec<(usize, ProbabilityOrRate)>, // (state id, probability) for each initial state
+	// The sparse matrix of the transitions, built on first use and cleared by `add_state` and `add_transition`
+	matrix: OnceLock<SparseMatrix>,
 }
 
 /// Default implementation for PrismVasModel
 impl Default for PrismVasModel {
 	fn default() -> Self {
 		// Create the absorbing state
-		let absorbing_state = DVector::from_element(0, -1);
 		let absorbing_state_id = 0;
 		// Add the absorbing state to the prism states
 		let mut states = Vec::new();
 		states.push(PrismVasState {
 			state_id: absorbing_state_id,
-			vector: absorbing_state,
-			label: Some("absorbing".to_string()), // Label for the absorbing state
-			used_rate: 0.0,                       // No used rate for the absorbing state
-			total_outgoing_rate: 0.0,             // No outgoing rate for the absorbing state
+			vector: None, // The absorbing state stands for the unexplored states
+			labels: BTreeSet::from([StateFormula::label("absorbing")]),
+			used_rate: 0.0,           // No used rate for the absorbing state
+			total_outgoing_rate: 0.0, // No outgoing rate for the absorbing state
 		});
 		PrismVasModel {
 			variable_names: Vec::new(),
 			states: states,
 			transitions: Vec::new(),
 			m_type: ModelType::ContinuousTime,
-			state_trie: VasTrieNode::new(),   // No trie by default
+			state_index: HashMap::new(),      // No indexed states by default
 			transition_map: HashMap::new(),   // No transitions by default
 			trace_trie: TraceTrieNode::new(), // No trace trie by default
+			initial_distribution: Vec::new(), // No initial states by default
+			matrix: OnceLock::new(),          // Built on first use
 		}
 	}
 }
@@ -620,66 +1206,75 @@ impl Default for PrismVasModel {
 impl ExplicitModel for PrismVasModel {
 	type StateType = VasState;
 	type TransitionType = VasTransition;
-	type MatrixType = (); // TODO: There is no matrix type for PrismVasModel, using this placeholder
+	type MatrixType = SparseMatrix;
 
 	/// Maps the state to a state index (in our case just a usize)
 	fn state_to_index(&self, state: &Self::StateType) -> Option<usize> {
-		for my_state in self.states.iter() {
-			if my_state.vector == state.vector.map(|v| v as i128) {
-				return Some(my_state.state_id);
-			}
-		}
-		None
+		self.state_id(&state.vector)
 	}
 
 	/// Like `state_to_index` but if the state is not present adds it and
 	/// assigns it a new index
 	fn find_or_add_index(&mut self, state: &Self::StateType) -> usize {
-		let index = self.state_to_index(state);
-		if let Some(idx) = index {
-			return idx; // State already exists, return its index
-		} else {
-			let new_index = self.states.len();
-			self.states.push(PrismVasState {
-				state_id: new_index,
-				vector: state.vector.map(|v| v as i128),
-				label: None,              // No label by default
+		match self.state_id(&state.vector) {
+			Some(idx) => idx, // State already exists, return its index
+			None => self.add_state(PrismVasState {
+				state_id: self.states.len(),
+				vector: Some(state.vector.clone()),
+				labels: BTreeSet::new(),  // No labels by default
 				used_rate: 0.0,           // No used rate by default
 				total_outgoing_rate: 0.0, // No outgoing rate by default
-			});
-			return new_index; // Return the newly added index
+			}),
 		}
 	}
 
 	/// Reserve an index in the explicit model (useful for artificially introduced absorbing
 	/// states). Returns whether or not the index was able to be reserved.
-	fn reserve_index(&mut self, _index: usize) -> bool {
-		todo!()
+	/// Only the next index can be reserved, which adds a state without a vector.
+	fn reserve_index(&mut self, index: usize) -> bool {
+		if index != self.states.len() {
+			return false;
+		}
+		self.matrix.take();
+		self.states.push(PrismVasState {
+			state_id: index,
+			vector: None,
+			labels: BTreeSet::new(),
+			used_rate: 0.0,
+			total_outgoing_rate: 0.0,
+		});
+		true
 	}
 
 	/// The number of states added to our model so far
 	fn state_count(&self) -> usize {
-		todo!()
+		self.states.len()
 	}
 
 	/// The type of this model
 	fn model_type(&self) -> ModelType {
-		todo!()
+		self.m_type
 	}
 
-	/// Adds an entry to the sparse matrix
+	/// Adds an entry to the sparse matrix, i.e., a transition that does not
+	/// come from a transition of the abstract model
 	fn add_entry(
 		&mut self,
-		_from_idx: usize,
-		_to_idx: usize,
-		_entry: <Self::TransitionType as Transition>::RateOrProbabilityType,
+		from_idx: usize,
+		to_idx: usize,
+		entry: <Self::TransitionType as Transition>::RateOrProbabilityType,
 	) {
-		todo!()
+		self.add_transition(PrismVasTransition {
+			transition_id: None,
+			from_state: from_idx,
+			to_state: to_idx,
+			rate: entry,
+		});
 	}
 
-	/// Converts this model into a sparse matrix
+	/// A copy of the sparse matrix of this model (see `matrix`)
 	fn to_matrix(&self) -> Self::MatrixType {
-		todo!()
+		self.matrix().clone()
 	}
 
 	/// Whether or not this model has not been expanded yet/is empty
@@ -704,38 +1299,50 @@ impl PrismVasModel {
 		model.variable_names = abstract_model.variable_names.clone().into_vec();
 		model.m_type = abstract_model.m_type;
 		// Create the absorbing state
-		let absorbing_state = DVector::from_element(model.variable_names.len(), -1);
 		let absorbing_state_id = 0;
 		model.states = Vec::new();
 		// Add the absorbing state to the prism states
 		model.add_state(PrismVasState {
 			state_id: absorbing_state_id,
-			vector: absorbing_state,
-			label: Some("absorbing".to_string()), // Label
-			used_rate: 0.0,                       // No used rate
-			total_outgoing_rate: 0.0,             // No outgoing rate
+			vector: None, // The absorbing state stands for the unexplored states
+			labels: BTreeSet::from([StateFormula::label("absorbing")]),
+			used_rate: 0.0,           // No used rate
+			total_outgoing_rate: 0.0, // No outgoing rate
 		});
 		model
 	}
 
+	/// The sparse matrix of this model, adding up the rates of transitions between
+	/// the same states. It is built once and kept until a state or transition is added.
+	pub fn matrix(&self) -> &SparseMatrix {
+		self.matrix.get_or_init(|| {
+			SparseMatrix::from_entries(
+				self.states.len(),
+				self.transitions
+					.iter()
+					.map(|t| (t.from_state, t.to_state, t.rate)),
+			)
+		})
+	}
+
 	/// Adds a transition to the model
 	pub fn add_transition(&mut self, transition: PrismVasTransition) {
-		let transition_id = transition.transition_id;
 		let from_state = transition.from_state;
 		let to_state = transition.to_state;
 		// let transition_rate = transition.rate;
-		if let Some(from_state) = self.states.iter_mut().find(|s| s.state_id == from_state) {
+		if let Some(from_state) = self.states.get_mut(from_state) {
 			from_state.used_rate += transition.rate;
 			if from_state.used_rate > from_state.total_outgoing_rate + ROUNDING_ERROR {
 				error!(
-					"State {:?} has used rate {} greater than total outgoing rate {} after adding transition {}.",
-					from_state.vector.iter().collect::<Vec<_>>(),
+					"State {:?} has used rate {} greater than total outgoing rate {} after adding transition {:?}.",
+					from_state.entries(),
 					from_state.used_rate,
 					from_state.total_outgoing_rate,
-					transition_id
+					transition.transition_id
 				);
 			}
 		}
+		self.matrix.take();
 		self.transitions.push(transition);
 		self.transition_map
 			.entry(from_state)
@@ -750,9 +1357,53 @@ impl PrismVasModel {
 		// );
 	}
 
-	/// Adds a state to the model
-	pub fn add_state(&mut self, state: PrismVasState) {
+	/// Adds probability to starting in the given state, making it an initial state
+	pub fn add_initial_probability(&mut self, state_id: usize, probability: ProbabilityOrRate) {
+		match self
+			.initial_distribution
+			.iter_mut()
+			.find(|(id, _)| *id == state_id)
+		{
+			Some((_, existing)) => *existing += probability,
+			None => self.initial_distribution.push((state_id, probability)),
+		}
+	}
+
+	/// The ID of the state with the given vector, if it is in the model
+	pub fn state_id(&self, vector: &VasStateVector) -> Option<usize> {
+		self.state_index.get(vector).copied()
+	}
+
+	/// Adds a state to the model under the next ID and returns that ID. The state
+	/// is indexed by its vector, if it has one, so `state_id` finds it.
+	pub fn add_state(&mut self, mut state: PrismVasState) -> usize {
+		let state_id = self.states.len();
+		if state.state_id != state_id {
+			warning!(
+				"State {:?} was given ID {} but is added under ID {}",
+				state.entries(),
+				state.state_id,
+				state_id
+			);
+			state.state_id = state_id;
+		}
+		if let Some(vector) = &state.vector {
+			match self.state_index.entry(vector.clone()) {
+				Entry::Occupied(existing) => {
+					warning!(
+						"State {:?} was already in the model under ID {}",
+						vector.as_slice(),
+						existing.get()
+					);
+				}
+				Entry::Vacant(entry) => {
+					entry.insert(state_id);
+				}
+			}
+		}
+		self.matrix.take();
 		self.states.push(state);
+		state_id
 	}
 
 	/// Adds absorbing transitions to all states
@@ -796,19 +1447,19 @@ impl PrismVasModel {
 					error!(
 						"State {} {:?} has used rate {} greater than total outgoing rate {}",
 						state.state_id,
-						state.vector.iter().collect::<Vec<_>>(),
+						state.entries(),
 						used_rate,
 						total_outgoing_rate
 					);
 					self.transitions.iter().for_each(|tr| {
 						if tr.from_state == state.state_id {
 							debug_message!(
-								"    Transition {} from state {} ({:?}) to state {} ({:?}) with rate {}",
+								"    Transition {:?} from state {} ({:?}) to state {} ({:?}) with rate {}",
 								tr.transition_id,
 								tr.from_state,
-								self.states[tr.from_state].vector.iter().collect::<Vec<_>>(),
+								self.states[tr.from_state].entries(),
 								tr.to_state,
-								self.states[tr.to_state].vector.iter().collect::<Vec<_>>(),
+								self.states[tr.to_state].entries(),
 								tr.rate
 							);
 						}
@@ -819,19 +1470,67 @@ impl PrismVasModel {
 				continue;
 			}
 			// Add the absorbing transition
-			let transition = PrismVasTransition {
-				transition_id: usize::MAX, // Use a special ID for absorbing transitions
-				from_state: state.state_id,
-				to_state: 0,
-				rate: total_outgoing_rate - used_rate, // Absorbing transitions have zero rate
-			};
 			// debug_message!("vas_model.rs adding absorbing transition()");
-			self.add_transition(transition);
+			self.add_entry(state.state_id, 0, total_outgoing_rate - used_rate);
 		}
 		println!("\n");
 		message!("All absorbing transitions added.");
 	}
 
+	/// Returns a copy of this model with every target state collapsed into a single
+	/// sink labeled `target`, which takes the place of the first target state. Like
+	/// the absorbing state, the sink has no vector and no outgoing transitions.
+	pub fn with_target_sink(&self) -> Self {
+		let target_label = StateFormula::label("target");
+		let mut model = Self {
+			variable_names: self.variable_names.clone(),
+			m_type: self.m_type,
+			states: Vec::new(),
+			..Self::default()
+		};
+		// The ID of each state in the new model
+		let mut new_ids = Vec::with_capacity(self.states.len());
+		let mut sink_id = None;
+		for state in self.states.iter() {
+			if state.has_label(&target_label) {
+				let id = *sink_id.get_or_insert_with(|| {
+					model.add_state(PrismVasState {
+						state_id: model.states.len(),
+						vector: None,
+						labels: BTreeSet::from([target_label.clone()]),
+						used_rate: 0.0,
+						total_outgoing_rate: 0.0,
+					})
+				});
+				new_ids.push(id);
+			} else {
+				new_ids.push(model.add_state(PrismVasState {
+					state_id: model.states.len(),
+					used_rate: 0.0, // Recomputed as the transitions are added
+					..state.clone()
+				}));
+			}
+		}
+		for transition in self.transitions.iter() {
+			if sink_id == Some(new_ids[transition.from_state]) {
+				continue;
+			}
+			model.add_transition(PrismVasTransition {
+				from_state: new_ids[transition.from_state],
+				to_state: new_ids[transition.to_state],
+				..transition.clone()
+			});
+		}
+		for (id, probability) in self.initial_distribution.iter() {
+			model.add_initial_probability(new_ids[*id], *probability);
+		}
+		message!(
+			"Collapsed {} target states into one sink",
+			new_ids.iter().filter(|id| Some(**id) == sink_id).count()
+		);
+		model
+	}
+
 	/// This function prints the PRISM-style explicit state space to .sta and .tra files.
 	/// The .sta file contains the state vectors and their IDs,
 	/// while the .tra file contains the transitions between states with their rates.
@@ -845,10 +1544,60 @@ impl PrismVasModel {
 			}
 		};
 
-		// Write labels and state associations
-		writeln!(lab_file, "0=\"init\" 1=\"deadlock\"").unwrap();
-		writeln!(lab_file, "0: 1").unwrap();
-		writeln!(lab_file, "1: 0").unwrap();
+		// Write labels and state associations: every initial state is labeled `init`,
+		// the states where the abstract model has no transitions `deadlock`, and the
+		// target states and the absorbing state by their own labels
+		let matrix = self.matrix();
+		let initial_ids: BTreeSet<usize> = self
+			.initial_distribution
+			.iter()
+			.map(|(id, _)| *id)
+			.collect();
+		let target_label = StateFormula::label("target");
+		let absorbing_label = StateFormula::label("absorbing");
+		writeln!(
+			lab_file,
+			"0=\"init\" 1=\"deadlock\" 2=\"target\" 3=\"absorbing\""
+		)
+		.unwrap();
+		for state in self.states.iter() {
+			// Sinks that stand for other states have no vector and are not deadlocks
+			let is_deadlock = state.vector.is_some()
+				&& state.total_outgoing_rate == 0.0
+				&& matrix.exit_rates()[state.state_id] == 0.0;
+			let labels: Vec<&str> = [
+				(initial_ids.contains(&state.state_id), "0"),
+				(is_deadlock, "1"),
+				(state.has_label(&target_label), "2"),
+				(state.has_label(&absorbing_label), "3"),
+			]
+			.iter()
+			.filter(|(holds, _)| *holds)
+			.map(|(_, label)| *label)
+			.collect();
+			if !labels.is_empty() {
+				writeln!(lab_file, "{}: {}", state.state_id, labels.join(" ")).unwrap();
+			}
+		}
+		// With more than one initial state, write the initial distribution as one
+		// probability per state, in the format of PRISM's `-importinitdist`
+		let has_initial_distribution = self.initial_distribution.len() > 1;
+		if has_initial_distribution {
+			let mut init_file = match File::create(format!("{}.init", output_file)) {
+				Ok(f) => f,
+				Err(e) => {
+					error!("Error creating .init file: {}", e);
+					return;
+				}
+			};
+			let mut probabilities = vec![0.0; self.states.len()];
+			for (id, probability) in self.initial_distribution.iter() {
+				probabilities[*id] = *probability;
+			}
+			for probability in probabilities {
+				writeln!(init_file, "{}", probability).unwrap();
+			}
+		}
 		// Write .sta file
 		let mut sta_file = match File::create(format!("{}.sta", output_file)) {
 			Ok(f) => f,
@@ -860,17 +1609,19 @@ impl PrismVasModel {
 
 		// header info
 		let num_states = self.states.len();
-		let num_transitions = self.transitions.len();
 		let var_names = self.variable_names.join(",");
 		writeln!(sta_file, "({})", var_names).unwrap();
-		// states
+		// states, where sinks without a vector are written with -1 for every variable,
+		// a value no explored state has
 		for i in 0..num_states {
-			let state_str = self.states[i]
-				.vector
-				.iter()
-				.map(|x| x.to_string())
-				.collect::<Vec<_>>()
-				.join(",");
+			let state_str = match &self.states[i].vector {
+				Some(vector) => vector
+					.iter()
+					.map(|x| x.to_string())
+					.collect::<Vec<_>>()
+					.join(","),
+				None => vec!["-1"; self.variable_names.len()].join(","),
+			};
 			writeln!(sta_file, "{}: ({})", i, state_str).unwrap();
 		}
 		// Write .tra file
@@ -881,19 +1632,71 @@ impl PrismVasModel {
 				return;
 			}
 		};
-		writeln!(tra_file, "{} {}", num_states, num_transitions).unwrap();
-		// transitions
-		for t in self.transitions.iter() {
-			writeln!(tra_file, "{} {} {}", t.from_state, t.to_state, t.rate).unwrap();
+		writeln!(tra_file, "{} {}", num_states, matrix.entry_count()).unwrap();
+		// transitions, sorted by state as PRISM expects
+		for (from, to, rate) in matrix.entries() {
+			writeln!(tra_file, "{} {} {}", from, to, rate).unwrap();
 		}
 		// Output results to the specified output file
 		message!(
 			"Resulting explicit state space written to: {}.tra,sta,lab",
 			output_file
 		);
-		message!(
-			"Check this with the following command:\n\n\tprism -importmodel {}.tra,sta,lab <property file>.csl -ctmc\n",
-			output_file
-		);
+		if has_initial_distribution {
+			message!("Initial distribution written to: {}.init", output_file);
+			message!(
+				"Check this with the following command:\n\n\tprism -importmodel {}.tra,sta,lab -importinitdist {}.init <property file>.csl -{}\n",
+				output_file,
+				output_file,
+				self.m_type
+			);
+		} else {
+			message!(
+				"Check this with the following command:\n\n\tprism -importmodel {}.tra,sta,lab <property file>.csl -{}\n",
+				output_file,
+				self.m_type
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeSet;
+
+	use nalgebra::DVector;
+
+	use super::{PrismVasModel, PrismVasState, PrismVasTransition};
+	use crate::model::model::ExplicitModel;
+
+	fn state(model: &PrismVasModel, value: i128) -> PrismVasState {
+		PrismVasState {
+			state_id: model.states.len(),
+			vector: Some(DVector::from_vec(vec![value])),
+			labels: BTreeSet::new(),
+			used_rate: 0.0,
+			total_outgoing_rate: 10.0,
+		}
+	}
+
+	#[test]
+	fn matrix_is_rebuilt_after_changes() {
+		let mut model = PrismVasModel::new();
+		let first = model.add_state(state(&model, 0));
+		let second = model.add_state(state(&model, 1));
+		model.add_transition(PrismVasTransition {
+			transition_id: Some(0),
+			from_state: first,
+			to_state: second,
+			rate: 2.0,
+		});
+		assert_eq!(model.matrix().state_count(), 3);
+		assert_eq!(model.matrix().exit_rates(), &[0.0, 2.0, 0.0]);
+		model.add_entry(first, 0, 1.0);
+		assert_eq!(model.matrix().exit_rates(), &[0.0, 3.0, 0.0]);
+		let third = model.add_state(state(&model, 2));
+		assert_eq!(model.matrix().state_count(), 4);
+		model.add_entry(second, third, 4.0);
+		assert_eq!(model.to_matrix().exit_rates(), &[0.0, 3.0, 4.0, 0.0]);
 	}
 }
@@ -0,0 +1,182 @@
+use crate::{
+	model::vas_model::{VasProperty, VasStateVector},
+	property::property::{StateExpression, StateFormula},
+};
+
+/// A boolean combination of atomic VAS properties, used as the target of a model.
+/// For example, `(S1 >= 30) & (S4 <= 25) | !(GBG = 0)`.
+#[derive(Clone, Debug)]
+pub enum VasTarget {
+	Atom(VasProperty),
+	Not(Box<VasTarget>),
+	And(Box<VasTarget>, Box<VasTarget>),
+	Or(Box<VasTarget>, Box<VasTarget>),
+}
+
+impl VasTarget {
+	/// Checks whether the target holds in the given state vector.
+	pub fn is_satisfied(&self, state: &VasStateVector) -> bool {
+		match self {
+			VasTarget::Atom(property) => property.is_satisfied(state),
+			VasTarget::Not(inner) => !inner.is_satisfied(state),
+			VasTarget::And(lhs, rhs) => lhs.is_satisfied(state) && rhs.is_satisfied(state),
+			VasTarget::Or(lhs, rhs) => lhs.is_satisfied(state) || rhs.is_satisfied(state),
+		}
+	}
+
+	/// Conjoins two targets. Used when a model contains several target lines.
+	pub fn and(self, other: VasTarget) -> VasTarget {
+		VasTarget::And(Box::new(self), Box::new(other))
+	}
+
+	/// Returns an equivalent target where every negation has been pushed
+	/// into the atoms (negation normal form). The result contains no `Not` nodes.
+	pub fn negation_normal_form(&self) -> VasTarget {
+		fn nnf(target: &VasTarget, negated: bool) -> VasTarget {
+			match (target, negated) {
+				(VasTarget::Atom(property), false) => VasTarget::Atom(property.clone()),
+				(VasTarget::Atom(property), true) => VasTarget::Atom(VasProperty {
+					relation: property.relation.negate(),
+					..property.clone()
+				}),
+				(VasTarget::Not(inner), _) => nnf(inner, !negated),
+				(VasTarget::And(lhs, rhs), false) => {
+					VasTarget::And(Box::new(nnf(lhs, false)), Box::new(nnf(rhs, false)))
+				}
+				(VasTarget::And(lhs, rhs), true) => {
+					VasTarget::Or(Box::new(nnf(lhs, true)), Box::new(nnf(rhs, true)))
+				}
+				(VasTarget::Or(lhs, rhs), false) => {
+					VasTarget::Or(Box::new(nnf(lhs, false)), Box::new(nnf(rhs, false)))
+				}
+				(VasTarget::Or(lhs, rhs), true) => {
+					VasTarget::And(Box::new(nnf(lhs, true)), Box::new(nnf(rhs, true)))
+				}
+			}
+		}
+		nnf(self, false)
+	}
+
+	/// All atomic properties in the target, in the order they were written.
+	/// Atoms under a negation are returned as written (not negated).
+	pub fn atoms(&self) -> Vec<&VasProperty> {
+		fn collect<'a>(target: &'a VasTarget, atoms: &mut Vec<&'a VasProperty>) {
+			match target {
+				VasTarget::Atom(property) => atoms.push(property),
+				VasTarget::Not(inner) => collect(inner, atoms),
+				VasTarget::And(lhs, rhs) | VasTarget::Or(lhs, rhs) => {
+					collect(lhs, atoms);
+					collect(rhs, atoms);
+				}
+			}
+		}
+		let mut atoms = Vec::new();
+		collect(self, &mut atoms);
+		atoms
+	}
+
+	/// Whether any atom of the target is a linear combination of variables
+	pub fn has_linear_atoms(&self) -> bool {
+		self.atoms().iter().any(|atom| atom.is_linear())
+	}
+
+	/// Maps every variable index in the target through `map`, e.g., after
+	/// variables have been removed from a model. Returns `None` if any variable
+	/// cannot be mapped.
+	pub fn remap_variables(&self, map: &dyn Fn(usize) -> Option<usize>) -> Option<VasTarget> {
+		Some(match self {
+			VasTarget::Atom(property) => VasTarget::Atom(VasProperty {
+				variable_index: map(property.variable_index)?,
+				linear_terms: property
+					.linear_terms
+					.iter()
+					.map(|(index, coefficient)| Some((map(*index)?, *coefficient)))
+					.collect::<Option<Vec<_>>>()?,
+				..property.clone()
+			}),
+			VasTarget::Not(inner) => VasTarget::Not(Box::new(inner.remap_variables(map)?)),
+			VasTarget::And(lhs, rhs) => VasTarget::And(
+				Box::new(lhs.remap_variables(map)?),
+				Box::new(rhs.remap_variables(map)?),
+			),
+			VasTarget::Or(lhs, rhs) => VasTarget::Or(
+				Box::new(lhs.remap_variables(map)?),
+				Box::new(rhs.remap_variables(map)?),
+			),
+		})
+	}
+
+	/// Converts the target to a state formula over the given variable names,
+	/// so that it can be used as the definition of a state label
+	pub fn to_state_formula(&self, variable_names: &[String]) -> Result<StateFormula, String> {
+		Ok(match self {
+			VasTarget::Atom(_) => StateFormula::Expression(StateExpression::new(
+				&self.to_string_with_names(variable_names),
+			)?),
+			VasTarget::Not(inner) => {
+				StateFormula::Not(Box::new(inner.to_state_formula(variable_names)?))
+			}
+			VasTarget::And(lhs, rhs) => StateFormula::And(
+				Box::new(lhs.to_state_formula(variable_names)?),
+				Box::new(rhs.to_state_formula(variable_names)?),
+			),
+			VasTarget::Or(lhs, rhs) => StateFormula::Or(
+				Box::new(lhs.to_state_formula(variable_names)?),
+				Box::new(rhs.to_state_formula(variable_names)?),
+			),
+		})
+	}
+
+	/// Formats the target using the given variable names
+	pub fn to_string_with_names(&self, variable_names: &[String]) -> String {
+		match self {
+			VasTarget::Atom(property) => {
+				let name = |index: usize| {
+					variable_names
+						.get(index)
+						.map(|s| s.as_str())
+						.unwrap_or("Unknown")
+				};
+				let lhs = if property.is_linear() {
+					property
+						.linear_terms
+						.iter()
+						.enumerate()
+						.map(|(i, (index, coefficient))| {
+							let sign = match (i, *coefficient < 0) {
+								(0, false) => "",
+								(0, true) => "-",
+								(_, false) => " + ",
+								(_, true) => " - ",
+							};
+							match coefficient.abs() {
+								1 => format!("{}{}", sign, name(*index)),
+								c => format!("{}{}*{}", sign, c, name(*index)),
+							}
+						})
+						.collect::<String>()
+				} else {
+					name(property.variable_index).to_string()
+				};
+				format!("{} {} {}", lhs, property.relation, property.target_value)
+			}
+			VasTarget::Not(inner) => format!("!({})", inner.to_string_with_names(variable_names)),
+			VasTarget::And(lhs, rhs) => format!(
+				"({}) & ({})",
+				lhs.to_string_with_names(variable_names),
+				rhs.to_string_with_names(variable_names)
+			),
+			VasTarget::Or(lhs, rhs) => format!(
+				"({}) | ({})",
+				lhs.to_string_with_names(variable_names),
+				rhs.to_string_with_names(variable_names)
+			),
+		}
+	}
+}
+
+impl From<VasProperty> for VasTarget {
+	fn from(property: VasProperty) -> Self {
+		VasTarget::Atom(property)
+	}
+}
@@ -0,0 +1,844 @@
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use crate::{
+	model::vas_target::VasTarget,
+	parser::{parser::ModelOptions, target_parser::parse_target},
+	property::property::{
+		ProbabilityRelation, Property, PropertyQuery, StateExpression, StateFormula,
+	},
+};
+
+/// Labels that explicit models give their states without a definition in the property file
+const MODEL_LABELS: [&str; 4] = ["init", "deadlock", "target", "absorbing"];
+
+/// A token in a property file
+#[derive(Clone, Debug, PartialEq)]
+enum CslToken {
+	Identifier(String),
+	Number(f64),
+	Quoted(String),
+	// Comparison operators, as written
+	Relation(&'static str),
+	And,
+	Or,
+	Not,
+	Plus,
+	Minus,
+	Star,
+	Slash,
+	Question,
+	Colon,
+	Comma,
+	Semicolon,
+	LeftParen,
+	RightParen,
+	LeftBracket,
+	RightBracket,
+}
+
+impl fmt::Display for CslToken {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			CslToken::Identifier(name) => write!(f, "{}", name),
+			CslToken::Number(value) => write!(f, "{}", value),
+			CslToken::Quoted(label) => write!(f, "\"{}\"", label),
+			CslToken::Relation(relation) => write!(f, "{}", relation),
+			CslToken::And => write!(f, "&"),
+			CslToken::Or => write!(f, "|"),
+			CslToken::Not => write!(f, "!"),
+			CslToken::Plus => write!(f, "+"),
+			CslToken::Minus => write!(f, "-"),
+			CslToken::Star => write!(f, "*"),
+			CslToken::Slash => write!(f, "/"),
+			CslToken::Question => write!(f, "?"),
+			CslToken::Colon => write!(f, ":"),
+			CslToken::Comma => write!(f, ","),
+			CslToken::Semicolon => write!(f, ";"),
+			CslToken::LeftParen => write!(f, "("),
+			CslToken::RightParen => write!(f, ")"),
+			CslToken::LeftBracket => write!(f, "["),
+			CslToken::RightBracket => write!(f, "]"),
+		}
+	}
+}
+
+/// Splits a property file into tokens, dropping `//` comments
+fn tokenize(source: &str) -> Result<Vec<CslToken>, String> {
+	let chars: Vec<char> = source.chars().collect();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		let next = chars.get(i + 1).copied();
+		let (token, length) = match c {
+			_ if c.is_whitespace() => {
+				i += 1;
+				continue;
+			}
+			'/' if next == Some('/') => {
+				while i < chars.len() && chars[i] != '\n' {
+					i += 1;
+				}
+				continue;
+			}
+			'"' => {
+				let end = chars[i + 1..]
+					.iter()
+					.position(|&c| c == '"')
+					.ok_or_else(|| "Unterminated label name".to_string())?;
+				let label: String = chars[i + 1..i + 1 + end].iter().collect();
+				(CslToken::Quoted(label), end + 2)
+			}
+			'<' if next == Some('=') => (CslToken::Relation("<="), 2),
+			'>' if next == Some('=') => (CslToken::Relation(">="), 2),
+			'!' if next == Some('=') => (CslToken::Relation("!="), 2),
+			'=' if next == Some('=') => (CslToken::Relation("="), 2),
+			'<' => (CslToken::Relation("<"), 1),
+			'>' => (CslToken::Relation(">"), 1),
+			'=' => (CslToken::Relation("="), 1),
+			'&' => (CslToken::And, if next == Some('&') { 2 } else { 1 }),
+			'|' => (CslToken::Or, if next == Some('|') { 2 } else { 1 }),
+			'!' => (CslToken::Not, 1),
+			'+' => (CslToken::Plus, 1),
+			'-' => (CslToken::Minus, 1),
+			'*' => (CslToken::Star, 1),
+			'/' => (CslToken::Slash, 1),
+			'?' => (CslToken::Question, 1),
+			':' => (CslToken::Colon, 1),
+			',' => (CslToken::Comma, 1),
+			';' => (CslToken::Semicolon, 1),
+			'(' => (CslToken::LeftParen, 1),
+			')' => (CslToken::RightParen, 1),
+			'[' => (CslToken::LeftBracket, 1),
+			']' => (CslToken::RightBracket, 1),
+			_ if c.is_ascii_digit() || c == '.' => {
+				let mut end = i;
+				while end < chars.len()
+					&& (chars[end].is_ascii_digit()
+						|| chars[end] == '.'
+						|| matches!(chars[end], 'e' | 'E')
+						|| (matches!(chars[end], '+' | '-') && matches!(chars[end - 1], 'e' | 'E')))
+				{
+					end += 1;
+				}
+				let literal: String = chars[i..end].iter().collect();
+				let value = literal
+					.parse::<f64>()
+					.map_err(|_| format!("Expected a number, got `{}`", literal))?;
+				(CslToken::Number(value), end - i)
+			}
+			_ if c.is_alphabetic() || c == '_' => {
+				// Names of included variables have the form `prefix.name`
+				let mut end = i;
+				while end < chars.len()
+					&& (chars[end].is_alphanumeric() || chars[end] == '_' || chars[end] == '.')
+				{
+					end += 1;
+				}
+				(
+					CslToken::Identifier(chars[i..end].iter().collect()),
+					end - i,
+				)
+			}
+			_ => return Err(format!("Unexpected character `{}` in property", c)),
+		};
+		tokens.push(token);
+		i += length;
+	}
+	Ok(tokens)
+}
+
+/// The contents of a CSL property file: its labels and its properties
+#[derive(Debug, Default)]
+pub(crate) struct PropertyFile {
+	pub(crate) labels: Vec<(String, StateFormula)>,
+	pub(crate) queries: Vec<PropertyQuery>,
+}
+
+impl PropertyFile {
+	/// The definition of a label, if the file has one
+	pub fn label(&self, name: &str) -> Option<&StateFormula> {
+		self.labels
+			.iter()
+			.find(|(label, _)| label == name)
+			.map(|(_, formula)| formula)
+	}
+
+	/// The first property of the file
+	pub fn query(&self) -> Result<&PropertyQuery, String> {
+		self.queries
+			.first()
+			.ok_or_else(|| "The property file has no properties".to_string())
+	}
+
+	/// The target of a model checked against this file: the `"target"` label if
+	/// there is one, and otherwise the states of interest of the first property
+	pub fn target(&self, variable_names: &[String]) -> Result<VasTarget, String> {
+		match self.label("target") {
+			Some(formula) => self.to_vas_target(formula, variable_names),
+			None => self.to_vas_target(&self.query()?.target_formula()?, variable_names),
+		}
+	}
+
+	/// Converts a state formula to a target, replacing labels by their definitions
+	pub fn to_vas_target(
+		&self,
+		formula: &StateFormula,
+		variable_names: &[String],
+	) -> Result<VasTarget, String> {
+		self.expand_labels(formula, false, &mut Vec::new())
+			.and_then(|formula| state_formula_target(&formula, variable_names))
+	}
+
+	/// The formula with every label defined in the file replaced by its definition,
+	/// keeping the labels of explicit models such as `"init"` and `"target"`. The
+	/// states of an explicit model carry these labels, even sinks without a vector.
+	pub fn expand(&self, formula: &StateFormula) -> Result<StateFormula, String> {
+		self.expand_labels(formula, true, &mut Vec::new())
+	}
+
+	/// The formula with every label replaced by its definition, except the labels of
+	/// explicit models if `keep_model_labels`
+	fn expand_labels(
+		&self,
+		formula: &StateFormula,
+		keep_model_labels: bool,
+		expanding: &mut Vec<String>,
+	) -> Result<StateFormula, String> {
+		Ok(match formula {
+			StateFormula::StateLabel(name) => {
+				if keep_model_labels && MODEL_LABELS.contains(&name.as_str()) {
+					return Ok(formula.clone());
+				}
+				let Some(definition) = self.label(name) else {
+					if MODEL_LABELS.contains(&name.as_str()) {
+						return Ok(formula.clone());
+					}
+					return Err(format!("Unknown label `\"{}\"`", name));
+				};
+				if expanding.contains(name) {
+					return Err(format!(
+						"Label `\"{}\"` is defined in terms of itself",
+						name
+					));
+				}
+				expanding.push(name.clone());
+				let expanded = self.expand_labels(definition, keep_model_labels, expanding)?;
+				expanding.pop();
+				expanded
+			}
+			StateFormula::Not(inner) => StateFormula::Not(Box::new(self.expand_labels(
+				inner,
+				keep_model_labels,
+				expanding,
+			)?)),
+			StateFormula::And(lhs, rhs) => StateFormula::And(
+				Box::new(self.expand_labels(lhs, keep_model_labels, expanding)?),
+				Box::new(self.expand_labels(rhs, keep_model_labels, expanding)?),
+			),
+			StateFormula::Or(lhs, rhs) => StateFormula::Or(
+				Box::new(self.expand_labels(lhs, keep_model_labels, expanding)?),
+				Box::new(self.expand_labels(rhs, keep_model_labels, expanding)?),
+			),
+			formula => formula.clone(),
+		})
+	}
+}
+
+/// Converts a state formula without labels to a target over the variables
+fn state_formula_target(
+	formula: &StateFormula,
+	variable_names: &[String],
+) -> Result<VasTarget, String> {
+	Ok(match formula {
+		StateFormula::Expression(expression) => parse_target(expression.text(), variable_names)?,
+		StateFormula::Not(inner) => {
+			VasTarget::Not(Box::new(state_formula_target(inner, variable_names)?))
+		}
+		StateFormula::And(lhs, rhs) => VasTarget::And(
+			Box::new(state_formula_target(lhs, variable_names)?),
+			Box::new(state_formula_target(rhs, variable_names)?),
+		),
+		StateFormula::Or(lhs, rhs) => VasTarget::Or(
+			Box::new(state_formula_target(lhs, variable_names)?),
+			Box::new(state_formula_target(rhs, variable_names)?),
+		),
+		StateFormula::Constant(value) => {
+			return Err(format!("`{}` cannot be used as a target", value))
+		}
+		StateFormula::StateLabel(name) => return Err(format!("Unknown label `\"{}\"`", name)),
+	})
+}
+
+/// Recursive descent parser over the tokens of a property file.
+/// In state formulas, `!` binds tighter than `&`, which binds tighter than `|`.
+struct CslParser {
+	tokens: Vec<CslToken>,
+	position: usize,
+	// Values of the `const` declarations read so far
+	constants: HashMap<String, f64>,
+}
+
+impl CslParser {
+	fn peek(&self) -> Option<&CslToken> {
+		self.tokens.get(self.position)
+	}
+
+	fn peek_at(&self, offset: usize) -> Option<&CslToken> {
+		self.tokens.get(self.position + offset)
+	}
+
+	fn advance(&mut self) -> Option<CslToken> {
+		let token = self.tokens.get(self.position).cloned();
+		self.position += 1;
+		token
+	}
+
+	fn expect(&mut self, expected: CslToken) -> Result<(), String> {
+		match self.advance() {
+			Some(token) if token == expected => Ok(()),
+			Some(token) => Err(format!("Expected `{}`, got `{}`", expected, token)),
+			None => Err(format!("Expected `{}` before end of property", expected)),
+		}
+	}
+
+	fn peek_identifier(&self, name: &str) -> bool {
+		matches!(self.peek(), Some(CslToken::Identifier(identifier)) if identifier == name)
+	}
+
+	/// Parses the whole file: `const` and `label` declarations, and properties,
+	/// which may be named (`"name": P=? [...]`) and end with an optional `;`
+	fn parse_file(&mut self) -> Result<PropertyFile, String> {
+		let mut file = PropertyFile::default();
+		while self.peek().is_some() {
+			if self.peek_identifier("const") {
+				self.parse_constant()?;
+			} else if self.peek_identifier("label") {
+				self.advance();
+				let name = match self.advance() {
+					Some(CslToken::Quoted(name)) => name,
+					Some(token) => return Err(format!("Expected a label name, got `{}`", token)),
+					None => return Err("Expected a label name before end of file".to_string()),
+				};
+				if file.label(&name).is_some() {
+					return Err(format!("Label `\"{}\"` is defined more than once", name));
+				}
+				self.expect(CslToken::Relation("="))?;
+				let formula = self.parse_or()?;
+				self.expect(CslToken::Semicolon)?;
+				file.labels.push((name, formula));
+			} else {
+				if matches!(self.peek(), Some(CslToken::Quoted(_)))
+					&& self.peek_at(1) == Some(&CslToken::Colon)
+				{
+					self.position += 2;
+				}
+				file.queries.push(self.parse_weighted_query()?);
+				if self.peek() == Some(&CslToken::Semicolon) {
+					self.advance();
+				}
+			}
+		}
+		Ok(file)
+	}
+
+	/// Parses `const [int|double] NAME = VALUE;`
+	fn parse_constant(&mut self) -> Result<(), String> {
+		self.advance();
+		if self.peek_identifier("int") || self.peek_identifier("double") {
+			self.advance();
+		}
+		let name = match self.advance() {
+			Some(CslToken::Identifier(name)) => name,
+			Some(token) => return Err(format!("Expected a constant name, got `{}`", token)),
+			None => return Err("Expected a constant name before end of file".to_string()),
+		};
+		self.expect(CslToken::Relation("="))?;
+		let value = self.parse_number()?;
+		self.expect(CslToken::Semicolon)?;
+		self.constants.insert(name, value);
+		Ok(())
+	}
+
+	/// Parses an optionally negated number or constant
+	fn parse_number(&mut self) -> Result<f64, String> {
+		let sign = if self.peek() == Some(&CslToken::Minus) {
+			self.advance();
+			-1.0
+		} else {
+			1.0
+		};
+		match self.advance() {
+			Some(CslToken::Number(value)) => Ok(sign * value),
+			Some(CslToken::Identifier(name)) => self
+				.constants
+				.get(&name)
+				.map(|value| sign * value)
+				.ok_or_else(|| format!("Unknown constant `{}`", name)),
+			Some(token) => Err(format!("Expected a number, got `{}`", token)),
+			None => Err("Expected a number before end of property".to_string()),
+		}
+	}
+
+	/// Parses a query, or the weighted sum `w1 * filter(state, Q, "init_1") + ...`
+	/// over initial states that the PRISM exporter writes, which stands for `Q`
+	/// since the weights are the model's initial distribution
+	fn parse_weighted_query(&mut self) -> Result<PropertyQuery, String> {
+		let starts_filter = |parser: &Self| {
+			parser.peek_identifier("filter")
+				|| (matches!(parser.peek(), Some(CslToken::Number(_)))
+					&& parser.peek_at(1) == Some(&CslToken::Star))
+		};
+		if !starts_filter(self) {
+			return self.parse_query();
+		}
+		let mut query: Option<PropertyQuery> = None;
+		loop {
+			if let Some(CslToken::Number(_)) = self.peek() {
+				self.position += 2;
+			}
+			if !self.peek_identifier("filter") {
+				return Err("Expected `filter` in a weighted sum of properties".to_string());
+			}
+			self.advance();
+			self.expect(CslToken::LeftParen)?;
+			if !self.peek_identifier("state") {
+				return Err("Only `filter(state, ...)` is supported".to_string());
+			}
+			self.advance();
+			self.expect(CslToken::Comma)?;
+			let inner = self.parse_query()?;
+			self.expect(CslToken::Comma)?;
+			self.parse_or()?;
+			self.expect(CslToken::RightParen)?;
+			match &query {
+				Some(existing) if *existing != inner => {
+					return Err(format!(
+						"Expected the same property in every filter, got `{}` and `{}`",
+						existing, inner
+					));
+				}
+				_ => query = Some(inner),
+			}
+			if self.peek() != Some(&CslToken::Plus) {
+				break;
+			}
+			self.advance();
+		}
+		Ok(query.unwrap())
+	}
+
+	/// Parses `P=? [...]`, `Pmax=? [...]`, `P>=p [...]` or `S=? [...]`
+	fn parse_query(&mut self) -> Result<PropertyQuery, String> {
+		let operator = match self.advance() {
+			Some(CslToken::Identifier(operator)) => operator,
+			Some(token) => return Err(format!("Expected `P` or `S`, got `{}`", token)),
+			None => return Err("Expected a property before end of file".to_string()),
+		};
+		let relation = match self.advance() {
+			Some(CslToken::Relation(relation)) => relation,
+			Some(token) => return Err(format!("Expected `=?` or a bound, got `{}`", token)),
+			None => return Err("Expected `=?` or a bound before end of property".to_string()),
+		};
+		let bound = if relation == "=" {
+			self.expect(CslToken::Question)?;
+			None
+		} else {
+			let relation = match relation {
+				"<" => ProbabilityRelation::LessThan,
+				"<=" => ProbabilityRelation::LessThanOrEqual,
+				">" => ProbabilityRelation::GreaterThan,
+				">=" => ProbabilityRelation::GreaterThanOrEqual,
+				_ => return Err(format!("Unsupported probability bound `{}`", relation)),
+			};
+			let probability = self.parse_number()?;
+			if !(0.0..=1.0).contains(&probability) {
+				return Err(format!(
+					"Probability bound {} is not in [0, 1]",
+					probability
+				));
+			}
+			Some((relation, probability))
+		};
+		self.expect(CslToken::LeftBracket)?;
+		let query = match (operator.as_str(), bound) {
+			("P", None) => PropertyQuery::Probability(self.parse_path()?),
+			("P", Some((relation, probability))) => {
+				PropertyQuery::BoundedProbability(self.parse_path()?, relation, probability)
+			}
+			("Pmax", None) => PropertyQuery::MaxProbability(self.parse_path()?),
+			("S", None) => PropertyQuery::SteadyState(self.parse_or()?),
+			("Pmax" | "S", Some(_)) => {
+				return Err(format!("`{}` only supports `=?`", operator));
+			}
+			_ => return Err(format!("Unsupported property operator `{}`", operator)),
+		};
+		self.expect(CslToken::RightBracket)?;
+		Ok(query)
+	}
+
+	/// Parses an optional time bound `<=t` (or `<t`) after `F` or `U`
+	fn parse_time_bound(&mut self) -> Result<Option<f64>, String> {
+		match self.peek() {
+			Some(CslToken::Relation("<=" | "<")) => {
+				self.advance();
+				let bound = self.parse_number()?;
+				if bound < 0.0 {
+					return Err(format!("Time bound {} is negative", bound));
+				}
+				Ok(Some(bound))
+			}
+			Some(CslToken::Relation(relation)) => {
+				Err(format!("Unsupported time bound `{}`", relation))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	/// Parses `F[<=t] phi`, `G phi` or `phi U[<=t] psi`
+	fn parse_path(&mut self) -> Result<Property, String> {
+		if self.peek_identifier("F") {
+			self.advance();
+			let bound = self.parse_time_bound()?;
+			return Ok(Property::Finally(self.parse_or()?, bound));
+		}
+		// `X` is the next-step operator unless it is a variable in a comparison
+		if self.peek_identifier("X")
+			&& !matches!(
+				self.peek_at(1),
+				Some(
+					CslToken::Relation(_)
+						| CslToken::Plus | CslToken::Minus
+						| CslToken::Star | CslToken::Slash
+				)
+			) {
+			return Err("The next-step operator `X` is not supported".to_string());
+		}
+		if self.peek_identifier("G") {
+			self.advance();
+			if let Some(CslToken::Relation(_)) = self.peek() {
+				return Err("Time bounds on `G` are not supported".to_string());
+			}
+			return Ok(Property::Globally(self.parse_or()?));
+		}
+		let lhs = self.parse_or()?;
+		if !self.peek_identifier("U") {
+			return match self.peek() {
+				Some(token) => Err(format!("Expected `F`, `G` or `U`, got `{}`", token)),
+				None => Err("Expected `F`, `G` or `U` before end of property".to_string()),
+			};
+		}
+		self.advance();
+		let bound = self.parse_time_bound()?;
+		Ok(Property::Until(lhs, self.parse_or()?, bound))
+	}
+
+	fn parse_or(&mut self) -> Result<StateFormula, String> {
+		let mut formula = self.parse_and()?;
+		while self.peek() == Some(&CslToken::Or) {
+			self.advance();
+			let rhs = self.parse_and()?;
+			formula = StateFormula::Or(Box::new(formula), Box::new(rhs));
+		}
+		Ok(formula)
+	}
+
+	fn parse_and(&mut self) -> Result<StateFormula, String> {
+		let mut formula = self.parse_unary()?;
+		while self.peek() == Some(&CslToken::And) {
+			self.advance();
+			let rhs = self.parse_unary()?;
+			formula = StateFormula::And(Box::new(formula), Box::new(rhs));
+		}
+		Ok(formula)
+	}
+
+	fn parse_unary(&mut self) -> Result<StateFormula, String> {
+		match self.peek() {
+			Some(CslToken::Not) => {
+				self.advance();
+				Ok(StateFormula::Not(Box::new(self.parse_unary()?)))
+			}
+			Some(CslToken::Quoted(label)) => {
+				let label = label.clone();
+				self.advance();
+				Ok(StateFormula::StateLabel(label))
+			}
+			Some(CslToken::Identifier(name)) if name == "true" || name == "false" => {
+				let value = name == "true";
+				self.advance();
+				Ok(StateFormula::Constant(value))
+			}
+			Some(CslToken::LeftParen) => {
+				// The parentheses may group a formula, as in `(S1 >= 30)`, or
+				// a term of a comparison, as in `(S1 + S2) >= 30`
+				let start = self.position;
+				self.advance();
+				if let Ok(formula) = self.parse_or() {
+					if self.peek() == Some(&CslToken::RightParen) {
+						self.advance();
+						if !self.continues_term() {
+							return Ok(formula);
+						}
+					}
+				}
+				self.position = start;
+				self.parse_comparison()
+			}
+			_ => self.parse_comparison(),
+		}
+	}
+
+	/// Whether the next token continues an arithmetic term or starts a comparison
+	fn continues_term(&self) -> bool {
+		matches!(
+			self.peek(),
+			Some(
+				CslToken::Relation(_)
+					| CslToken::Plus
+					| CslToken::Minus
+					| CslToken::Star
+					| CslToken::Slash
+			)
+		)
+	}
+
+	/// Parses an atomic comparison `TERM REL TERM`, kept as text with
+	/// constants replaced by their values
+	fn parse_comparison(&mut self) -> Result<StateFormula, String> {
+		let lhs = self.parse_term()?;
+		let relation = match self.advance() {
+			Some(CslToken::Relation(relation)) => relation,
+			Some(token) => return Err(format!("Expected comparison operator, got `{}`", token)),
+			None => return Err("Expected comparison operator before end of property".to_string()),
+		};
+		let rhs = self.parse_term()?;
+		let text = format!("{} {} {}", lhs, relation, rhs);
+		Ok(StateFormula::Expression(StateExpression::new(&text)?))
+	}
+
+	/// Parses an arithmetic term over variables, numbers and constants
+	fn parse_term(&mut self) -> Result<String, String> {
+		let mut text = String::new();
+		loop {
+			// Unary minus is written against its operand
+			while self.peek() == Some(&CslToken::Minus) {
+				self.advance();
+				text.push('-');
+			}
+			match self.advance() {
+				Some(CslToken::LeftParen) => {
+					let inner = self.parse_term()?;
+					self.expect(CslToken::RightParen)?;
+					text.push_str(&format!("({})", inner));
+				}
+				Some(CslToken::Number(value)) => text.push_str(&value.to_string()),
+				Some(CslToken::Identifier(name)) => match self.constants.get(&name) {
+					Some(value) => text.push_str(&value.to_string()),
+					None => text.push_str(&name),
+				},
+				Some(token) => {
+					return Err(format!("Expected a variable or number, got `{}`", token))
+				}
+				None => {
+					return Err("Expected a variable or number before end of property".to_string())
+				}
+			}
+			match self.peek() {
+				Some(
+					operator
+					@ (CslToken::Plus | CslToken::Minus | CslToken::Star | CslToken::Slash),
+				) => {
+					text.push_str(&format!(" {} ", operator));
+					self.advance();
+				}
+				_ => break,
+			}
+		}
+		Ok(text)
+	}
+}
+
+/// Parses the contents of a CSL property file
+pub fn parse_properties(source: &str) -> Result<PropertyFile, String> {
+	let mut parser = CslParser {
+		tokens: tokenize(source)?,
+		position: 0,
+		constants: HashMap::new(),
+	};
+	parser.parse_file()
+}
+
+/// Reads and parses a CSL property file
+pub fn parse_property_file(filename: &str) -> Result<PropertyFile, String> {
+	let source = fs::read_to_string(filename)
+		.map_err(|e| format!("Could not read property file {}: {}", filename, e))?;
+	parse_properties(&source).map_err(|e| format!("{}: {}", filename, e))
+}
+
+/// The target given with `--prop`, if any
+pub fn override_target(
+	options: &ModelOptions,
+	variable_names: &[String],
+) -> Option<Result<VasTarget, String>> {
+	options.property_file.as_deref().map(|filename| {
+		parse_property_file(filename)?
+			.target(variable_names)
+			.map_err(|e| format!("{}: {}", filename, e))
+	})
+}
+
+/// PRISM and SBML models carry no target of their own, so it is read from the
+/// file given with `--prop`, or else from the companion `.prop` of the model
+pub(crate) fn companion_target(
+	filename: &str,
+	options: &ModelOptions,
+	variable_names: &[String],
+) -> Result<VasTarget, String> {
+	if let Some(target) = override_target(options, variable_names) {
+		return target;
+	}
+	let property_file = Path::new(filename).with_extension("prop");
+	if !property_file.exists() {
+		return Err(format!(
+			"No target found: provide {} or use --prop",
+			property_file.display()
+		));
+	}
+	let property_file = property_file.to_string_lossy();
+	parse_property_file(&property_file)?
+		.target(variable_names)
+		.map_err(|e| format!("{}: {}", property_file, e))
+}
+
+/// The property file a model is checked against: the file given with `--prop`,
+/// or else the companion `.prop` of the model if there is one
+pub(crate) fn model_property_file(
+	filename: &str,
+	options: &ModelOptions,
+) -> Option<Result<PropertyFile, String>> {
+	let property_file = match &options.property_file {
+		Some(property_file) => property_file.to_string(),
+		None => {
+			let property_file = Path::new(filename).with_extension("prop");
+			if !property_file.exists() {
+				return None;
+			}
+			property_file.to_string_lossy().to_string()
+		}
+	};
+	Some(parse_property_file(&property_file))
+}
+
+/// The query a model is checked against: the first property of its property
+/// file (see `model_property_file`)
+pub(crate) fn model_query(
+	filename: &str,
+	options: &ModelOptions,
+) -> Option<Result<PropertyQuery, String>> {
+	model_property_file(filename, options)
+		.map(|properties| properties.and_then(|properties| properties.query().cloned()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse_properties;
+	use crate::property::property::{ProbabilityRelation, Property, PropertyQuery};
+
+	/// The first query of a property file
+	fn query(source: &str) -> PropertyQuery {
+		parse_properties(source)
+			.unwrap_or_else(|e| panic!("{}: {}", source, e))
+			.query()
+			.unwrap()
+			.clone()
+	}
+
+	fn variable_names() -> Vec<String> {
+		vec!["X".to_string(), "Y".to_string()]
+	}
+
+	#[test]
+	fn accepts_path_and_steady_state_properties() {
+		let cases = [
+			("P=? [ F X >= 5 ]", "P=? [ F X >= 5 ]"),
+			("P=? [ F<=10 X >= 5 ]", "P=? [ F<=10 X >= 5 ]"),
+			("P=? [ Y = 0 U X >= 5 ]", "P=? [ Y = 0 U X >= 5 ]"),
+			("P=? [ Y = 0 U<=2.5 X >= 5 ]", "P=? [ Y = 0 U<=2.5 X >= 5 ]"),
+			("P=? [ G X < 5 ]", "P=? [ G X < 5 ]"),
+			("P>=0.9 [ F X >= 5 ]", "P>=0.9 [ F X >= 5 ]"),
+			("Pmax=? [ F X >= 5 ]", "Pmax=? [ F X >= 5 ]"),
+			("S=? [ X >= 5 ]", "S=? [ X >= 5 ]"),
+		];
+		for (source, expected) in cases {
+			assert_eq!(query(source).to_string(), expected);
+		}
+		match query("const double T = 4; P<0.5 [ F<=T X >= 5 ];") {
+			PropertyQuery::BoundedProbability(
+				Property::Finally(_, Some(bound)),
+				ProbabilityRelation::LessThan,
+				probability,
+			) => {
+				assert_eq!(bound, 4.0);
+				assert_eq!(probability, 0.5);
+			}
+			query => panic!("Unexpected query {}", query),
+		}
+		match query("P=? [ Y = 0 U<=3 X >= 5 ]") {
+			PropertyQuery::Probability(Property::Until(_, _, bound)) => {
+				assert_eq!(bound, Some(3.0));
+			}
+			query => panic!("Unexpected query {}", query),
+		}
+	}
+
+	#[test]
+	fn rejects_unsupported_properties() {
+		for source in [
+			"P=? [ G<=5 X < 5 ]",
+			"P=? [ F>=5 X >= 5 ]",
+			"P=? [ F<=-1 X >= 5 ]",
+			"P=? [ X X >= 5 ]",
+			"P>=2 [ F X >= 5 ]",
+			"S>=0.5 [ X >= 5 ]",
+			"P=? [ X >= 5 ]",
+		] {
+			assert!(parse_properties(source).is_err(), "{}", source);
+		}
+	}
+
+	#[test]
+	fn targets_come_from_reachability_properties() {
+		let names = variable_names();
+		for source in [
+			"P=? [ F X >= 5 ]",
+			"P=? [ F<=10 X >= 5 ]",
+			"P=? [ true U X >= 5 ]",
+			"S=? [ X >= 5 ]",
+		] {
+			let target = parse_properties(source).unwrap().target(&names).unwrap();
+			assert_eq!(target.to_string_with_names(&names), "X >= 5", "{}", source);
+		}
+		// `U` with a left operand and `G` are not decided by reaching a set of states
+		for source in [
+			"P=? [ Y = 0 U X >= 5 ]",
+			"P=? [ Y = 0 U<=3 X >= 5 ]",
+			"P=? [ G X < 5 ]",
+		] {
+			assert!(
+				parse_properties(source).unwrap().target(&names).is_err(),
+				"{}",
+				source
+			);
+		}
+		// A `"target"` label is used instead of the property
+		let properties = parse_properties("label \"target\" = Y >= 2; P=? [ G X < 5 ]").unwrap();
+		assert_eq!(
+			properties
+				.target(&names)
+				.unwrap()
+				.to_string_with_names(&names),
+			"Y >= 2"
+		);
+	}
+}
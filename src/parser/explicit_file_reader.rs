@@ -0,0 +1,327 @@
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fs,
+	path::Path,
+};
+
+use nalgebra::DVector;
+
+use crate::{
+	model::{
+		model::ProbabilityOrRate,
+		vas_model::{
+			AbstractVas, PrismVasModel, PrismVasState, PrismVasTransition, VasStateVector, VasValue,
+		},
+	},
+	property::property::StateFormula,
+	*,
+};
+
+/// Labels that are worked out again when the model is exported, so they are not kept
+const DERIVED_LABELS: &[&str] = &["deadlock"];
+
+/// Reads a file for the given base name and extension, e.g., `output.sta`
+fn read_explicit_file(input: &str, extension: &str) -> Result<String, String> {
+	let filename = format!("{}.{}", input, extension);
+	fs::read_to_string(&filename).map_err(|e| format!("Could not read {}: {}", filename, e))
+}
+
+/// Splits a line of the form `<state>: <rest>` into the state ID and the rest
+fn split_state_line<'a>(line: &'a str, filename: &str) -> Result<(usize, &'a str), String> {
+	let (id, rest) = line
+		.split_once(':')
+		.ok_or_else(|| format!("Expected `<state>: ...` in {}: {}", filename, line))?;
+	let id = id
+		.trim()
+		.parse::<usize>()
+		.map_err(|_| format!("Invalid state ID in {}: {}", filename, line))?;
+	Ok((id, rest.trim()))
+}
+
+/// Reads the state vectors of a `.sta` file, in the order of the variables of the
+/// abstract model, whatever the order of the columns in the file. Sinks that stand
+/// for other states, like the absorbing state, are written with negative values
+/// and have no vector.
+fn read_states(
+	abstract_model: &AbstractVas,
+	input: &str,
+) -> Result<Vec<Option<VasStateVector>>, String> {
+	let filename = format!("{}.sta", input);
+	let contents = read_explicit_file(input, "sta")?;
+	let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+	let header = lines
+		.next()
+		.ok_or_else(|| format!("{} is empty", filename))?
+		.trim();
+	let names: Vec<&str> = header
+		.strip_prefix('(')
+		.and_then(|header| header.strip_suffix(')'))
+		.ok_or_else(|| format!("Expected `(<variables>)` on the first line of {}", filename))?
+		.split(',')
+		.map(|name| name.trim())
+		.collect();
+	// The position in the abstract model of each column
+	let columns = names
+		.iter()
+		.map(|name| {
+			abstract_model
+				.variable_names
+				.iter()
+				.position(|variable| variable == name)
+				.ok_or_else(|| format!("Variable {} of {} is not in the model", name, filename))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	if let Some(missing) = abstract_model
+		.variable_names
+		.iter()
+		.find(|variable| !names.contains(&variable.as_str()))
+	{
+		return Err(format!("Variable {} is missing from {}", missing, filename));
+	}
+	let mut states = Vec::new();
+	for line in lines {
+		let (id, values) = split_state_line(line, &filename)?;
+		if id != states.len() {
+			return Err(format!(
+				"Expected state {} but found state {} in {}",
+				states.len(),
+				id,
+				filename
+			));
+		}
+		let values: Vec<&str> = values
+			.strip_prefix('(')
+			.and_then(|values| values.strip_suffix(')'))
+			.ok_or_else(|| format!("Expected `(<values>)` in {}: {}", filename, line))?
+			.split(',')
+			.collect();
+		if values.len() != columns.len() {
+			return Err(format!(
+				"State {} has {} values but {} has {} variables",
+				id,
+				values.len(),
+				filename,
+				columns.len()
+			));
+		}
+		let mut vector = DVector::from_element(columns.len(), 0);
+		for (column, value) in columns.iter().zip(values.iter()) {
+			vector[*column] = value
+				.trim()
+				.parse::<VasValue>()
+				.map_err(|_| format!("Invalid value {} of state {} in {}", value, id, filename))?;
+		}
+		states.push(vector.iter().all(|v| *v >= 0).then_some(vector));
+	}
+	Ok(states)
+}
+
+/// Reads the labels of each state from a `.lab` file
+fn read_labels(input: &str, state_count: usize) -> Result<Vec<BTreeSet<String>>, String> {
+	let filename = format!("{}.lab", input);
+	let contents = read_explicit_file(input, "lab")?;
+	let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+	// The header declares the labels, e.g., `0="init" 1="deadlock"`
+	let mut names = BTreeMap::new();
+	let header = lines.next().unwrap_or_default();
+	for declaration in header.split_whitespace() {
+		let (index, name) = declaration
+			.split_once('=')
+			.ok_or_else(|| format!("Invalid label declaration {} in {}", declaration, filename))?;
+		let index = index
+			.parse::<usize>()
+			.map_err(|_| format!("Invalid label index {} in {}", index, filename))?;
+		names.insert(index, name.trim_matches('"').to_string());
+	}
+	let mut labels = vec![BTreeSet::new(); state_count];
+	for line in lines {
+		let (id, indices) = split_state_line(line, &filename)?;
+		let state_labels = labels
+			.get_mut(id)
+			.ok_or_else(|| format!("Unknown state {} in {}", id, filename))?;
+		for index in indices.split_whitespace() {
+			let name = index
+				.parse::<usize>()
+				.ok()
+				.and_then(|index| names.get(&index))
+				.ok_or_else(|| format!("Undeclared label {} in {}", index, filename))?;
+			state_labels.insert(name.clone());
+		}
+	}
+	Ok(labels)
+}
+
+/// Reads the (from, to, rate) entries of a `.tra` file
+fn read_entries(
+	input: &str,
+	state_count: usize,
+) -> Result<Vec<(usize, usize, ProbabilityOrRate)>, String> {
+	let filename = format!("{}.tra", input);
+	let contents = read_explicit_file(input, "tra")?;
+	let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+	let header: Vec<&str> = lines
+		.next()
+		.ok_or_else(|| format!("{} is empty", filename))?
+		.split_whitespace()
+		.collect();
+	if header.first().and_then(|n| n.parse::<usize>().ok()) != Some(state_count) {
+		return Err(format!(
+			"{} does not have the {} states of the .sta file",
+			filename, state_count
+		));
+	}
+	let mut entries = Vec::new();
+	for line in lines {
+		let fields: Vec<&str> = line.split_whitespace().collect();
+		if fields.len() != 3 {
+			return Err(format!(
+				"Expected `<from> <to> <rate>` in {} (nondeterministic models are not supported): {}",
+				filename, line
+			));
+		}
+		let state = |field: &str| {
+			field
+				.parse::<usize>()
+				.ok()
+				.filter(|state| *state < state_count)
+				.ok_or_else(|| format!("Invalid state {} in {}", field, filename))
+		};
+		let rate = fields[2]
+			.parse::<ProbabilityOrRate>()
+			.map_err(|_| format!("Invalid rate {} in {}", fields[2], filename))?;
+		entries.push((state(fields[0])?, state(fields[1])?, rate));
+	}
+	Ok(entries)
+}
+
+/// Reads the initial distribution, one probability per state, from an `.init` file
+/// in the format of PRISM's `-importinitdist`, if there is one
+fn read_initial_distribution(
+	input: &str,
+	state_count: usize,
+) -> Result<Option<Vec<ProbabilityOrRate>>, String> {
+	let filename = format!("{}.init", input);
+	if !Path::new(&filename).exists() {
+		return Ok(None);
+	}
+	let probabilities = read_explicit_file(input, "init")?
+		.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| {
+			line.trim()
+				.parse::<ProbabilityOrRate>()
+				.map_err(|_| format!("Invalid probability {} in {}", line, filename))
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	if probabilities.len() != state_count {
+		return Err(format!(
+			"{} has {} probabilities but there are {} states",
+			filename,
+			probabilities.len(),
+			state_count
+		));
+	}
+	Ok(Some(probabilities))
+}
+
+/// Reads an explicit state space written as `<input>.tra,sta,lab` (and `<input>.init`
+/// if there is one), e.g., by a previous run or by PRISM, into a `PrismVasModel` for
+/// the given abstract model. The total outgoing rate of each state is computed from
+/// the abstract model, and each transition is matched to the abstract transition that
+/// it fires. Transitions into the absorbing state are dropped, since they are added
+/// again once the model is expanded. If the first state is not labeled `absorbing`, an
+/// absorbing state is added before the states of the files.
+pub(crate) fn read_explicit_prism_files(
+	abstract_model: &AbstractVas,
+	input: &str,
+) -> Result<PrismVasModel, String> {
+	let vectors = read_states(abstract_model, input)?;
+	let labels = read_labels(input, vectors.len())?;
+	let entries = read_entries(input, vectors.len())?;
+	let initial_distribution = read_initial_distribution(input, vectors.len())?;
+	let mut model = PrismVasModel::from_abstract_model(abstract_model);
+	let has_absorbing_state = labels.first().is_some_and(|l| l.contains("absorbing"));
+	if let Some(id) = labels
+		.iter()
+		.skip(1)
+		.position(|labels| labels.contains("absorbing"))
+	{
+		return Err(format!(
+			"State {} is labeled absorbing, but only the first state may be",
+			id + 1
+		));
+	}
+	// The ID in the model of each state of the files
+	let mut ids = Vec::with_capacity(vectors.len());
+	for (id, (vector, state_labels)) in vectors.iter().zip(labels.iter()).enumerate() {
+		if id == 0 && has_absorbing_state {
+			ids.push(0);
+			continue;
+		}
+		ids.push(
+			model.add_state(PrismVasState {
+				state_id: model.states.len(),
+				vector: vector.clone(),
+				labels: state_labels
+					.iter()
+					.filter(|name| !DERIVED_LABELS.contains(&name.as_str()))
+					.map(|name| StateFormula::label(name))
+					.collect(),
+				used_rate: 0.0,
+				// Sinks, like the absorbing state, have no transitions
+				total_outgoing_rate: vector
+					.as_ref()
+					.map_or(0.0, |vector| abstract_model.total_outgoing_rate(vector)),
+			}),
+		);
+	}
+	for (from, to, rate) in entries {
+		if ids[to] == 0 {
+			continue;
+		}
+		let transition_id = match (&vectors[from], &vectors[to]) {
+			(Some(from_vector), Some(to_vector)) => {
+				let update = to_vector - from_vector;
+				abstract_model
+					.transitions
+					.iter()
+					.find(|t| t.update_vector == update && t.enabled_vector(from_vector))
+					.map(|t| t.transition_id)
+			}
+			_ => None,
+		};
+		model.add_transition(PrismVasTransition {
+			transition_id,
+			from_state: ids[from],
+			to_state: ids[to],
+			rate,
+		});
+	}
+	match initial_distribution {
+		Some(probabilities) => {
+			for (id, probability) in probabilities.iter().enumerate() {
+				if *probability > 0.0 {
+					model.add_initial_probability(ids[id], *probability);
+				}
+			}
+		}
+		None => {
+			let initial_ids: Vec<usize> = (0..vectors.len())
+				.filter(|id| labels[*id].contains("init"))
+				.collect();
+			for id in initial_ids.iter() {
+				model.add_initial_probability(ids[*id], 1.0 / initial_ids.len() as f64);
+			}
+		}
+	}
+	if model.initial_distribution.is_empty() {
+		return Err(format!("No state of {}.lab is labeled init", input));
+	}
+	message!(
+		"Read {} states and {} transitions from {}.tra,sta,lab",
+		model.states.len(),
+		model.transitions.len(),
+		input
+	);
+	Ok(model)
+}
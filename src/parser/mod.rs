@@ -1,3 +1,9 @@
+pub(crate) mod csl_parser;
+pub(crate) mod explicit_file_reader;
+pub(crate) mod parameters;
 pub(crate) mod parser;
+pub(crate) mod prism_parser;
+pub(crate) mod sbml_parser;
+pub(crate) mod target_parser;
 pub(crate) mod vas_file_reader;
 pub(crate) mod vas_parser;
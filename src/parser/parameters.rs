@@ -0,0 +1,238 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Once},
+};
+
+use evalexpr::{
+	error::EvalexprResultValue, Context, ContextWithMutableVariables, DefaultNumericTypes,
+	EvalexprError, EvalexprResult, HashMapContext, IterateVariablesContext, Value,
+};
+use nalgebra::DVector;
+
+use crate::{
+	model::vas_model::{RateFn, VasState, VasValue},
+	warning,
+};
+
+/// Named parameter values, usable as variables in `evalexpr` expressions
+pub(crate) type ParameterContext = HashMapContext<DefaultNumericTypes>;
+
+/// Parameter values given on the command line with `--param NAME=VALUE`.
+/// These replace the values of the parameters declared in a model file.
+#[derive(Clone, Debug, Default)]
+pub struct ParameterOverrides(Vec<(String, String)>);
+
+impl ParameterOverrides {
+	/// Reads the `NAME=VALUE` assignments given on the command line
+	pub fn from_assignments(assignments: &[String]) -> Result<Self, String> {
+		let mut overrides = Vec::new();
+		for assignment in assignments {
+			let (name, value) = assignment
+				.split_once('=')
+				.map(|(name, value)| (name.trim(), value.trim()))
+				.filter(|(name, value)| !name.is_empty() && !value.is_empty())
+				.ok_or_else(|| {
+					format!(
+						"Invalid parameter `{}`: expected NAME=VALUE, e.g., --param k1=0.1",
+						assignment
+					)
+				})?;
+			overrides.retain(|(existing, _): &(String, String)| existing != name);
+			overrides.push((name.to_string(), value.to_string()));
+		}
+		Ok(Self(overrides))
+	}
+
+	/// The value given on the command line for a parameter, if any
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.0
+			.iter()
+			.find(|(existing, _)| existing == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Warns about command line parameters that the model does not declare,
+	/// since a misspelled name would otherwise be ignored silently.
+	pub fn warn_unused(&self, declared: &[String], filename: &str) {
+		for (name, _) in self.0.iter() {
+			if !declared.contains(name) {
+				warning!(
+					"Parameter `{}` is set on the command line but not declared in {}",
+					name,
+					filename
+				);
+			}
+		}
+	}
+}
+
+/// Evaluates `expression` over the parameters and adds it to them as `name`
+pub fn define_parameter(
+	name: &str,
+	expression: &str,
+	parameters: &mut ParameterContext,
+) -> Result<(), String> {
+	let value = match evalexpr::eval_with_context(expression, parameters) {
+		Ok(value @ (Value::Int(_) | Value::Float(_))) => value,
+		Ok(value) => {
+			return Err(format!(
+				"Parameter `{}` must be a number, got `{}`",
+				name, value
+			))
+		}
+		Err(e) => return Err(format!("Unable to evaluate `{}`: {}", expression, e)),
+	};
+	parameters
+		.set_value(name.to_string(), value)
+		.map_err(|e| format!("Unable to set parameter `{}`: {}", name, e))
+}
+
+/// Evaluates a numeric expression over the parameters, e.g., `k1*2`.
+/// Parameters are read as floats here, so `k1 / 10` is not an integer
+/// division even when `k1` is set to an integer.
+pub fn evaluate_float(expression: &str, parameters: &ParameterContext) -> Result<f64, String> {
+	if let Ok(value) = expression.parse::<f64>() {
+		return Ok(value);
+	}
+	evalexpr::eval_number_with_context(expression, &float_parameters(parameters)?)
+		.map_err(|e| format!("Unable to evaluate `{}`: {}", expression, e))
+}
+
+/// Parameters as floats, so that expressions over them do not use integer division
+fn float_parameters(parameters: &ParameterContext) -> Result<ParameterContext, String> {
+	let mut float_parameters = ParameterContext::new();
+	for (name, value) in parameter_values(parameters) {
+		float_parameters
+			.set_value(name, Value::Float(value))
+			.map_err(|e| e.to_string())?;
+	}
+	Ok(float_parameters)
+}
+
+/// Evaluates an integer expression over the parameters, e.g., `N/2`
+pub fn evaluate_integer(
+	expression: &str,
+	parameters: &ParameterContext,
+) -> Result<VasValue, String> {
+	if let Ok(value) = expression.parse::<VasValue>() {
+		return Ok(value);
+	}
+	match evalexpr::eval_with_context(expression, parameters) {
+		Ok(Value::Int(value)) => Ok(value as VasValue),
+		Ok(Value::Float(value)) if value.fract() == 0.0 => Ok(value as VasValue),
+		Ok(value) => Err(format!(
+			"Expected integer, got `{}` = {}",
+			expression, value
+		)),
+		Err(e) => Err(format!("Unable to evaluate `{}`: {}", expression, e)),
+	}
+}
+
+/// The parameters as plain numbers
+pub fn parameter_values(parameters: &ParameterContext) -> HashMap<String, f64> {
+	parameters
+		.iter_variables()
+		.filter_map(|(name, value)| value.as_number().ok().map(|number| (name, number)))
+		.collect()
+}
+
+/// The species counts of a state together with the parameters, for
+/// evaluating rate laws. Species take precedence over parameters.
+struct RateContext<'a> {
+	state: &'a VasState,
+	parameters: &'a ParameterContext,
+}
+
+impl Context for RateContext<'_> {
+	type NumericTypes = DefaultNumericTypes;
+
+	fn get_value(&self, identifier: &str) -> Option<&Value<Self::NumericTypes>> {
+		self.state
+			.get_value(identifier)
+			.or_else(|| self.parameters.get_value(identifier))
+	}
+
+	fn call_function(
+		&self,
+		identifier: &str,
+		_argument: &Value<Self::NumericTypes>,
+	) -> EvalexprResultValue<Self::NumericTypes> {
+		Err(EvalexprError::FunctionIdentifierNotFound(
+			identifier.to_string(),
+		))
+	}
+
+	fn are_builtin_functions_disabled(&self) -> bool {
+		false
+	}
+
+	fn set_builtin_functions_disabled(
+		&mut self,
+		disabled: bool,
+	) -> EvalexprResult<(), Self::NumericTypes> {
+		if disabled {
+			Err(EvalexprError::BuiltinFunctionsCannotBeDisabled)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+/// Whether an expression refers to any of the variables, e.g., a rate law
+/// such as `Vmax*S/(K+S)` rather than a rate constant
+pub fn refers_to_variables(expression: &str, variable_names: &[String]) -> bool {
+	evalexpr::build_operator_tree::<DefaultNumericTypes>(expression).is_ok_and(|tree| {
+		tree.iter_variable_identifiers()
+			.any(|identifier| variable_names.iter().any(|name| name == identifier))
+	})
+}
+
+/// Compiles a rate law over the species counts and parameters, e.g., Hill
+/// kinetics `Vmax*S^n/(K^n+S^n)`, into a function of the state.
+/// The expression is parsed once and checked by evaluating it in the zero state.
+pub fn compile_rate_law(
+	expression: &str,
+	variable_names: &[String],
+	parameters: &ParameterContext,
+) -> Result<RateFn, String> {
+	let tree = evalexpr::build_operator_tree::<DefaultNumericTypes>(expression)
+		.map_err(|e| format!("Unable to parse rate `{}`: {}", expression, e))?;
+	let names: Arc<[String]> = variable_names.into();
+	let parameters = float_parameters(parameters)?;
+	for identifier in tree.iter_variable_identifiers() {
+		if !names.iter().any(|name| name == identifier)
+			&& parameters.get_value(identifier).is_none()
+		{
+			return Err(format!(
+				"Unknown identifier `{}` in rate `{}`",
+				identifier, expression
+			));
+		}
+	}
+	let evaluate = move |state: &VasState| {
+		let state = VasState::with_variable_names(state.vector.clone(), names.clone());
+		tree.eval_number_with_context(&RateContext {
+			state: &state,
+			parameters: &parameters,
+		})
+	};
+	evaluate(&VasState::new(DVector::zeros(variable_names.len())))
+		.map_err(|e| format!("Unable to evaluate rate `{}`: {}", expression, e))?;
+	// An expression that evaluates in the zero state but fails elsewhere has rate
+	// zero there, with a warning the first time this happens
+	let expression = expression.to_string();
+	let warned = Once::new();
+	Ok(Arc::new(move |state: &VasState| {
+		evaluate(state).unwrap_or_else(|e| {
+			warned.call_once(|| {
+				warning!(
+					"Unable to evaluate rate `{}` in state {:?}: {}. Using rate 0 wherever it fails.",
+					expression,
+					state.vector.as_slice(),
+					e
+				);
+			});
+			0.0
+		})
+	}))
+}
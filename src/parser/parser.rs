@@ -1,48 +1,194 @@
 use std::fmt;
 
+use crate::logging::messages::*;
 use crate::model::model::ModelType;
 use crate::model::*;
+use crate::parser::parameters::ParameterOverrides;
 use crate::parser::parser::model::AbstractModel;
 
+/// Options given on the command line that change how model files are read
+#[derive(Clone, Debug, Default)]
+pub struct ModelOptions {
+	/// Values from `--param`, replacing those of the parameters declared in the model
+	pub parameters: ParameterOverrides,
+	/// The property file from `--prop`, whose target replaces the model's own
+	pub property_file: Option<String>,
+}
+
 pub(crate) trait ModelParseError: ToString {
 	/// The line number where the error occurred
 	fn line(&self) -> (u64, String);
 	/// The column where the error occurred (not all errors can provide this)
 	fn column(&self) -> Option<u64>;
+	/// The zero-based columns `(start, end)` of the text where the error occurred
+	fn column_span(&self) -> Option<(u64, u64)> {
+		self.column().map(|column| (column, column + 1))
+	}
+	/// The error message, without the line it occurred on
+	fn message(&self) -> String {
+		self.to_string()
+	}
 }
 
-/// A wrapper type for ModelParseError to implement fmt::Display
+/// A parse error at a line and column of a model file, for parsers that keep
+/// the source lines around (e.g., the PRISM and SBML parsers)
+#[derive(Debug)]
+pub(crate) struct PositionedParseError {
+	line: u64,
+	line_content: String,
+	column: Option<u64>,
+	message: String,
+}
+
+impl PositionedParseError {
+	/// An error at the zero-based `line` and `column` of `lines`
+	pub(crate) fn at(lines: &[String], line: usize, column: usize, message: String) -> Self {
+		Self {
+			line: line as u64 + 1,
+			// Tabs are shown as single spaces so the column marker lines up
+			line_content: lines
+				.get(line)
+				.map(|line| line.replace('\t', " "))
+				.unwrap_or_default(),
+			column: Some(column as u64),
+			message,
+		}
+	}
+
+	pub(crate) fn without_position(message: String) -> Self {
+		Self {
+			line: 0,
+			line_content: String::new(),
+			column: None,
+			message,
+		}
+	}
+}
+
+impl ModelParseError for PositionedParseError {
+	fn line(&self) -> (u64, String) {
+		(self.line, self.line_content.clone())
+	}
+
+	fn column(&self) -> Option<u64> {
+		self.column
+	}
+}
+
+impl fmt::Display for PositionedParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.column {
+			Some(column) => write!(f, "{} (column {})", self.message, column + 1),
+			None => write!(f, "{}", self.message),
+		}
+	}
+}
+
+/// A wrapper type for ModelParseError to implement fmt::Display.
+/// Shows the line with a caret under the offending text.
 impl fmt::Display for dyn ModelParseError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let (line_num, line_content) = self.line();
-		let col = self.column();
-		let err_str = self.to_string();
-		let marker = if col.is_some() {
-			let col_n = col.unwrap();
-			format!(
-				"{}^{}",
-				" ".repeat(col_n as usize),
-				"-".repeat(line_content.len() - col_n as usize - 1)
-			)
-		} else {
-			"^".repeat(line_content.len())
-		};
+		let err_str = self.message();
+		if line_num == 0 || line_content.is_empty() {
+			return write!(f, "[Parse Error] {}", err_str);
+		}
 		write!(
 			f,
-			"[Parse Error] Error in model parsing. Unable to parse model!\n{}: {}\n{}\n{}",
-			line_num, line_content, marker, err_str
-		)
+			"[Parse Error] Line {}: {}\n\t{}",
+			line_num, err_str, line_content
+		)?;
+		if let Some((start, end)) = self.column_span() {
+			// Tabs are kept so the marker lines up with the line above
+			let indent: String = line_content
+				.chars()
+				.take(start as usize)
+				.map(|c| if c == '\t' { '\t' } else { ' ' })
+				.collect();
+			write!(
+				f,
+				"\n\t{}{}",
+				indent,
+				"^".repeat(end.saturating_sub(start).max(1) as usize)
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// Escapes a string for JSON output
+fn json_string(s: &str) -> String {
+	let mut escaped = String::from("\"");
+	for c in s.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\t' => escaped.push_str("\\t"),
+			c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped.push('"');
+	escaped
+}
+
+/// Formats parse errors as JSON, e.g., for editor integration.
+/// Lines and columns are one-based (`end_column` is exclusive), and `null` when unknown.
+pub(crate) fn parse_errors_json(filename: &str, errors: &[Box<dyn ModelParseError>]) -> String {
+	let optional = |value: Option<u64>| value.map_or("null".to_string(), |v| v.to_string());
+	let errors = errors
+		.iter()
+		.map(|error| {
+			let (line_num, _) = error.line();
+			let span = error.column_span();
+			format!(
+				"{{\"line\": {}, \"column\": {}, \"end_column\": {}, \"message\": {}}}",
+				optional(Some(line_num).filter(|&line| line > 0)),
+				optional(span.map(|(start, _)| start + 1)),
+				optional(span.map(|(_, end)| end + 1)),
+				json_string(&error.message())
+			)
+		})
+		.collect::<Vec<_>>();
+	format!(
+		"{{\"file\": {}, \"errors\": [{}]}}",
+		json_string(filename),
+		errors.join(", ")
+	)
+}
+
+/// Parses a model file and reports every problem found, as text or as JSON
+/// on standard output. Returns whether the model parsed without errors.
+pub(crate) fn lint_model(filename: &str, json: bool, options: &ModelOptions) -> bool {
+	let errors = match vas_model::AbstractVas::parse_file(filename, options) {
+		Ok(_) => Vec::new(),
+		Err(errors) => errors,
+	};
+	if json {
+		println!("{}", parse_errors_json(filename, &errors));
+	} else if errors.is_empty() {
+		message!("No problems found in {}", filename);
+	} else {
+		for err in errors.iter() {
+			error!("{}", err.as_ref());
+		}
+		message!("{} problem(s) found in {}", errors.len(), filename);
 	}
+	errors.is_empty()
 }
 
 pub(crate) trait Parser {
 	type ModelType: AbstractModel + Into<ModelType>;
 	type ParserErrorType: ModelParseError + fmt::Debug;
 
-	fn parse(filename: &str) -> Result<Self::ModelType, Self::ParserErrorType>;
+	fn parse(
+		filename: &str,
+		options: &ModelOptions,
+	) -> Result<Self::ModelType, Self::ParserErrorType>;
 
-	fn parse_or_panic(filename: &str) -> ModelType {
-		let model = Self::parse(filename);
+	fn parse_or_panic(filename: &str, options: &ModelOptions) -> ModelType {
+		let model = Self::parse(filename, options);
 		match model {
 			Ok(model) => {
 				return model.into();
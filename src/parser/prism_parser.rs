@@ -0,0 +1,1458 @@
+use std::{collections::HashMap, fmt, fs, sync::Arc};
+
+use nalgebra::DVector;
+
+use crate::{
+	model::{
+		model::ProbabilityOrRate,
+		vas_model::{
+			AbstractVas, AllowedRelation, VasState, VasStateVector, VasTransition, VasValue,
+		},
+	},
+	parser::{
+		csl_parser::companion_target,
+		parameters::{define_parameter, evaluate_float, ParameterContext, ParameterOverrides},
+		parser::{ModelOptions, Parser, PositionedParseError},
+		target_parser::parse_target,
+	},
+	warning,
+};
+
+/// Keywords for model types we recognize but cannot import
+const UNSUPPORTED_MODEL_TYPES: &[&str] = &[
+	"dtmc",
+	"probabilistic",
+	"mdp",
+	"nondeterministic",
+	"pta",
+	"ctmdp",
+	"smg",
+];
+/// Keywords for top-level constructs outside of the supported subset
+const UNSUPPORTED_CONSTRUCTS: &[&str] = &["formula", "global", "init", "system", "player"];
+
+/// Parser for the subset of the PRISM language used by our CRN models:
+/// a `ctmc` header, constants, modules with bounded integer variables, and
+/// guarded commands with mass-action rates and constant integer updates.
+/// Commands with the same action label are synchronized across modules,
+/// as in the files produced by the SBML-to-PRISM converter. Upper bounds in
+/// the guards of mass-action commands are dropped with a warning, since the
+/// VAS does not bound species counts. Commands with other rates are evaluated,
+/// guard included, as custom rate functions.
+pub(crate) struct PrismParser;
+
+impl Parser for PrismParser {
+	type ModelType = AbstractVas;
+	type ParserErrorType = PositionedParseError;
+
+	fn parse(
+		filename: &str,
+		options: &ModelOptions,
+	) -> Result<Self::ModelType, Self::ParserErrorType> {
+		let source = fs::read_to_string(filename).map_err(|e| {
+			PositionedParseError::without_position(format!("Unable to read `{}`: {}", filename, e))
+		})?;
+		let lines: Vec<String> = source.lines().map(|line| line.to_string()).collect();
+		let tokens = tokenize(&lines)?;
+		let mut reader = PrismReader {
+			tokens,
+			position: 0,
+			lines: &lines,
+			parameters: &options.parameters,
+		};
+		let program = reader.parse_program()?;
+		let constant_names: Vec<String> = program.constants.keys().cloned().collect();
+		options.parameters.warn_unused(&constant_names, filename);
+		program.into_vas(&lines, filename, options)
+	}
+}
+
+/// A parse error at the given position of a PRISM model
+fn error_at(lines: &[String], position: Position, message: String) -> PositionedParseError {
+	PositionedParseError::at(lines, position.line, position.column, message)
+}
+
+/// A zero-based line and column in the source file
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Position {
+	line: usize,
+	column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PrismToken {
+	Identifier(String),
+	Number(String),
+	Str(String),
+	Symbol(&'static str),
+}
+
+impl fmt::Display for PrismToken {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PrismToken::Identifier(name) => write!(f, "{}", name),
+			PrismToken::Number(number) => write!(f, "{}", number),
+			PrismToken::Str(string) => write!(f, "\"{}\"", string),
+			PrismToken::Symbol(symbol) => write!(f, "{}", symbol),
+		}
+	}
+}
+
+/// Symbols in the PRISM language, longest first so that they are matched greedily
+const SYMBOLS: &[&str] = &[
+	"->", "..", "<=", ">=", "!=", "[", "]", "(", ")", "{", "}", ";", ":", "'", "=", "+", "-", "*",
+	"/", "&", "|", "!", "<", ">", ",", "?",
+];
+
+/// Splits the model into tokens, dropping whitespace and `//` comments
+fn tokenize(lines: &[String]) -> Result<Vec<(PrismToken, Position)>, PositionedParseError> {
+	let mut tokens = Vec::new();
+	for (line_num, line) in lines.iter().enumerate() {
+		let chars: Vec<char> = line.chars().collect();
+		let mut i = 0;
+		while i < chars.len() {
+			let c = chars[i];
+			let position = Position {
+				line: line_num,
+				column: i,
+			};
+			if c.is_whitespace() {
+				i += 1;
+			} else if c == '/' && chars.get(i + 1) == Some(&'/') {
+				break;
+			} else if c.is_ascii_digit() {
+				let start = i;
+				while i < chars.len() && chars[i].is_ascii_digit() {
+					i += 1;
+				}
+				// A single `.` followed by a digit is a decimal point, `..` is a range
+				if chars.get(i) == Some(&'.')
+					&& chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())
+				{
+					i += 1;
+					while i < chars.len() && chars[i].is_ascii_digit() {
+						i += 1;
+					}
+				}
+				if matches!(chars.get(i), Some('e') | Some('E')) {
+					let mut j = i + 1;
+					if matches!(chars.get(j), Some('+') | Some('-')) {
+						j += 1;
+					}
+					if chars.get(j).is_some_and(|d| d.is_ascii_digit()) {
+						i = j;
+						while i < chars.len() && chars[i].is_ascii_digit() {
+							i += 1;
+						}
+					}
+				}
+				tokens.push((
+					PrismToken::Number(chars[start..i].iter().collect()),
+					position,
+				));
+			} else if c.is_alphabetic() || c == '_' {
+				let start = i;
+				while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+					i += 1;
+				}
+				tokens.push((
+					PrismToken::Identifier(chars[start..i].iter().collect()),
+					position,
+				));
+			} else if c == '"' {
+				let start = i + 1;
+				i += 1;
+				while i < chars.len() && chars[i] != '"' {
+					i += 1;
+				}
+				if i == chars.len() {
+					return Err(error_at(lines, position, "Unterminated string".to_string()));
+				}
+				tokens.push((PrismToken::Str(chars[start..i].iter().collect()), position));
+				i += 1;
+			} else if let Some(symbol) = SYMBOLS.iter().find(|symbol| {
+				symbol
+					.chars()
+					.enumerate()
+					.all(|(k, s)| chars.get(i + k) == Some(&s))
+			}) {
+				tokens.push((PrismToken::Symbol(symbol), position));
+				i += symbol.len();
+			} else {
+				return Err(error_at(
+					lines,
+					position,
+					format!("Unexpected character `{}`", c),
+				));
+			}
+		}
+	}
+	Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryOp {
+	Or,
+	And,
+	Relation(AllowedRelation),
+	Add,
+	Subtract,
+	Multiply,
+	Divide,
+}
+
+#[derive(Clone, Debug)]
+enum ExprKind {
+	Number(f64),
+	Identifier(String),
+	Boolean(bool),
+	Not(Box<Expr>),
+	Negate(Box<Expr>),
+	Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// An expression in a guard, rate, constant or label
+#[derive(Clone, Debug)]
+struct Expr {
+	kind: ExprKind,
+	position: Position,
+}
+
+impl fmt::Display for Expr {
+	/// Formats the expression in the syntax accepted by the target parser
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.kind {
+			ExprKind::Number(value) => write!(f, "{}", value),
+			ExprKind::Identifier(name) => write!(f, "{}", name),
+			ExprKind::Boolean(value) => write!(f, "{}", value),
+			ExprKind::Not(inner) => write!(f, "!({})", inner),
+			ExprKind::Negate(inner) => write!(f, "-{}", inner),
+			ExprKind::Binary(op, lhs, rhs) => match op {
+				BinaryOp::Or => write!(f, "({}) | ({})", lhs, rhs),
+				BinaryOp::And => write!(f, "({}) & ({})", lhs, rhs),
+				BinaryOp::Relation(relation) => write!(f, "{} {} {}", lhs, relation, rhs),
+				BinaryOp::Add => write!(f, "{} + {}", lhs, rhs),
+				BinaryOp::Subtract => write!(f, "{} - {}", lhs, rhs),
+				BinaryOp::Multiply => write!(f, "{}*{}", lhs, rhs),
+				BinaryOp::Divide => write!(f, "{}/{}", lhs, rhs),
+			},
+		}
+	}
+}
+
+struct PrismVariable {
+	name: String,
+	initial_value: VasValue,
+}
+
+/// An update `(X' = X + delta)` in a command
+struct PrismUpdate {
+	variable: String,
+	delta: VasValue,
+	position: Position,
+}
+
+/// An expression with its constants folded and its species replaced by their
+/// index, to evaluate custom rates and guards in a state
+#[derive(Clone, Debug)]
+enum StateExpr {
+	Number(f64),
+	Species(usize),
+	Not(Box<StateExpr>),
+	Negate(Box<StateExpr>),
+	Binary(BinaryOp, Box<StateExpr>, Box<StateExpr>),
+}
+
+impl StateExpr {
+	/// Evaluates the expression in a state, with `true` as 1 and `false` as 0
+	fn evaluate(&self, state: &VasStateVector) -> f64 {
+		let truth = |value: bool| if value { 1.0 } else { 0.0 };
+		match self {
+			StateExpr::Number(value) => *value,
+			StateExpr::Species(index) => state[*index] as f64,
+			StateExpr::Not(inner) => truth(inner.evaluate(state) == 0.0),
+			StateExpr::Negate(inner) => -inner.evaluate(state),
+			StateExpr::Binary(op, lhs, rhs) => {
+				let lhs_value = lhs.evaluate(state);
+				let rhs_value = rhs.evaluate(state);
+				match op {
+					BinaryOp::Or => truth(lhs_value != 0.0 || rhs_value != 0.0),
+					BinaryOp::And => truth(lhs_value != 0.0 && rhs_value != 0.0),
+					BinaryOp::Relation(relation) => truth(relation.holds(lhs_value, rhs_value)),
+					BinaryOp::Add => lhs_value + rhs_value,
+					BinaryOp::Subtract => lhs_value - rhs_value,
+					BinaryOp::Multiply => lhs_value * rhs_value,
+					BinaryOp::Divide => lhs_value / rhs_value,
+				}
+			}
+		}
+	}
+}
+
+struct PrismCommand {
+	module: String,
+	action: Option<String>,
+	guard: Expr,
+	rate: Option<Expr>,
+	updates: Vec<PrismUpdate>,
+	position: Position,
+}
+
+/// The parsed (but not yet analyzed) contents of a PRISM file
+struct PrismProgram {
+	constants: HashMap<String, f64>,
+	variables: Vec<PrismVariable>,
+	commands: Vec<PrismCommand>,
+	target_label: Option<Expr>,
+}
+
+/// Reads the token stream into a `PrismProgram`
+struct PrismReader<'a> {
+	tokens: Vec<(PrismToken, Position)>,
+	position: usize,
+	lines: &'a [String],
+	parameters: &'a ParameterOverrides,
+}
+
+impl<'a> PrismReader<'a> {
+	fn peek(&self) -> Option<&PrismToken> {
+		self.tokens.get(self.position).map(|(token, _)| token)
+	}
+
+	fn peek_at(&self, offset: usize) -> Option<&PrismToken> {
+		self.tokens
+			.get(self.position + offset)
+			.map(|(token, _)| token)
+	}
+
+	/// The position of the next token, or the end of the file
+	fn current_position(&self) -> Position {
+		match self.tokens.get(self.position) {
+			Some((_, position)) => *position,
+			None => {
+				let line = self.lines.len().saturating_sub(1);
+				Position {
+					line,
+					column: self
+						.lines
+						.get(line)
+						.map(|l| l.chars().count().saturating_sub(1))
+						.unwrap_or(0),
+				}
+			}
+		}
+	}
+
+	fn advance(&mut self) -> Option<(PrismToken, Position)> {
+		let token = self.tokens.get(self.position).cloned();
+		self.position += 1;
+		token
+	}
+
+	fn error_here(&self, message: String) -> PositionedParseError {
+		error_at(self.lines, self.current_position(), message)
+	}
+
+	fn unexpected(&self, expected: &str) -> PositionedParseError {
+		match self.peek() {
+			Some(token) => self.error_here(format!("Expected {}, got `{}`", expected, token)),
+			None => self.error_here(format!("Expected {} before end of file", expected)),
+		}
+	}
+
+	fn is_symbol(&self, symbol: &str) -> bool {
+		matches!(self.peek(), Some(PrismToken::Symbol(s)) if *s == symbol)
+	}
+
+	fn is_keyword(&self, keyword: &str) -> bool {
+		matches!(self.peek(), Some(PrismToken::Identifier(name)) if name == keyword)
+	}
+
+	fn expect_symbol(&mut self, symbol: &str) -> Result<Position, PositionedParseError> {
+		if self.is_symbol(symbol) {
+			Ok(self.advance().unwrap().1)
+		} else {
+			Err(self.unexpected(&format!("`{}`", symbol)))
+		}
+	}
+
+	fn expect_identifier(&mut self) -> Result<(String, Position), PositionedParseError> {
+		match self.peek() {
+			Some(PrismToken::Identifier(_)) => match self.advance() {
+				Some((PrismToken::Identifier(name), position)) => Ok((name, position)),
+				_ => unreachable!(),
+			},
+			_ => Err(self.unexpected("an identifier")),
+		}
+	}
+
+	fn parse_program(&mut self) -> Result<PrismProgram, PositionedParseError> {
+		let mut program = PrismProgram {
+			constants: HashMap::new(),
+			variables: Vec::new(),
+			commands: Vec::new(),
+			target_label: None,
+		};
+		let mut has_model_type = false;
+		while let Some(token) = self.peek().cloned() {
+			let keyword = match &token {
+				PrismToken::Identifier(keyword) => keyword.as_str(),
+				_ => return Err(self.unexpected("a declaration")),
+			};
+			match keyword {
+				"ctmc" | "stochastic" => {
+					has_model_type = true;
+					self.advance();
+				}
+				_ if UNSUPPORTED_MODEL_TYPES.contains(&keyword) => {
+					return Err(self.error_here(format!(
+						"Unsupported model type `{}`: only `ctmc` models can be imported",
+						keyword
+					)));
+				}
+				_ if !has_model_type => {
+					return Err(self.error_here(format!(
+						"Expected the model type `ctmc` before `{}`",
+						keyword
+					)));
+				}
+				"const" => self.parse_constant(&mut program.constants)?,
+				"module" => self.parse_module(&mut program)?,
+				"rewards" => {
+					// Reward structures do not affect the state space
+					while !self.is_keyword("endrewards") {
+						if self.advance().is_none() {
+							return Err(self.unexpected("`endrewards`"));
+						}
+					}
+					self.advance();
+				}
+				"label" => {
+					self.advance();
+					let name = match self.advance() {
+						Some((PrismToken::Str(name), _)) => name,
+						_ => {
+							self.position -= 1;
+							return Err(self.unexpected("a label name in quotes"));
+						}
+					};
+					self.expect_symbol("=")?;
+					let expr = self.parse_expression()?;
+					self.expect_symbol(";")?;
+					if name == "target" {
+						program.target_label = Some(expr);
+					}
+				}
+				_ if UNSUPPORTED_CONSTRUCTS.contains(&keyword) => {
+					return Err(self.error_here(format!(
+						"`{}` declarations are not supported by the PRISM importer",
+						keyword
+					)));
+				}
+				_ => return Err(self.unexpected("a declaration")),
+			}
+		}
+		if !has_model_type {
+			return Err(PositionedParseError::without_position(
+				"Expected the model type `ctmc`".to_string(),
+			));
+		}
+		Ok(program)
+	}
+
+	/// Parses `const [int|double] NAME = EXPR;`
+	fn parse_constant(
+		&mut self,
+		constants: &mut HashMap<String, f64>,
+	) -> Result<(), PositionedParseError> {
+		self.advance();
+		if self.is_keyword("bool") {
+			return Err(self.error_here("Boolean constants are not supported".to_string()));
+		}
+		if self.is_keyword("int") || self.is_keyword("double") {
+			self.advance();
+		}
+		let (name, position) = self.expect_identifier()?;
+		let expr = if self.is_symbol("=") {
+			self.advance();
+			Some(self.parse_expression()?)
+		} else {
+			None
+		};
+		self.expect_symbol(";")?;
+		// Values given on the command line replace those in the model,
+		// and may define constants the model leaves undefined
+		let value = match (self.parameters.get(&name), expr) {
+			(Some(value), _) => {
+				let mut parameters = ParameterContext::new();
+				for (constant, constant_value) in constants.iter() {
+					define_parameter(constant, &constant_value.to_string(), &mut parameters)
+						.map_err(|e| error_at(self.lines, position, e))?;
+				}
+				evaluate_float(value, &parameters).map_err(|e| error_at(self.lines, position, e))?
+			}
+			(None, Some(expr)) => evaluate_constant(&expr, constants, self.lines)?,
+			(None, None) => {
+				return Err(error_at(
+					self.lines,
+					position,
+					format!(
+						"Constant `{}` must be given a value (in the model or with --param {}=...)",
+						name, name
+					),
+				))
+			}
+		};
+		constants.insert(name, value);
+		Ok(())
+	}
+
+	/// Parses `module NAME ... endmodule`
+	fn parse_module(&mut self, program: &mut PrismProgram) -> Result<(), PositionedParseError> {
+		self.advance();
+		let (module_name, _) = self.expect_identifier()?;
+		if self.is_symbol("=") {
+			return Err(self.error_here("Module renaming is not supported".to_string()));
+		}
+		loop {
+			match self.peek() {
+				Some(PrismToken::Identifier(name)) if name == "endmodule" => {
+					self.advance();
+					return Ok(());
+				}
+				Some(PrismToken::Identifier(_))
+					if self.peek_at(1) == Some(&PrismToken::Symbol(":")) =>
+				{
+					let variable = self.parse_variable(&program.constants)?;
+					program.variables.push(variable);
+				}
+				Some(PrismToken::Symbol("[")) => {
+					let command = self.parse_command(&module_name)?;
+					program.commands.push(command);
+				}
+				_ => return Err(self.unexpected("a variable, command or `endmodule`")),
+			}
+		}
+	}
+
+	/// Parses `NAME : [LOW..HIGH] init VALUE;` or `NAME : int init VALUE;`
+	fn parse_variable(
+		&mut self,
+		constants: &HashMap<String, f64>,
+	) -> Result<PrismVariable, PositionedParseError> {
+		let (name, _) = self.expect_identifier()?;
+		self.expect_symbol(":")?;
+		let mut lower_bound = 0;
+		if self.is_symbol("[") {
+			self.advance();
+			let low = self.parse_expression()?;
+			lower_bound = evaluate_integer(&low, constants, self.lines)?;
+			self.expect_symbol("..")?;
+			// Variable ranges are not enforced in the VAS, which is unbounded
+			let high = self.parse_expression()?;
+			evaluate_integer(&high, constants, self.lines)?;
+			self.expect_symbol("]")?;
+		} else if self.is_keyword("int") {
+			self.advance();
+		} else {
+			return Err(self.unexpected("an integer range or `int`"));
+		}
+		let initial_value = if self.is_keyword("init") {
+			self.advance();
+			let init = self.parse_expression()?;
+			evaluate_integer(&init, constants, self.lines)?
+		} else {
+			lower_bound
+		};
+		self.expect_symbol(";")?;
+		Ok(PrismVariable {
+			name,
+			initial_value,
+		})
+	}
+
+	/// Parses `[action] GUARD -> RATE : UPDATES;`
+	fn parse_command(&mut self, module: &str) -> Result<PrismCommand, PositionedParseError> {
+		let position = self.expect_symbol("[")?;
+		let action = if self.is_symbol("]") {
+			None
+		} else {
+			Some(self.expect_identifier()?.0)
+		};
+		self.expect_symbol("]")?;
+		let guard = self.parse_expression()?;
+		self.expect_symbol("->")?;
+		let (rate, updates) = if self.starts_updates() {
+			(None, self.parse_updates()?)
+		} else {
+			let rate = self.parse_expression()?;
+			self.expect_symbol(":")?;
+			(Some(rate), self.parse_updates()?)
+		};
+		if self.is_symbol("+") {
+			return Err(self.error_here(
+				"Commands with more than one probabilistic branch are not supported".to_string(),
+			));
+		}
+		self.expect_symbol(";")?;
+		Ok(PrismCommand {
+			module: module.to_string(),
+			action,
+			guard,
+			rate,
+			updates,
+			position,
+		})
+	}
+
+	/// Whether the next tokens are updates (`true;` or `(X' = ...)`) rather than a rate
+	fn starts_updates(&self) -> bool {
+		(self.is_keyword("true") && self.peek_at(1) == Some(&PrismToken::Symbol(";")))
+			|| (self.is_symbol("(")
+				&& matches!(self.peek_at(1), Some(PrismToken::Identifier(_)))
+				&& self.peek_at(2) == Some(&PrismToken::Symbol("'")))
+	}
+
+	/// Parses `true` or `(X' = X + n) & (Y' = Y - m) & ...`
+	fn parse_updates(&mut self) -> Result<Vec<PrismUpdate>, PositionedParseError> {
+		if self.is_keyword("true") {
+			self.advance();
+			return Ok(Vec::new());
+		}
+		let mut updates = Vec::new();
+		loop {
+			self.expect_symbol("(")?;
+			let (variable, position) = self.expect_identifier()?;
+			self.expect_symbol("'")?;
+			self.expect_symbol("=")?;
+			let expr = self.parse_expression()?;
+			self.expect_symbol(")")?;
+			let delta = update_delta(&variable, &expr).ok_or_else(|| {
+				error_at(
+					self.lines,
+					expr.position,
+					format!(
+						"Unsupported update for `{}`: only `{}' = {} + n` and `{}' = {} - n` are supported",
+						variable, variable, variable, variable, variable
+					),
+				)
+			})?;
+			updates.push(PrismUpdate {
+				variable,
+				delta,
+				position,
+			});
+			if self.is_symbol("&") {
+				self.advance();
+			} else {
+				return Ok(updates);
+			}
+		}
+	}
+
+	fn parse_expression(&mut self) -> Result<Expr, PositionedParseError> {
+		self.parse_or()
+	}
+
+	fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+		let position = lhs.position;
+		Expr {
+			kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+			position,
+		}
+	}
+
+	fn parse_or(&mut self) -> Result<Expr, PositionedParseError> {
+		let mut expr = self.parse_and()?;
+		while self.is_symbol("|") {
+			self.advance();
+			let rhs = self.parse_and()?;
+			expr = Self::binary(BinaryOp::Or, expr, rhs);
+		}
+		Ok(expr)
+	}
+
+	fn parse_and(&mut self) -> Result<Expr, PositionedParseError> {
+		let mut expr = self.parse_not()?;
+		while self.is_symbol("&") {
+			self.advance();
+			let rhs = self.parse_not()?;
+			expr = Self::binary(BinaryOp::And, expr, rhs);
+		}
+		Ok(expr)
+	}
+
+	fn parse_not(&mut self) -> Result<Expr, PositionedParseError> {
+		if self.is_symbol("!") {
+			let position = self.advance().unwrap().1;
+			let inner = self.parse_not()?;
+			return Ok(Expr {
+				kind: ExprKind::Not(Box::new(inner)),
+				position,
+			});
+		}
+		self.parse_relation()
+	}
+
+	fn parse_relation(&mut self) -> Result<Expr, PositionedParseError> {
+		let lhs = self.parse_additive()?;
+		let relation = match self.peek() {
+			Some(PrismToken::Symbol(symbol)) => symbol.parse::<AllowedRelation>().ok(),
+			_ => None,
+		};
+		match relation {
+			Some(relation) => {
+				self.advance();
+				let rhs = self.parse_additive()?;
+				Ok(Self::binary(BinaryOp::Relation(relation), lhs, rhs))
+			}
+			None => Ok(lhs),
+		}
+	}
+
+	fn parse_additive(&mut self) -> Result<Expr, PositionedParseError> {
+		let mut expr = self.parse_multiplicative()?;
+		loop {
+			let op = if self.is_symbol("+") {
+				BinaryOp::Add
+			} else if self.is_symbol("-") {
+				BinaryOp::Subtract
+			} else {
+				return Ok(expr);
+			};
+			self.advance();
+			let rhs = self.parse_multiplicative()?;
+			expr = Self::binary(op, expr, rhs);
+		}
+	}
+
+	fn parse_multiplicative(&mut self) -> Result<Expr, PositionedParseError> {
+		let mut expr = self.parse_unary()?;
+		loop {
+			let op = if self.is_symbol("*") {
+				BinaryOp::Multiply
+			} else if self.is_symbol("/") {
+				BinaryOp::Divide
+			} else {
+				return Ok(expr);
+			};
+			self.advance();
+			let rhs = self.parse_unary()?;
+			expr = Self::binary(op, expr, rhs);
+		}
+	}
+
+	fn parse_unary(&mut self) -> Result<Expr, PositionedParseError> {
+		if self.is_symbol("-") {
+			let position = self.advance().unwrap().1;
+			let inner = self.parse_unary()?;
+			return Ok(Expr {
+				kind: ExprKind::Negate(Box::new(inner)),
+				position,
+			});
+		}
+		self.parse_primary()
+	}
+
+	fn parse_primary(&mut self) -> Result<Expr, PositionedParseError> {
+		let position = self.current_position();
+		match self.peek().cloned() {
+			Some(PrismToken::Number(literal)) => {
+				self.advance();
+				let value = literal.parse::<f64>().map_err(|_| {
+					error_at(
+						self.lines,
+						position,
+						format!("Invalid number `{}`", literal),
+					)
+				})?;
+				Ok(Expr {
+					kind: ExprKind::Number(value),
+					position,
+				})
+			}
+			Some(PrismToken::Identifier(name)) => {
+				self.advance();
+				let kind = match name.as_str() {
+					"true" => ExprKind::Boolean(true),
+					"false" => ExprKind::Boolean(false),
+					_ if self.is_symbol("(") => {
+						return Err(error_at(
+							self.lines,
+							position,
+							format!("Function `{}` is not supported", name),
+						));
+					}
+					_ => ExprKind::Identifier(name),
+				};
+				Ok(Expr { kind, position })
+			}
+			Some(PrismToken::Symbol("(")) => {
+				self.advance();
+				let expr = self.parse_expression()?;
+				self.expect_symbol(")")?;
+				Ok(expr)
+			}
+			_ => Err(self.unexpected("an expression")),
+		}
+	}
+}
+
+/// Reads the change `n` from an update expression `X + n` or `X - n`,
+/// where `X` is the updated variable itself
+fn update_delta(variable: &str, expr: &Expr) -> Option<VasValue> {
+	let is_variable = |e: &Expr| matches!(&e.kind, ExprKind::Identifier(name) if name == variable);
+	let integer = |e: &Expr| match e.kind {
+		ExprKind::Number(value) if value.fract() == 0.0 => Some(value as VasValue),
+		_ => None,
+	};
+	match &expr.kind {
+		ExprKind::Identifier(_) if is_variable(expr) => Some(0),
+		ExprKind::Binary(BinaryOp::Add, lhs, rhs) if is_variable(lhs) => integer(rhs),
+		ExprKind::Binary(BinaryOp::Add, lhs, rhs) if is_variable(rhs) => integer(lhs),
+		ExprKind::Binary(BinaryOp::Subtract, lhs, rhs) if is_variable(lhs) => {
+			integer(rhs).map(|n| -n)
+		}
+		_ => None,
+	}
+}
+
+/// Evaluates an expression over constants only
+fn evaluate_constant(
+	expr: &Expr,
+	constants: &HashMap<String, f64>,
+	lines: &[String],
+) -> Result<f64, PositionedParseError> {
+	match &expr.kind {
+		ExprKind::Number(value) => Ok(*value),
+		ExprKind::Identifier(name) => constants.get(name).copied().ok_or_else(|| {
+			error_at(
+				lines,
+				expr.position,
+				format!("`{}` is not a defined constant", name),
+			)
+		}),
+		ExprKind::Negate(inner) => Ok(-evaluate_constant(inner, constants, lines)?),
+		ExprKind::Binary(op, lhs, rhs) => {
+			let lhs_value = evaluate_constant(lhs, constants, lines)?;
+			let rhs_value = evaluate_constant(rhs, constants, lines)?;
+			match op {
+				BinaryOp::Add => Ok(lhs_value + rhs_value),
+				BinaryOp::Subtract => Ok(lhs_value - rhs_value),
+				BinaryOp::Multiply => Ok(lhs_value * rhs_value),
+				BinaryOp::Divide => Ok(lhs_value / rhs_value),
+				_ => Err(error_at(
+					lines,
+					expr.position,
+					"Expected a numeric expression".to_string(),
+				)),
+			}
+		}
+		_ => Err(error_at(
+			lines,
+			expr.position,
+			"Expected a numeric expression".to_string(),
+		)),
+	}
+}
+
+/// Evaluates a constant expression that must be an integer
+fn evaluate_integer(
+	expr: &Expr,
+	constants: &HashMap<String, f64>,
+	lines: &[String],
+) -> Result<VasValue, PositionedParseError> {
+	let value = evaluate_constant(expr, constants, lines)?;
+	if value.fract() != 0.0 {
+		return Err(error_at(
+			lines,
+			expr.position,
+			format!("Expected an integer, got `{}`", value),
+		));
+	}
+	Ok(value as VasValue)
+}
+
+/// A mass-action rate: a constant times the product of species counts. Each
+/// species factor `X - i` is recorded by its offset `i`, so `X*(X-1)` gives
+/// the offsets `[0, 1]`.
+struct MassAction {
+	constant: ProbabilityOrRate,
+	offsets: Vec<Vec<VasValue>>,
+}
+
+impl PrismProgram {
+	fn variable_index(&self, name: &str) -> Option<usize> {
+		self.variables.iter().position(|v| v.name == name)
+	}
+
+	/// Resolves the constants and species of an expression for `StateExpr::evaluate`
+	fn state_expression(
+		&self,
+		expr: &Expr,
+		lines: &[String],
+	) -> Result<StateExpr, PositionedParseError> {
+		Ok(match &expr.kind {
+			ExprKind::Number(value) => StateExpr::Number(*value),
+			ExprKind::Boolean(value) => StateExpr::Number(if *value { 1.0 } else { 0.0 }),
+			ExprKind::Identifier(name) => match self.variable_index(name) {
+				Some(index) => StateExpr::Species(index),
+				None => StateExpr::Number(evaluate_constant(expr, &self.constants, lines)?),
+			},
+			ExprKind::Not(inner) => StateExpr::Not(Box::new(self.state_expression(inner, lines)?)),
+			ExprKind::Negate(inner) => {
+				StateExpr::Negate(Box::new(self.state_expression(inner, lines)?))
+			}
+			ExprKind::Binary(op, lhs, rhs) => StateExpr::Binary(
+				*op,
+				Box::new(self.state_expression(lhs, lines)?),
+				Box::new(self.state_expression(rhs, lines)?),
+			),
+		})
+	}
+
+	/// Decomposes a rate expression into a mass-action term, rejecting anything else
+	fn mass_action(
+		&self,
+		expr: &Expr,
+		lines: &[String],
+	) -> Result<MassAction, PositionedParseError> {
+		let not_mass_action = |position: Position| {
+			error_at(
+				lines,
+				position,
+				"Rate is not mass-action: expected a constant times a product of species counts"
+					.to_string(),
+			)
+		};
+		let mut offsets = vec![Vec::new(); self.variables.len()];
+		match &expr.kind {
+			ExprKind::Identifier(name) => {
+				if let Some(index) = self.variable_index(name) {
+					offsets[index].push(0);
+					Ok(MassAction {
+						constant: 1.0,
+						offsets,
+					})
+				} else {
+					Ok(MassAction {
+						constant: evaluate_constant(expr, &self.constants, lines)?,
+						offsets,
+					})
+				}
+			}
+			// A falling factorial factor such as `X-1`
+			ExprKind::Binary(BinaryOp::Subtract, lhs, rhs) if matches!(&lhs.kind, ExprKind::Identifier(name) if self.variable_index(name).is_some()) =>
+			{
+				let ExprKind::Identifier(name) = &lhs.kind else {
+					unreachable!()
+				};
+				let offset = evaluate_integer(rhs, &self.constants, lines)
+					.map_err(|_| not_mass_action(rhs.position))?;
+				offsets[self.variable_index(name).unwrap()].push(offset);
+				Ok(MassAction {
+					constant: 1.0,
+					offsets,
+				})
+			}
+			ExprKind::Binary(BinaryOp::Multiply, lhs, rhs) => {
+				let lhs = self.mass_action(lhs, lines)?;
+				let rhs = self.mass_action(rhs, lines)?;
+				let offsets = lhs
+					.offsets
+					.into_iter()
+					.zip(rhs.offsets)
+					.map(|(mut l, r)| {
+						l.extend(r);
+						l
+					})
+					.collect();
+				Ok(MassAction {
+					constant: lhs.constant * rhs.constant,
+					offsets,
+				})
+			}
+			ExprKind::Binary(BinaryOp::Divide, lhs, rhs) => {
+				let lhs = self.mass_action(lhs, lines)?;
+				let divisor = evaluate_constant(rhs, &self.constants, lines)
+					.map_err(|_| not_mass_action(rhs.position))?;
+				Ok(MassAction {
+					constant: lhs.constant / divisor,
+					offsets: lhs.offsets,
+				})
+			}
+			_ => match evaluate_constant(expr, &self.constants, lines) {
+				Ok(constant) => Ok(MassAction { constant, offsets }),
+				Err(_) => Err(not_mass_action(expr.position)),
+			},
+		}
+	}
+
+	/// Computes the lower bound each species must meet for a guard to hold.
+	/// Upper bounds are dropped, since the VAS does not bound species counts.
+	fn guard_bounds(
+		&self,
+		expr: &Expr,
+		lines: &[String],
+	) -> Result<Vec<VasValue>, PositionedParseError> {
+		let mut bounds = vec![0; self.variables.len()];
+		let unsupported = || {
+			error_at(
+				lines,
+				expr.position,
+				format!(
+					"Unsupported guard `{}`: expected lower bounds on species, conjoined with `&`",
+					expr
+				),
+			)
+		};
+		match &expr.kind {
+			ExprKind::Boolean(true) => {}
+			ExprKind::Binary(BinaryOp::And, lhs, rhs) => {
+				let lhs = self.guard_bounds(lhs, lines)?;
+				let rhs = self.guard_bounds(rhs, lines)?;
+				bounds = lhs.iter().zip(&rhs).map(|(l, r)| *l.max(r)).collect();
+			}
+			ExprKind::Binary(BinaryOp::Relation(relation), lhs, rhs) => {
+				// Normalize to `species <relation> constant`
+				let lhs_constant = evaluate_constant(lhs, &self.constants, lines).ok();
+				let rhs_constant = evaluate_constant(rhs, &self.constants, lines).ok();
+				let (relation, species, constant) = match (lhs_constant, rhs_constant) {
+					// Guards over constants only, e.g., `k > 0`
+					(Some(lhs_value), Some(rhs_value)) => {
+						if relation.holds(lhs_value, rhs_value) {
+							return Ok(bounds);
+						}
+						return Err(error_at(
+							lines,
+							expr.position,
+							format!("Guard `{}` can never be satisfied", expr),
+						));
+					}
+					(_, Some(value)) => (*relation, lhs, value),
+					(Some(value), None) => (relation.flip(), rhs, value),
+					(None, None) => return Err(unsupported()),
+				};
+				match (relation, &species.kind) {
+					(
+						AllowedRelation::LessThan | AllowedRelation::LessThanOrEqual,
+						ExprKind::Identifier(name),
+					) if self.variable_index(name).is_some() => {
+						warning!(
+							"Dropping upper bound `{}` from PRISM guard, since the VAS does not bound species counts",
+							expr
+						);
+					}
+					(AllowedRelation::GreaterThanOrEqual, ExprKind::Identifier(name)) => {
+						let index = self.variable_index(name).ok_or_else(unsupported)?;
+						bounds[index] = constant.ceil() as VasValue;
+					}
+					(AllowedRelation::GreaterThan, ExprKind::Identifier(name)) => {
+						let index = self.variable_index(name).ok_or_else(unsupported)?;
+						bounds[index] = constant.floor() as VasValue + 1;
+					}
+					// `rate > 0` holds exactly when every species in the rate is present
+					(AllowedRelation::GreaterThan, _) if constant == 0.0 => {
+						let rate = self.mass_action(species, lines)?;
+						if rate.constant <= 0.0 {
+							return Err(unsupported());
+						}
+						bounds = rate
+							.offsets
+							.iter()
+							.map(|o| (o.len() as VasValue).min(1))
+							.collect();
+					}
+					_ => return Err(unsupported()),
+				}
+			}
+			_ => return Err(unsupported()),
+		}
+		Ok(bounds)
+	}
+
+	/// The update of a transition: the change to each species made by its commands
+	fn group_update(
+		&self,
+		name: &str,
+		commands: &[&PrismCommand],
+		lines: &[String],
+	) -> Result<Vec<VasValue>, PositionedParseError> {
+		let mut update = vec![0; self.variables.len()];
+		let mut updated = vec![false; self.variables.len()];
+		for prism_update in commands.iter().flat_map(|command| &command.updates) {
+			let index = self.variable_index(&prism_update.variable).ok_or_else(|| {
+				error_at(
+					lines,
+					prism_update.position,
+					format!("Unknown variable `{}`", prism_update.variable),
+				)
+			})?;
+			if updated[index] {
+				return Err(error_at(
+					lines,
+					prism_update.position,
+					format!(
+						"`{}` is updated more than once by action `{}`",
+						prism_update.variable, name
+					),
+				));
+			}
+			updated[index] = true;
+			update[index] = prism_update.delta;
+		}
+		Ok(update)
+	}
+
+	/// Builds a transition whose rate is not mass-action, e.g., the net rate
+	/// `kf*A - kr*B` of a reversible reaction. Its rate is the product of the
+	/// rates of its commands where all of their guards hold, and 0 elsewhere
+	/// (or where the product is negative).
+	fn custom_transition(
+		&self,
+		transition_id: usize,
+		name: String,
+		commands: &[&PrismCommand],
+		update: Vec<VasValue>,
+		lines: &[String],
+	) -> Result<VasTransition, PositionedParseError> {
+		let guards = commands
+			.iter()
+			.map(|command| self.state_expression(&command.guard, lines))
+			.collect::<Result<Vec<_>, _>>()?;
+		let rates = commands
+			.iter()
+			.filter_map(|command| command.rate.as_ref())
+			.map(|rate| self.state_expression(rate, lines))
+			.collect::<Result<Vec<_>, _>>()?;
+		warning!(
+			"Rate of `{}` is not mass-action. It is evaluated, with its guard, as a custom rate function.",
+			name
+		);
+		let decrement: Vec<VasValue> = update.iter().map(|delta| (-delta).max(0)).collect();
+		let increment: Vec<VasValue> = decrement
+			.iter()
+			.zip(&update)
+			.map(|(consumed, delta)| consumed + delta)
+			.collect();
+		let mut transition = VasTransition::new(
+			transition_id,
+			name,
+			increment.into_boxed_slice(),
+			decrement.into_boxed_slice(),
+			1.0,
+		);
+		transition.set_custom_rate_fn(Arc::new(move |state: &VasState| {
+			if guards
+				.iter()
+				.any(|guard| guard.evaluate(&state.vector) == 0.0)
+			{
+				return 0.0;
+			}
+			// `max` also turns a NaN rate into 0
+			rates
+				.iter()
+				.map(|rate| rate.evaluate(&state.vector))
+				.product::<f64>()
+				.max(0.0)
+		}));
+		Ok(transition)
+	}
+
+	/// Combines synchronized commands into VAS transitions and builds the model
+	fn into_vas(
+		self,
+		lines: &[String],
+		filename: &str,
+		options: &ModelOptions,
+	) -> Result<AbstractVas, PositionedParseError> {
+		if self.variables.is_empty() {
+			return Err(PositionedParseError::without_position(
+				"The model declares no variables".to_string(),
+			));
+		}
+		// Group commands by action label, keeping unlabeled commands on their own
+		let mut groups: Vec<(String, Vec<&PrismCommand>)> = Vec::new();
+		let mut unlabeled_count: HashMap<&str, usize> = HashMap::new();
+		for command in &self.commands {
+			match &command.action {
+				Some(action) => match groups.iter_mut().find(|(name, _)| name == action) {
+					Some((_, commands)) => {
+						if commands.iter().any(|c| c.module == command.module) {
+							return Err(error_at(
+								lines,
+								command.position,
+								format!(
+									"Module `{}` has more than one command labeled `{}`",
+									command.module, action
+								),
+							));
+						}
+						commands.push(command);
+					}
+					None => groups.push((action.clone(), vec![command])),
+				},
+				None => {
+					let count = unlabeled_count.entry(&command.module).or_insert(0);
+					groups.push((format!("{}_{}", command.module, count), vec![command]));
+					*count += 1;
+				}
+			}
+		}
+
+		let num_variables = self.variables.len();
+		let mut transitions = Vec::new();
+		for (transition_id, (name, commands)) in groups.into_iter().enumerate() {
+			let update = self.group_update(&name, &commands, lines)?;
+			let is_mass_action = commands
+				.iter()
+				.filter_map(|command| command.rate.as_ref())
+				.all(|rate| self.mass_action(rate, lines).is_ok());
+			if !is_mass_action {
+				transitions.push(self.custom_transition(
+					transition_id,
+					name,
+					&commands,
+					update,
+					lines,
+				)?);
+				continue;
+			}
+			let mut rate_const = 1.0;
+			let mut offsets = vec![Vec::new(); num_variables];
+			let mut guard = vec![0; num_variables];
+			for command in &commands {
+				if let Some(rate) = &command.rate {
+					let rate = self.mass_action(rate, lines)?;
+					rate_const *= rate.constant;
+					for (total, o) in offsets.iter_mut().zip(rate.offsets) {
+						total.extend(o);
+					}
+				}
+				let bounds = self.guard_bounds(&command.guard, lines)?;
+				for i in 0..num_variables {
+					guard[i] = guard[i].max(bounds[i]);
+				}
+			}
+			// The rate is computed from the enabled bounds (see `VasTransition::get_sck_rate`),
+			// so every species that bounds the transition n times must appear in the rate as
+			// the falling factorial `X*(X-1)*...*(X-n+1)`. Under the stochastic convention,
+			// that is `n!` times `X choose n`.
+			let position = commands[0].position;
+			let mut decrement = vec![0; num_variables];
+			for i in 0..num_variables {
+				let species = &self.variables[i].name;
+				let mut species_offsets = offsets[i].clone();
+				species_offsets.sort();
+				let multiplicity = species_offsets.len() as VasValue;
+				if species_offsets != (0..multiplicity).collect::<Vec<_>>() {
+					return Err(error_at(
+						lines,
+						position,
+						format!(
+							"Rate of `{}` must use `{}` as a falling factorial, e.g., `{}*({}-1)`",
+							name, species, species, species
+						),
+					));
+				}
+				let consumed = (-update[i]).max(0);
+				if consumed.max(guard[i]) > multiplicity {
+					return Err(error_at(
+						lines,
+						position,
+						format!(
+							"Rate of `{}` is not mass-action: it requires {} of `{}` but its rate has {} factor(s) of it",
+							name,
+							consumed.max(guard[i]),
+							species,
+							multiplicity
+						),
+					));
+				}
+				rate_const *= (1..=multiplicity).product::<VasValue>() as ProbabilityOrRate;
+				decrement[i] = multiplicity;
+			}
+			let increment: Vec<VasValue> = (0..num_variables)
+				.map(|i| decrement[i] + update[i])
+				.collect();
+			transitions.push(VasTransition::new(
+				transition_id,
+				name,
+				increment.into_boxed_slice(),
+				decrement.into_boxed_slice(),
+				rate_const,
+			));
+		}
+
+		let variable_names: Box<[String]> = self.variables.iter().map(|v| v.name.clone()).collect();
+		// A property file given with `--prop` replaces the model's own target label
+		let target = match (&self.target_label, &options.property_file) {
+			(Some(expr), None) => parse_target(&expr.to_string(), &variable_names)
+				.map_err(|e| error_at(lines, expr.position, e))?,
+			_ => companion_target(filename, options, &variable_names).map_err(|e| {
+				PositionedParseError::without_position(format!(
+					"{} (or add `label \"target\" = ...;` to the model)",
+					e
+				))
+			})?,
+		};
+		let initial_state = VasState::new(DVector::from_iterator(
+			num_variables,
+			self.variables.iter().map(|v| v.initial_value),
+		));
+		Ok(AbstractVas::new(
+			variable_names,
+			vec![initial_state],
+			transitions,
+			target,
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use nalgebra::DVector;
+
+	use super::PrismParser;
+	use crate::{
+		model::{
+			model::Transition,
+			vas_model::{AbstractVas, VasState},
+		},
+		parser::parser::{ModelOptions, ModelParseError, Parser, PositionedParseError},
+	};
+
+	/// Parses `source` as a PRISM model written to a temporary file
+	fn parse(name: &str, source: &str) -> Result<AbstractVas, PositionedParseError> {
+		let directory = std::env::temp_dir().join(format!("prism_{}_{}", name, std::process::id()));
+		fs::create_dir_all(&directory).unwrap();
+		let model_file = directory.join(format!("{}.sm", name));
+		fs::write(&model_file, source).unwrap();
+		let result = PrismParser::parse(model_file.to_str().unwrap(), &ModelOptions::default());
+		fs::remove_dir_all(&directory).unwrap();
+		result
+	}
+
+	#[test]
+	fn synchronized_commands_form_one_transition() {
+		let model = parse(
+			"sync",
+			"ctmc
+const double k = 0.5;
+module A
+  A : int init 3;
+  [R0] A >= 1 -> (A' = A - 1);
+endmodule
+module B
+  B : int init 0;
+  [R0] true -> (B' = B + 1);
+endmodule
+module rates
+  [R0] true -> k * A : true;
+endmodule
+label \"target\" = B = 3;
+",
+		)
+		.unwrap();
+		assert_eq!(model.transitions.len(), 1);
+		let transition = &model.transitions[0];
+		assert_eq!(transition.update_vector, DVector::from_vec(vec![-1, 1]));
+		assert_eq!(transition.consume_vector, DVector::from_vec(vec![1, 0]));
+		let state = DVector::from_vec(vec![3, 0]);
+		assert!((transition.get_sck_rate(&state) - 1.5).abs() < 1e-12);
+	}
+
+	#[test]
+	fn falling_factorial_is_mass_action() {
+		let model = parse(
+			"dimer",
+			"ctmc
+const double k = 0.1;
+module dimer
+  X : int init 10;
+  Y : int init 0;
+  [R0] X >= 2 -> k * X * (X - 1) : (X' = X - 2) & (Y' = Y + 1);
+endmodule
+label \"target\" = Y = 5;
+",
+		)
+		.unwrap();
+		let transition = &model.transitions[0];
+		assert!(transition.custom_rate_fn.is_none());
+		assert_eq!(transition.consume_vector, DVector::from_vec(vec![2, 0]));
+		// The stochastic rate constant absorbs the 2! of `X choose 2`
+		assert!((transition.rate_const - 0.2).abs() < 1e-12);
+		let state = DVector::from_vec(vec![10, 0]);
+		assert!((transition.get_sck_rate(&state) - 0.1 * 10.0 * 9.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn other_rates_fall_back_to_custom_rate() {
+		let model = parse(
+			"isomerization",
+			"ctmc
+const double kf = 0.12;
+const double kr = 1.0;
+module isomerization
+  A : int init 100;
+  B : int init 0;
+  [R0] A >= 1 -> kf * A - kr * B : (A' = A - 1) & (B' = B + 1);
+endmodule
+label \"target\" = B = 30;
+",
+		)
+		.unwrap();
+		let transition = &model.transitions[0];
+		assert!(transition.custom_rate_fn.is_some());
+		let state = VasState::new(DVector::from_vec(vec![100, 2]));
+		let rate = transition.rate_probability_at(&state).unwrap();
+		assert!((rate - (0.12 * 100.0 - 2.0)).abs() < 1e-9);
+		// A negative rate disables the transition
+		let state = VasState::new(DVector::from_vec(vec![1, 50]));
+		assert!(!transition.enabled(&state));
+		assert_eq!(transition.rate_probability_at(&state), None);
+	}
+
+	#[test]
+	fn upper_bound_in_guard_is_dropped() {
+		let model = parse(
+			"bounded",
+			"ctmc
+const double k = 2.0;
+module bounded
+  X : int init 0;
+  [R0] X < 5 -> k : (X' = X + 1);
+endmodule
+label \"target\" = X = 20;
+",
+		)
+		.unwrap();
+		let transition = &model.transitions[0];
+		let state = DVector::from_vec(vec![10]);
+		assert!(transition.enabled_vector(&state));
+		assert!((transition.get_sck_rate(&state) - 2.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn errors_report_their_position() {
+		let error = parse(
+			"position",
+			"ctmc
+module M
+  X : int init 0 $;
+endmodule
+",
+		)
+		.err()
+		.unwrap();
+		assert_eq!(error.line(), (3, "  X : int init 0 $;".to_string()));
+		assert_eq!(error.column(), Some(17));
+		let error = parse("dtmc", "dtmc\nmodule M\n  X : int init 0;\nendmodule\n")
+			.err()
+			.unwrap();
+		assert_eq!(error.line().0, 1);
+		assert_eq!(error.column(), Some(0));
+	}
+}
@@ -0,0 +1,801 @@
+use std::{collections::HashMap, fs, sync::Arc};
+
+use nalgebra::DVector;
+use roxmltree::{Document, Node, TextPos};
+
+use crate::{
+	model::{
+		model::ProbabilityOrRate,
+		vas_model::{
+			AbstractVas, RateConvention, VasState, VasStateVector, VasTransition, VasValue,
+		},
+	},
+	parser::{
+		csl_parser::companion_target,
+		parameters::{evaluate_float, ParameterContext},
+		parser::{ModelOptions, Parser, PositionedParseError},
+	},
+	warning,
+};
+
+/// Model components outside of the supported subset of SBML
+const UNSUPPORTED_COMPONENTS: &[&str] = &[
+	"listOfRules",
+	"listOfEvents",
+	"listOfInitialAssignments",
+	"listOfConstraints",
+];
+/// Tolerance when reading integer counts and stoichiometries from floats
+const INTEGER_TOLERANCE: f64 = 1e-9;
+
+/// Parser for SBML Level 3 core models of chemical reaction networks.
+/// Species amounts become the initial state, reactant and product
+/// stoichiometries become the transition vectors, and mass-action kinetic
+/// laws become rate constants. Other closed-form kinetic laws are evaluated
+/// as custom rate functions. The target is read from the companion `.prop`.
+pub(crate) struct SbmlParser;
+
+impl Parser for SbmlParser {
+	type ModelType = AbstractVas;
+	type ParserErrorType = PositionedParseError;
+
+	fn parse(
+		filename: &str,
+		options: &ModelOptions,
+	) -> Result<Self::ModelType, Self::ParserErrorType> {
+		let source = fs::read_to_string(filename).map_err(|e| {
+			PositionedParseError::without_position(format!("Unable to read `{}`: {}", filename, e))
+		})?;
+		let lines: Vec<String> = source.lines().map(|line| line.to_string()).collect();
+		let document = Document::parse(&source)
+			.map_err(|e| error_at(&lines, e.pos(), format!("Invalid XML: {}", e)))?;
+		let reader = SbmlReader {
+			document: &document,
+			lines: &lines,
+			options,
+		};
+		reader.read_model(filename)
+	}
+}
+
+/// A parse error at the given (one-based) position of an SBML model
+fn error_at(lines: &[String], position: TextPos, message: String) -> PositionedParseError {
+	PositionedParseError::at(
+		lines,
+		position.row.saturating_sub(1) as usize,
+		position.col.saturating_sub(1) as usize,
+		message,
+	)
+}
+
+/// MathML operators allowed in kinetic laws
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MathOperator {
+	Plus,
+	Minus,
+	Times,
+	Divide,
+	Power,
+	Exp,
+	Ln,
+	Log,
+	Root,
+	Abs,
+	Floor,
+	Ceiling,
+}
+
+impl MathOperator {
+	fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"plus" => Some(Self::Plus),
+			"minus" => Some(Self::Minus),
+			"times" => Some(Self::Times),
+			"divide" => Some(Self::Divide),
+			"power" => Some(Self::Power),
+			"exp" => Some(Self::Exp),
+			"ln" => Some(Self::Ln),
+			"log" => Some(Self::Log),
+			"root" => Some(Self::Root),
+			"abs" => Some(Self::Abs),
+			"floor" => Some(Self::Floor),
+			"ceiling" => Some(Self::Ceiling),
+			_ => None,
+		}
+	}
+}
+
+/// A kinetic law with its parameters and compartments replaced by their values
+#[derive(Clone, Debug, PartialEq)]
+enum RateExpr {
+	Constant(ProbabilityOrRate),
+	Species(usize),
+	Apply(MathOperator, Vec<RateExpr>),
+}
+
+impl RateExpr {
+	/// Evaluates the law with species counts taken from the state
+	fn evaluate(&self, state: &VasStateVector) -> ProbabilityOrRate {
+		match self {
+			RateExpr::Constant(value) => *value,
+			RateExpr::Species(index) => state[*index] as ProbabilityOrRate,
+			RateExpr::Apply(operator, arguments) => {
+				let values: Vec<ProbabilityOrRate> =
+					arguments.iter().map(|a| a.evaluate(state)).collect();
+				match (operator, values.as_slice()) {
+					(MathOperator::Plus, _) => values.iter().sum(),
+					(MathOperator::Times, _) => values.iter().product(),
+					(MathOperator::Minus, [x]) => -x,
+					(MathOperator::Minus, [x, y]) => x - y,
+					(MathOperator::Divide, [x, y]) => x / y,
+					(MathOperator::Power, [x, y]) => x.powf(*y),
+					(MathOperator::Exp, [x]) => x.exp(),
+					(MathOperator::Ln, [x]) => x.ln(),
+					(MathOperator::Log, [x]) => x.log10(),
+					(MathOperator::Log, [base, x]) => x.log(*base),
+					(MathOperator::Root, [x]) => x.sqrt(),
+					(MathOperator::Root, [degree, x]) => x.powf(1.0 / degree),
+					(MathOperator::Abs, [x]) => x.abs(),
+					(MathOperator::Floor, [x]) => x.floor(),
+					(MathOperator::Ceiling, [x]) => x.ceil(),
+					// Arity is checked when the law is read
+					_ => ProbabilityOrRate::NAN,
+				}
+			}
+		}
+	}
+
+	fn contains_species(&self) -> bool {
+		match self {
+			RateExpr::Constant(_) => false,
+			RateExpr::Species(_) => true,
+			RateExpr::Apply(_, arguments) => arguments.iter().any(|a| a.contains_species()),
+		}
+	}
+
+	/// Splits a mass-action law into its rate constant and the species it
+	/// multiplies, e.g., `k * A * B / V` gives `(k / V, [A, B])`. Integer
+	/// powers of species count as repeated factors.
+	fn mass_action(&self) -> Option<(ProbabilityOrRate, Vec<usize>)> {
+		if !self.contains_species() {
+			return Some((self.evaluate(&DVector::zeros(0)), Vec::new()));
+		}
+		match self {
+			RateExpr::Species(index) => Some((1.0, vec![*index])),
+			RateExpr::Apply(MathOperator::Times, arguments) => {
+				let mut rate_const = 1.0;
+				let mut species = Vec::new();
+				for argument in arguments {
+					let (factor, factor_species) = argument.mass_action()?;
+					rate_const *= factor;
+					species.extend(factor_species);
+				}
+				Some((rate_const, species))
+			}
+			RateExpr::Apply(MathOperator::Divide, arguments)
+				if !arguments[1].contains_species() =>
+			{
+				let (numerator, species) = arguments[0].mass_action()?;
+				let denominator = arguments[1].evaluate(&DVector::zeros(0));
+				Some((numerator / denominator, species))
+			}
+			RateExpr::Apply(MathOperator::Power, arguments) => match arguments.as_slice() {
+				[RateExpr::Species(index), RateExpr::Constant(exponent)]
+					if *exponent >= 1.0 && exponent.fract() == 0.0 =>
+				{
+					Some((1.0, vec![*index; *exponent as usize]))
+				}
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+}
+
+/// A species taking part in a reaction, with its stoichiometry
+struct SpeciesReference {
+	species: usize,
+	stoichiometry: VasValue,
+}
+
+struct SbmlReader<'a, 'input> {
+	document: &'a Document<'input>,
+	lines: &'a [String],
+	options: &'a ModelOptions,
+}
+
+impl<'a, 'input> SbmlReader<'a, 'input> {
+	fn error(&self, node: Node, message: String) -> PositionedParseError {
+		error_at(
+			self.lines,
+			self.document.text_pos_at(node.range().start),
+			message,
+		)
+	}
+
+	fn read_model(&self, filename: &str) -> Result<AbstractVas, PositionedParseError> {
+		let root = self.document.root_element();
+		if root.tag_name().name() != "sbml" {
+			return Err(self.error(root, "Expected an <sbml> document".to_string()));
+		}
+		if root.attribute("level") != Some("3") {
+			return Err(self.error(
+				root,
+				format!(
+					"Only SBML Level 3 is supported (found level {})",
+					root.attribute("level").unwrap_or("unknown")
+				),
+			));
+		}
+		let model = child(root, "model")
+			.ok_or_else(|| self.error(root, "No <model> element found".to_string()))?;
+		for component in model.children().filter(|n| n.is_element()) {
+			if UNSUPPORTED_COMPONENTS.contains(&component.tag_name().name()) {
+				return Err(self.error(
+					component,
+					format!(
+						"<{}> is not supported in SBML import",
+						component.tag_name().name()
+					),
+				));
+			}
+		}
+
+		// Compartment sizes and global parameters may appear in kinetic laws
+		let mut constants: HashMap<String, ProbabilityOrRate> = HashMap::new();
+		for compartment in list_items(model, "listOfCompartments", "compartment") {
+			let id = self.required_attribute(compartment, "id")?;
+			let size = match compartment.attribute("size") {
+				Some(_) => self.number_attribute(compartment, "size")?,
+				None => 1.0,
+			};
+			constants.insert(id.to_string(), size);
+		}
+		let mut parameter_names = Vec::new();
+		for parameter in list_items(model, "listOfParameters", "parameter") {
+			let id = self.required_attribute(parameter, "id")?;
+			// Values given on the command line replace those in the model
+			let value = match self.options.parameters.get(id) {
+				Some(value) => evaluate_float(value, &ParameterContext::new())
+					.map_err(|e| self.error(parameter, e))?,
+				None => self.parameter_value(parameter, id)?,
+			};
+			constants.insert(id.to_string(), value);
+			parameter_names.push(id.to_string());
+		}
+		self.options
+			.parameters
+			.warn_unused(&parameter_names, filename);
+
+		// Species
+		let mut variable_names = Vec::new();
+		let mut initial_values = Vec::new();
+		let mut boundary = Vec::new();
+		for species in list_items(model, "listOfSpecies", "species") {
+			let id = self.required_attribute(species, "id")?;
+			let amount = if species.attribute("initialAmount").is_some() {
+				self.number_attribute(species, "initialAmount")?
+			} else if species.attribute("initialConcentration").is_some() {
+				let size = species
+					.attribute("compartment")
+					.and_then(|c| constants.get(c))
+					.copied()
+					.unwrap_or(1.0);
+				self.number_attribute(species, "initialConcentration")? * size
+			} else {
+				return Err(self.error(species, format!("Species {} has no initialAmount", id)));
+			};
+			let initial_value = self.integer(species, amount, "initial amount")?;
+			if initial_value < 0 {
+				return Err(self.error(
+					species,
+					format!("Species {} has a negative initial amount", id),
+				));
+			}
+			variable_names.push(id.to_string());
+			initial_values.push(initial_value);
+			boundary.push(
+				species.attribute("boundaryCondition") == Some("true")
+					|| species.attribute("constant") == Some("true"),
+			);
+		}
+		if variable_names.is_empty() {
+			return Err(self.error(model, "The model has no species".to_string()));
+		}
+		let species_index: HashMap<String, usize> = variable_names
+			.iter()
+			.enumerate()
+			.map(|(i, name)| (name.clone(), i))
+			.collect();
+
+		// Reactions
+		let num_variables = variable_names.len();
+		let mut transitions = Vec::new();
+		for (transition_id, reaction) in
+			list_items(model, "listOfReactions", "reaction").enumerate()
+		{
+			let name = self.required_attribute(reaction, "id")?.to_string();
+			if reaction.attribute("reversible") == Some("true") {
+				return Err(self.error(
+					reaction,
+					format!(
+						"Reaction {} is reversible; split it into forward and reverse reactions",
+						name
+					),
+				));
+			}
+			let reactants = self.species_references(reaction, "listOfReactants", &species_index)?;
+			let products = self.species_references(reaction, "listOfProducts", &species_index)?;
+			let kinetic_law = child(reaction, "kineticLaw").ok_or_else(|| {
+				self.error(reaction, format!("Reaction {} has no kineticLaw", name))
+			})?;
+			let mut local_constants = constants.clone();
+			for list in ["listOfLocalParameters", "listOfParameters"] {
+				for parameter in list_items(kinetic_law, list, "") {
+					let id = self.required_attribute(parameter, "id")?;
+					let value = self.parameter_value(parameter, id)?;
+					local_constants.insert(id.to_string(), value);
+				}
+			}
+			let math = child(kinetic_law, "math").ok_or_else(|| {
+				self.error(kinetic_law, format!("Reaction {} has no <math>", name))
+			})?;
+			let law = math.children().find(|n| n.is_element()).ok_or_else(|| {
+				self.error(math, format!("Reaction {} has an empty <math>", name))
+			})?;
+			let rate = self.rate_expression(law, &local_constants, &species_index)?;
+
+			let mut decrement = vec![0; num_variables];
+			let mut update = vec![0; num_variables];
+			for reactant in &reactants {
+				decrement[reactant.species] += reactant.stoichiometry;
+				update[reactant.species] -= reactant.stoichiometry;
+			}
+			for product in &products {
+				update[product.species] += product.stoichiometry;
+			}
+			// Boundary species are never changed by reactions
+			for (i, is_boundary) in boundary.iter().enumerate() {
+				if *is_boundary {
+					update[i] = 0;
+				}
+			}
+
+			// The law is mass-action when each reactant appears as often as its
+			// stoichiometry, e.g., `k * A^2` for `2A -> B`, and any other species
+			// (a catalyst) once. The propensity then follows
+			// `VasTransition::get_sck_rate` under the deterministic convention.
+			let mass_action = rate.mass_action().filter(|(_, species)| {
+				(0..num_variables).all(|i| {
+					let count = species.iter().filter(|&&s| s == i).count() as VasValue;
+					if decrement[i] > 0 {
+						count == decrement[i]
+					} else {
+						count <= 1
+					}
+				})
+			});
+			let rate_const = match &mass_action {
+				Some((rate_const, species)) => {
+					// Catalysts and modifiers in the law must be present to fire
+					for &i in species {
+						decrement[i] = decrement[i].max(1);
+					}
+					*rate_const
+				}
+				None => 1.0,
+			};
+			let increment: Vec<VasValue> = (0..num_variables)
+				.map(|i| decrement[i] + update[i])
+				.collect();
+			let mut transition = VasTransition::new(
+				transition_id,
+				name.clone(),
+				increment.into_boxed_slice(),
+				decrement.into_boxed_slice(),
+				rate_const,
+			);
+			if mass_action.is_none() {
+				warning!(
+					"Reaction {} does not have a mass-action kinetic law. Its rate is evaluated as a custom rate function.",
+					name
+				);
+				transition.set_custom_rate_fn(Arc::new(move |state: &VasState| {
+					rate.evaluate(&state.vector)
+				}));
+			}
+			transitions.push(transition);
+		}
+		if transitions.is_empty() {
+			return Err(self.error(model, "The model has no reactions".to_string()));
+		}
+
+		let target = companion_target(filename, self.options, &variable_names)
+			.map_err(PositionedParseError::without_position)?;
+		let initial_state = VasState::new(DVector::from_vec(initial_values));
+		let mut model = AbstractVas::new(
+			variable_names.into_boxed_slice(),
+			vec![initial_state],
+			transitions,
+			target,
+		);
+		// SBML laws give the deterministic rate, e.g., `k * A^2`
+		model.set_rate_convention(RateConvention::Deterministic);
+		Ok(model)
+	}
+
+	fn required_attribute<'n>(
+		&self,
+		node: Node<'n, 'input>,
+		name: &str,
+	) -> Result<&'n str, PositionedParseError> {
+		node.attribute(name).ok_or_else(|| {
+			self.error(
+				node,
+				format!(
+					"<{}> is missing attribute `{}`",
+					node.tag_name().name(),
+					name
+				),
+			)
+		})
+	}
+
+	fn number_attribute(&self, node: Node, name: &str) -> Result<f64, PositionedParseError> {
+		let value = self.required_attribute(node, name)?;
+		value.trim().parse::<f64>().map_err(|_| {
+			self.error(
+				node,
+				format!("Attribute `{}` is not a number: {}", name, value),
+			)
+		})
+	}
+
+	fn parameter_value(&self, parameter: Node, id: &str) -> Result<f64, PositionedParseError> {
+		if parameter.attribute("value").is_none() {
+			return Err(self.error(parameter, format!("Parameter {} has no value", id)));
+		}
+		self.number_attribute(parameter, "value")
+	}
+
+	fn integer(
+		&self,
+		node: Node,
+		value: f64,
+		what: &str,
+	) -> Result<VasValue, PositionedParseError> {
+		if (value - value.round()).abs() > INTEGER_TOLERANCE {
+			return Err(self.error(
+				node,
+				format!("Expected an integer {}, found {}", what, value),
+			));
+		}
+		Ok(value.round() as VasValue)
+	}
+
+	/// Reads the reactants or products of a reaction
+	fn species_references(
+		&self,
+		reaction: Node,
+		list: &str,
+		species_index: &HashMap<String, usize>,
+	) -> Result<Vec<SpeciesReference>, PositionedParseError> {
+		let mut references = Vec::new();
+		for reference in list_items(reaction, list, "speciesReference") {
+			let id = self.required_attribute(reference, "species")?;
+			let species = *species_index
+				.get(id)
+				.ok_or_else(|| self.error(reference, format!("Unknown species: {}", id)))?;
+			let stoichiometry = match reference.attribute("stoichiometry") {
+				Some(_) => {
+					let value = self.number_attribute(reference, "stoichiometry")?;
+					self.integer(reference, value, "stoichiometry")?
+				}
+				None => 1,
+			};
+			references.push(SpeciesReference {
+				species,
+				stoichiometry,
+			});
+		}
+		Ok(references)
+	}
+
+	/// Reads a MathML expression, replacing parameters by their values
+	fn rate_expression(
+		&self,
+		node: Node,
+		constants: &HashMap<String, ProbabilityOrRate>,
+		species_index: &HashMap<String, usize>,
+	) -> Result<RateExpr, PositionedParseError> {
+		match node.tag_name().name() {
+			"cn" => self.math_number(node).map(RateExpr::Constant),
+			"ci" => {
+				let id = node.text().unwrap_or_default().trim();
+				if let Some(index) = species_index.get(id) {
+					Ok(RateExpr::Species(*index))
+				} else if let Some(value) = constants.get(id) {
+					Ok(RateExpr::Constant(*value))
+				} else {
+					Err(self.error(node, format!("Unknown identifier in kinetic law: {}", id)))
+				}
+			}
+			"apply" => {
+				let mut elements = node.children().filter(|n| n.is_element());
+				let head = elements
+					.next()
+					.ok_or_else(|| self.error(node, "Empty <apply>".to_string()))?;
+				let operator =
+					MathOperator::from_name(head.tag_name().name()).ok_or_else(|| {
+						self.error(
+							head,
+							format!(
+								"Unsupported operation in kinetic law: <{}>",
+								head.tag_name().name()
+							),
+						)
+					})?;
+				let mut arguments = Vec::new();
+				for element in elements {
+					match element.tag_name().name() {
+						// Qualifiers such as the degree of a root or base of a log
+						"degree" | "logbase" => {
+							let inner =
+								element.children().find(|n| n.is_element()).ok_or_else(|| {
+									self.error(element, "Empty qualifier".to_string())
+								})?;
+							arguments
+								.insert(0, self.rate_expression(inner, constants, species_index)?);
+						}
+						_ => arguments.push(self.rate_expression(
+							element,
+							constants,
+							species_index,
+						)?),
+					}
+				}
+				let arity_ok = match operator {
+					MathOperator::Plus | MathOperator::Times => !arguments.is_empty(),
+					MathOperator::Minus | MathOperator::Log | MathOperator::Root => {
+						arguments.len() == 1 || arguments.len() == 2
+					}
+					MathOperator::Divide | MathOperator::Power => arguments.len() == 2,
+					_ => arguments.len() == 1,
+				};
+				if !arity_ok {
+					return Err(self.error(
+						head,
+						format!("Wrong number of arguments for <{}>", head.tag_name().name()),
+					));
+				}
+				Ok(RateExpr::Apply(operator, arguments))
+			}
+			"pi" => Ok(RateExpr::Constant(std::f64::consts::PI)),
+			"exponentiale" => Ok(RateExpr::Constant(std::f64::consts::E)),
+			name => Err(self.error(
+				node,
+				format!("Unsupported element in kinetic law: <{}>", name),
+			)),
+		}
+	}
+
+	/// Reads a `<cn>`, including the `e-notation` and `rational` forms
+	fn math_number(&self, node: Node) -> Result<f64, PositionedParseError> {
+		let parts: Vec<String> = node
+			.children()
+			.filter(|n| n.is_text())
+			.map(|n| n.text().unwrap_or_default().trim().to_string())
+			.filter(|text| !text.is_empty())
+			.collect();
+		let parse = |text: &str| {
+			text.parse::<f64>()
+				.map_err(|_| self.error(node, format!("Invalid number in kinetic law: {}", text)))
+		};
+		match (node.attribute("type"), parts.as_slice()) {
+			(Some("e-notation"), [mantissa, exponent]) => {
+				Ok(parse(mantissa)? * 10f64.powf(parse(exponent)?))
+			}
+			(Some("rational"), [numerator, denominator]) => {
+				Ok(parse(numerator)? / parse(denominator)?)
+			}
+			(_, [value]) => parse(value),
+			_ => Err(self.error(node, "Invalid <cn> in kinetic law".to_string())),
+		}
+	}
+}
+
+/// The first child element with the given local name
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+	node.children()
+		.find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// The items of a `listOf...` element, optionally restricted to one element name
+fn list_items<'a, 'input>(
+	node: Node<'a, 'input>,
+	list: &str,
+	item: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+	child(node, list)
+		.into_iter()
+		.flat_map(|list| list.children())
+		.filter(move |n| n.is_element() && (item.is_empty() || n.tag_name().name() == item))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, path::PathBuf};
+
+	use nalgebra::DVector;
+
+	use super::SbmlParser;
+	use crate::{
+		model::vas_model::{AbstractVas, VasStateVector, VasValue},
+		parser::parser::{ModelOptions, Parser},
+	};
+
+	/// The bundled models that have `.crn`, `.sm` and `.xml` versions
+	const BUNDLED_MODELS: &[&str] = &[
+		"EnzymaticFutileCycle",
+		"ModifiedYeastPolarization",
+		"ReversibleIsomerization",
+		"SimplifiedMotilityRegulation",
+		"SingleSpeciesProductionDegradation",
+	];
+
+	fn bundled_model(name: &str, extension: &str) -> AbstractVas {
+		let filename = format!(
+			"{}/models/{}/{}.{}",
+			env!("CARGO_MANIFEST_DIR"),
+			name,
+			name,
+			extension
+		);
+		AbstractVas::from_file(&filename, &ModelOptions::default())
+			.unwrap_or_else(|e| panic!("{}: {}", filename, e))
+	}
+
+	/// The propensity of each transition in a state, keyed and sorted by update vector
+	fn propensities(model: &AbstractVas, state: &VasStateVector) -> Vec<(Vec<VasValue>, f64)> {
+		let mut propensities: Vec<(Vec<VasValue>, f64)> = model
+			.transitions
+			.iter()
+			.map(|t| {
+				let rate = if t.enabled_vector(state) {
+					t.get_sck_rate(state)
+				} else {
+					0.0
+				};
+				(t.update_vector.iter().copied().collect(), rate)
+			})
+			.collect();
+		// Transitions with the same update are ordered by propensity
+		propensities.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+		propensities
+	}
+
+	/// Checks that two models have the same initial counts, stoichiometry and
+	/// propensities in the given states, with species in the same order
+	fn assert_same_model(expected: &AbstractVas, actual: &AbstractVas, states: &[VasStateVector]) {
+		assert_eq!(
+			expected.initial_states[0].vector,
+			actual.initial_states[0].vector
+		);
+		for state in states {
+			let expected = propensities(expected, state);
+			let actual = propensities(actual, state);
+			assert_eq!(expected.len(), actual.len());
+			for ((expected_update, expected_rate), (actual_update, actual_rate)) in
+				expected.iter().zip(actual.iter())
+			{
+				assert_eq!(expected_update, actual_update);
+				assert!(
+					(expected_rate - actual_rate).abs() <= 1e-9 * expected_rate.abs().max(1.0),
+					"Propensity {} != {} for update {:?} in state {}",
+					expected_rate,
+					actual_rate,
+					expected_update,
+					state
+				);
+			}
+		}
+	}
+
+	/// The initial state, and states with two more of every species, to
+	/// exercise the factors of species consumed more than once
+	fn test_states(model: &AbstractVas) -> Vec<VasStateVector> {
+		let initial = model.initial_states[0].vector.clone();
+		let mut states = vec![initial.clone()];
+		for i in 0..initial.len() {
+			let mut state = initial.clone();
+			state[i] += 2;
+			states.push(state);
+		}
+		states
+	}
+
+	#[test]
+	fn sbml_models_match_crn_models() {
+		for name in BUNDLED_MODELS {
+			let crn = bundled_model(name, "crn");
+			let sbml = bundled_model(name, "xml");
+			assert_eq!(crn.variable_names, sbml.variable_names);
+			assert_same_model(&crn, &sbml, &test_states(&crn));
+		}
+	}
+
+	#[test]
+	fn sbml_models_match_prism_models() {
+		for name in BUNDLED_MODELS {
+			let prism = bundled_model(name, "sm");
+			let sbml = bundled_model(name, "xml");
+			// ModifiedYeastPolarization.sm numbers its species S0 to S6, in the
+			// order of the species of the SBML model
+			if *name != "ModifiedYeastPolarization" {
+				assert_eq!(prism.variable_names, sbml.variable_names);
+			}
+			// ReversibleIsomerization.sm uses the net rate `kf*S0 - kr*S1`, which
+			// only matches the forward mass-action rate while there is no `S1`
+			let states = match *name {
+				"ReversibleIsomerization" => vec![prism.initial_states[0].vector.clone()],
+				_ => test_states(&prism),
+			};
+			assert_same_model(&prism, &sbml, &states);
+		}
+	}
+
+	#[test]
+	fn repeated_species_is_mass_action() {
+		let directory = std::env::temp_dir().join(format!("sbml_dimer_{}", std::process::id()));
+		fs::create_dir_all(&directory).unwrap();
+		let model_file: PathBuf = directory.join("dimer.xml");
+		fs::write(
+			&model_file,
+			r#"<?xml version="1.0" encoding="UTF-8"?>
+<sbml xmlns="http://www.sbml.org/sbml/level3/version1/core" level="3" version="1">
+  <model id="dimer">
+    <listOfCompartments>
+      <compartment id="cell" size="1" constant="true"/>
+    </listOfCompartments>
+    <listOfSpecies>
+      <species id="S" compartment="cell" initialAmount="10" hasOnlySubstanceUnits="true" boundaryCondition="false" constant="false"/>
+      <species id="D" compartment="cell" initialAmount="0" hasOnlySubstanceUnits="true" boundaryCondition="false" constant="false"/>
+    </listOfSpecies>
+    <listOfReactions>
+      <reaction id="R0" reversible="false">
+        <listOfReactants>
+          <speciesReference species="S" stoichiometry="2" constant="true"/>
+        </listOfReactants>
+        <listOfProducts>
+          <speciesReference species="D" stoichiometry="1" constant="true"/>
+        </listOfProducts>
+        <kineticLaw>
+          <math xmlns="http://www.w3.org/1998/Math/MathML">
+            <apply><times/><ci> k </ci><ci> S </ci><ci> S </ci></apply>
+          </math>
+          <listOfLocalParameters>
+            <localParameter id="k" value="0.5"/>
+          </listOfLocalParameters>
+        </kineticLaw>
+      </reaction>
+    </listOfReactions>
+  </model>
+</sbml>
+"#,
+		)
+		.unwrap();
+		fs::write(directory.join("dimer.prop"), "P=? [F<=10 (D >= 5)]\n").unwrap();
+		let model =
+			SbmlParser::parse(model_file.to_str().unwrap(), &ModelOptions::default()).unwrap();
+		fs::remove_dir_all(&directory).unwrap();
+
+		let transition = &model.transitions[0];
+		assert!(transition.custom_rate_fn.is_none());
+		assert_eq!(transition.rate_const, 0.5);
+		assert_eq!(transition.consume_vector, DVector::from_vec(vec![2, 0]));
+		assert_eq!(transition.update_vector, DVector::from_vec(vec![-2, 1]));
+		// `k*S*(S-1)` under the deterministic convention
+		let state = DVector::from_vec(vec![10, 0]);
+		assert_eq!(transition.get_sck_rate(&state), 0.5 * 10.0 * 9.0);
+	}
+}
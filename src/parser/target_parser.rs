@@ -0,0 +1,386 @@
+use std::{collections::HashMap, fmt};
+
+use crate::model::{
+	vas_model::{AllowedRelation, VasProperty, VasValue},
+	vas_target::VasTarget,
+};
+
+/// A token in a target expression
+#[derive(Clone, Debug, PartialEq)]
+enum TargetToken {
+	Identifier(String),
+	Integer(VasValue),
+	Relation(AllowedRelation),
+	And,
+	Or,
+	Not,
+	Plus,
+	Minus,
+	Star,
+	LeftParen,
+	RightParen,
+}
+
+impl fmt::Display for TargetToken {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TargetToken::Identifier(name) => write!(f, "{}", name),
+			TargetToken::Integer(value) => write!(f, "{}", value),
+			TargetToken::Relation(relation) => write!(f, "{}", relation),
+			TargetToken::And => write!(f, "&"),
+			TargetToken::Or => write!(f, "|"),
+			TargetToken::Not => write!(f, "!"),
+			TargetToken::Plus => write!(f, "+"),
+			TargetToken::Minus => write!(f, "-"),
+			TargetToken::Star => write!(f, "*"),
+			TargetToken::LeftParen => write!(f, "("),
+			TargetToken::RightParen => write!(f, ")"),
+		}
+	}
+}
+
+/// Splits a target expression into tokens. Whitespace between tokens is optional.
+fn tokenize(expression: &str) -> Result<Vec<TargetToken>, String> {
+	let chars: Vec<char> = expression.chars().collect();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		let next = chars.get(i + 1).copied();
+		match c {
+			_ if c.is_whitespace() => i += 1,
+			'(' => {
+				tokens.push(TargetToken::LeftParen);
+				i += 1;
+			}
+			')' => {
+				tokens.push(TargetToken::RightParen);
+				i += 1;
+			}
+			'&' => {
+				tokens.push(TargetToken::And);
+				i += if next == Some('&') { 2 } else { 1 };
+			}
+			'|' => {
+				tokens.push(TargetToken::Or);
+				i += if next == Some('|') { 2 } else { 1 };
+			}
+			'!' if next == Some('=') => {
+				tokens.push(TargetToken::Relation(AllowedRelation::NotEqual));
+				i += 2;
+			}
+			'!' => {
+				tokens.push(TargetToken::Not);
+				i += 1;
+			}
+			'+' => {
+				tokens.push(TargetToken::Plus);
+				i += 1;
+			}
+			'-' => {
+				tokens.push(TargetToken::Minus);
+				i += 1;
+			}
+			'*' => {
+				tokens.push(TargetToken::Star);
+				i += 1;
+			}
+			'<' | '>' | '=' => {
+				let operator: String = if next == Some('=') {
+					chars[i..i + 2].iter().collect()
+				} else {
+					c.to_string()
+				};
+				i += operator.len();
+				tokens.push(TargetToken::Relation(operator.parse::<AllowedRelation>()?));
+			}
+			_ if c.is_ascii_digit() => {
+				let start = i;
+				while i < chars.len() && chars[i].is_ascii_digit() {
+					i += 1;
+				}
+				let literal: String = chars[start..i].iter().collect();
+				let value = literal
+					.parse::<VasValue>()
+					.map_err(|_| format!("Expected integer, got `{}`", literal))?;
+				tokens.push(TargetToken::Integer(value));
+			}
+			_ if c.is_alphanumeric() || c == '_' => {
+				let start = i;
+				// Names of included variables have the form `prefix.name`
+				while i < chars.len()
+					&& (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+				{
+					i += 1;
+				}
+				tokens.push(TargetToken::Identifier(chars[start..i].iter().collect()));
+			}
+			_ => return Err(format!("Unexpected character `{}` in target", c)),
+		}
+	}
+	Ok(tokens)
+}
+
+/// Recursive descent parser over target tokens. `&` binds tighter than `|`,
+/// and `!` binds tighter than both.
+struct TargetParser<'a> {
+	tokens: Vec<TargetToken>,
+	position: usize,
+	variable_names: &'a [String],
+}
+
+impl<'a> TargetParser<'a> {
+	fn peek(&self) -> Option<&TargetToken> {
+		self.tokens.get(self.position)
+	}
+
+	fn advance(&mut self) -> Option<TargetToken> {
+		let token = self.tokens.get(self.position).cloned();
+		self.position += 1;
+		token
+	}
+
+	fn parse_or(&mut self) -> Result<VasTarget, String> {
+		let mut target = self.parse_and()?;
+		while self.peek() == Some(&TargetToken::Or) {
+			self.advance();
+			let rhs = self.parse_and()?;
+			target = VasTarget::Or(Box::new(target), Box::new(rhs));
+		}
+		Ok(target)
+	}
+
+	fn parse_and(&mut self) -> Result<VasTarget, String> {
+		let mut target = self.parse_unary()?;
+		while self.peek() == Some(&TargetToken::And) {
+			self.advance();
+			let rhs = self.parse_unary()?;
+			target = VasTarget::And(Box::new(target), Box::new(rhs));
+		}
+		Ok(target)
+	}
+
+	fn parse_unary(&mut self) -> Result<VasTarget, String> {
+		match self.peek() {
+			Some(TargetToken::Not) => {
+				self.advance();
+				Ok(VasTarget::Not(Box::new(self.parse_unary()?)))
+			}
+			Some(TargetToken::LeftParen) => {
+				// The parentheses may group a linear left-hand side, as in
+				// `(S1 + S2) >= 5`, or else a boolean target, as in `(S1 >= 30)`
+				let start = self.position;
+				if let Ok(target) = self.parse_atom() {
+					return Ok(target);
+				}
+				self.position = start;
+				self.advance();
+				let target = self.parse_or()?;
+				match self.advance() {
+					Some(TargetToken::RightParen) => Ok(target),
+					Some(token) => Err(format!("Expected `)`, got `{}`", token)),
+					None => Err("Expected `)` before end of target".to_string()),
+				}
+			}
+			_ => self.parse_atom(),
+		}
+	}
+
+	/// Parses an optionally negated integer literal.
+	fn parse_integer(&mut self) -> Result<VasValue, String> {
+		let sign = if self.peek() == Some(&TargetToken::Minus) {
+			self.advance();
+			-1
+		} else {
+			1
+		};
+		match self.advance() {
+			Some(TargetToken::Integer(value)) => Ok(sign * value),
+			Some(token) => Err(format!("Expected integer, got `{}`", token)),
+			None => Err("Expected integer before end of target".to_string()),
+		}
+	}
+
+	/// Parses a single term `[INT *] IDENT` of a linear combination.
+	fn parse_term(&mut self, sign: VasValue) -> Result<(usize, VasValue), String> {
+		let coefficient = if let Some(TargetToken::Integer(value)) = self.peek() {
+			let value = *value;
+			self.advance();
+			if self.peek() == Some(&TargetToken::Star) {
+				self.advance();
+			}
+			value
+		} else {
+			1
+		};
+		let variable_name = match self.advance() {
+			Some(TargetToken::Identifier(name)) => name,
+			Some(token) => return Err(format!("Expected variable name, got `{}`", token)),
+			None => return Err("Expected variable name before end of target".to_string()),
+		};
+		let variable_index = self
+			.variable_names
+			.iter()
+			.position(|name| *name == variable_name)
+			.ok_or_else(|| format!("Unspecified variable: `{}`", variable_name))?;
+		Ok((variable_index, sign * coefficient))
+	}
+
+	/// Parses `[-] SUMMAND ((+|-) SUMMAND)*`, where a summand is a term or a
+	/// parenthesized sum, and adds its terms, times `sign`, to `terms`
+	fn parse_sum(
+		&mut self,
+		sign: VasValue,
+		terms: &mut Vec<(usize, VasValue)>,
+	) -> Result<(), String> {
+		let mut summand_sign = if self.peek() == Some(&TargetToken::Minus) {
+			self.advance();
+			-sign
+		} else {
+			sign
+		};
+		loop {
+			if self.peek() == Some(&TargetToken::LeftParen) {
+				self.advance();
+				self.parse_sum(summand_sign, terms)?;
+				match self.advance() {
+					Some(TargetToken::RightParen) => {}
+					Some(token) => return Err(format!("Expected `)`, got `{}`", token)),
+					None => return Err("Expected `)` before end of target".to_string()),
+				}
+			} else {
+				let (variable_index, coefficient) = self.parse_term(summand_sign)?;
+				match terms.iter_mut().find(|(index, _)| *index == variable_index) {
+					Some((_, existing)) => *existing += coefficient,
+					None => terms.push((variable_index, coefficient)),
+				}
+			}
+			summand_sign = match self.peek() {
+				Some(TargetToken::Plus) => sign,
+				Some(TargetToken::Minus) => -sign,
+				_ => return Ok(()),
+			};
+			self.advance();
+		}
+	}
+
+	/// Parses `SUM REL INT`. Repeated variables are merged, and a lone variable
+	/// with coefficient 1 becomes a plain single-species property.
+	fn parse_atom(&mut self) -> Result<VasTarget, String> {
+		let mut terms: Vec<(usize, VasValue)> = Vec::new();
+		self.parse_sum(1, &mut terms)?;
+		terms.retain(|(_, coefficient)| *coefficient != 0);
+		let relation = match self.advance() {
+			Some(TargetToken::Relation(relation)) => relation,
+			Some(token) => return Err(format!("Expected comparison operator, got `{}`", token)),
+			None => return Err("Expected comparison operator before end of target".to_string()),
+		};
+		let target_value = self.parse_integer()?;
+		let Some(&(variable_index, _)) = terms.first() else {
+			return Err("Target has no non-zero terms".to_string());
+		};
+		let linear_terms = if terms.len() == 1 && terms[0].1 == 1 {
+			Vec::new()
+		} else {
+			terms
+		};
+		Ok(VasTarget::Atom(VasProperty {
+			variable_index,
+			relation,
+			target_value,
+			linear_terms,
+		}))
+	}
+}
+
+/// Parses a target expression such as `(S1 >= 30) & (S4 <= 25) | !(GBG = 0)`
+/// or `S1 + 2*S2 >= 40` over the given variable names.
+pub fn parse_target(expression: &str, variable_names: &[String]) -> Result<VasTarget, String> {
+	parse_target_with_constants(expression, variable_names, &HashMap::new())
+}
+
+/// Parses a target expression in which named constants, e.g., model
+/// parameters, may stand in for integers, as in `S1 >= N`.
+pub fn parse_target_with_constants(
+	expression: &str,
+	variable_names: &[String],
+	constants: &HashMap<String, f64>,
+) -> Result<VasTarget, String> {
+	let tokens = tokenize(expression)?
+		.into_iter()
+		.map(|token| match token {
+			TargetToken::Identifier(name) if !variable_names.contains(&name) => {
+				match constants.get(&name) {
+					Some(value) if value.fract() == 0.0 => {
+						Ok(TargetToken::Integer(*value as VasValue))
+					}
+					Some(value) => Err(format!(
+						"Parameter `{}` = {} is not an integer",
+						name, value
+					)),
+					None => Ok(TargetToken::Identifier(name)),
+				}
+			}
+			token => Ok(token),
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+	let mut parser = TargetParser {
+		tokens,
+		position: 0,
+		variable_names,
+	};
+	if parser.tokens.is_empty() {
+		return Err("Target is empty".to_string());
+	}
+	let target = parser.parse_or()?;
+	if let Some(token) = parser.peek() {
+		return Err(format!("Unexpected token `{}` after target", token));
+	}
+	Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+	use nalgebra::DVector;
+
+	use super::parse_target;
+	use crate::model::{
+		vas_model::{AllowedRelation, VasProperty, VasValue},
+		vas_target::VasTarget,
+	};
+
+	fn names() -> Vec<String> {
+		vec!["S1".to_string(), "S2".to_string(), "S3".to_string()]
+	}
+
+	fn atom(expression: &str) -> VasProperty {
+		match parse_target(expression, &names()) {
+			Ok(VasTarget::Atom(property)) => property,
+			target => panic!("Expected an atom for `{}`, got {:?}", expression, target),
+		}
+	}
+
+	#[test]
+	fn parenthesized_linear_left_hand_side() {
+		for expression in ["(S1 + S2) >= 5", "((S1) + S2) >= 5"] {
+			let property = atom(expression);
+			assert_eq!(property.relation, AllowedRelation::GreaterThanOrEqual);
+			assert_eq!(property.target_value, 5);
+			assert_eq!(property.linear_terms, vec![(0, 1), (1, 1)]);
+		}
+		// A minus in front of parentheses negates every term inside them
+		let property = atom("S3 - (S1 - 2*S2) < 0");
+		assert_eq!(property.linear_terms, vec![(2, 1), (0, -1), (1, 2)]);
+	}
+
+	#[test]
+	fn parenthesized_boolean_group() {
+		let target = parse_target("((S1 + S2) >= 5 | (S3 = 0)) & !(S1 > 2)", &names()).unwrap();
+		let state = |values: [VasValue; 3]| DVector::from_vec(values.to_vec());
+		assert!(target.is_satisfied(&state([2, 3, 1])));
+		assert!(target.is_satisfied(&state([0, 0, 0])));
+		assert!(!target.is_satisfied(&state([3, 3, 0])));
+		assert!(!target.is_satisfied(&state([1, 1, 1])));
+		assert!(parse_target("(S1 + S2 >= 5", &names()).is_err());
+	}
+}
@@ -1,33 +1,71 @@
-use std::fmt;
+use std::{
+	fmt, io,
+	path::{Path, PathBuf},
+};
 
+use evalexpr::Context;
 use nalgebra::DVector;
 
 use crate::{
-	logging::messages::*,
 	model::{
-		model::{AbstractModel, ProbabilityOrRate},
-		vas_model::{AbstractVas, VasProperty, VasState, VasTransition, VasValue},
+		model::{AbstractModel, ModelType, ProbabilityOrRate},
+		vas_model::{AbstractVas, RateConvention, VasState, VasTransition, VasValue},
+		vas_target::VasTarget,
+	},
+	parser::{
+		csl_parser::override_target,
+		parameters::{
+			compile_rate_law, define_parameter, evaluate_float, evaluate_integer, parameter_values,
+			refers_to_variables, ParameterContext, ParameterOverrides,
+		},
+		parser::{self, ModelOptions},
+		target_parser::parse_target_with_constants,
 	},
 	util::util::read_lines,
+	warning,
 };
 
-const VARIABLE_TERMS: &[&str] = &["species", "variable", "var"];
-const INITIAL_TERMS: &[&str] = &["initial", "init"];
-const TRANSITION_TERMS: &[&str] = &["reaction", "transition"];
-const DECREASE_TERMS: &[&str] = &["consume", "decrease", "decrement"];
-const INCREASE_TERMS: &[&str] = &["produce", "increase", "increment"];
-const RATE_TERMS: &[&str] = &["rate", "const"];
-const TARGET_TERMS: &[&str] = &["target", "goal", "prop", "check"];
+pub(crate) const PARAMETER_TERMS: &[&str] = &["param", "parameter"];
+pub(crate) const VARIABLE_TERMS: &[&str] = &["species", "variable", "var"];
+pub(crate) const INITIAL_TERMS: &[&str] = &["initial", "init"];
+pub(crate) const TRANSITION_TERMS: &[&str] = &["reaction", "transition"];
+pub(crate) const DECREASE_TERMS: &[&str] = &["consume", "decrease", "decrement"];
+pub(crate) const INCREASE_TERMS: &[&str] = &["produce", "increase", "increment"];
+pub(crate) const RATE_TERMS: &[&str] = &["rate", "const"];
+pub(crate) const TARGET_TERMS: &[&str] = &["target", "goal", "prop", "check"];
+pub(crate) const KINETICS_TERMS: &[&str] = &["kinetics"];
+pub(crate) const INCLUDE_TERMS: &[&str] = &["include"];
+pub(crate) const SHARE_TERMS: &[&str] = &["share"];
+pub(crate) const MODEL_TYPE_TERMS: &[&str] = &["ctmc", "dtmc"];
+/// Every keyword that may start a line
+pub(crate) const ALL_TERMS: &[&[&str]] = &[
+	PARAMETER_TERMS,
+	VARIABLE_TERMS,
+	INITIAL_TERMS,
+	TRANSITION_TERMS,
+	DECREASE_TERMS,
+	INCREASE_TERMS,
+	RATE_TERMS,
+	TARGET_TERMS,
+	KINETICS_TERMS,
+	INCLUDE_TERMS,
+	SHARE_TERMS,
+	MODEL_TYPE_TERMS,
+];
 
 #[derive(Clone, Debug)]
 enum ModelParseErrorType {
-	InvalidInitialVariableCount(String), // Variable name
+	InvalidInitialVariableCount(String), // The initial count
+	NegativeInitialCount(String),        // Variable name
 	InitUnspecified(String),             // The initial value for a variable is unspecified
 	UnexpextedTokenError(String),        // A token is found we were not expecting
 	ExpectedInteger(String),             // We expected an integer, we got this
 	ExpectedFloat(String),               // We expected a float, we got this
 	UnspecifiedTransitionError(String),  // The name of the transition
 	UnspecifiedVariableError(String),    // The name of the variable
+	UnspecifiedRateError(String),        // The name of the transition without a rate
+	DuplicateVariableError(String),      // The name of the variable
+	DuplicateTransitionError(String),    // The name of the transition
 	GeneralParseError(String),           // Description
 }
 impl ToString for ModelParseErrorType {
@@ -37,6 +75,9 @@ impl ToString for ModelParseErrorType {
 			Self::InvalidInitialVariableCount(count) => {
 				format!("Invalid initial count: `{}`.", count)
 			}
+			Self::NegativeInitialCount(var_name) => {
+				format!("The initial value for `{}` is negative.", var_name)
+			}
 			Self::InitUnspecified(var_name) => {
 				format!("The initial value for `{}` is unspecified.", var_name)
 			}
@@ -47,429 +88,1170 @@ impl ToString for ModelParseErrorType {
 				format!("Unspecified transition: `{}`.", transition)
 			}
 			Self::UnspecifiedVariableError(var) => format!("Unspecified variable: `{}`", var),
+			Self::UnspecifiedRateError(transition) => format!(
+				"Transition `{}` has no rate (add a `const` or `rate` line).",
+				transition
+			),
+			Self::DuplicateVariableError(var) => {
+				format!("Variable `{}` is declared more than once.", var)
+			}
+			Self::DuplicateTransitionError(transition) => {
+				format!("Transition `{}` is declared more than once.", transition)
+			}
 			Self::GeneralParseError(desc) => format!("General Parse Error: {}", desc),
 		}
 	}
 }
 #[derive(Clone, Debug)]
 pub struct ModelParseError {
+	// The one-based line number (0 if the error is not tied to a line)
 	line: usize,
+	// The text of the line, to show the error in context
+	line_content: String,
+	// The zero-based columns `(start, end)` of the offending text
+	span: Option<(usize, usize)>,
 	etype: ModelParseErrorType,
 }
 impl fmt::Display for ModelParseError {
-	/// Formats the error for display
+	/// Formats the error message. The line and its context are shown by
+	/// the `Display` implementation of `dyn parser::ModelParseError`.
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let line_num = self.line;
-		let err_str = self.etype.to_string();
-		write!(
-			f,
-			"[Parse Error] Error in model parsing. Unable to parse model!\nLine {}: {}\n{}",
-			line_num, err_str, "^"
-		)
+		write!(f, "{}", self.etype.to_string())
 	}
 }
-impl ModelParseError {
-	fn line(&self) -> (usize, String) {
-		(self.line, self.etype.to_string())
+impl parser::ModelParseError for ModelParseError {
+	fn line(&self) -> (u64, String) {
+		(self.line as u64, self.line_content.clone())
 	}
 
-	fn column(&self) -> Option<u32> {
-		None
+	fn column(&self) -> Option<u64> {
+		self.span.map(|(start, _)| start as u64)
 	}
 
-	fn invalid_init(line: usize, count: &dyn ToString) -> Self {
+	fn column_span(&self) -> Option<(u64, u64)> {
+		self.span.map(|(start, end)| (start as u64, end as u64))
+	}
+}
+impl ModelParseError {
+	/// Points the error at `part`, which should be a slice of the line `content`.
+	/// If it is not (e.g., a value given on the command line), the whole line is shown.
+	fn at(mut self, content: &str, part: &str) -> Self {
+		self.line_content = content.to_string();
+		self.span = span_of(content, part);
+		self
+	}
+
+	fn new(line: usize, etype: ModelParseErrorType) -> Self {
 		Self {
 			line,
-			etype: ModelParseErrorType::InvalidInitialVariableCount(count.to_string()),
+			line_content: String::new(),
+			span: None,
+			etype,
 		}
 	}
 
-	fn init_unspecified(line: usize, name: &dyn ToString) -> Self {
-		Self {
+	fn invalid_init(line: usize, count: &dyn ToString) -> Self {
+		Self::new(
 			line,
-			etype: ModelParseErrorType::InitUnspecified(name.to_string()),
-		}
+			ModelParseErrorType::InvalidInitialVariableCount(count.to_string()),
+		)
+	}
+
+	fn negative_init(line: usize, name: &dyn ToString) -> Self {
+		Self::new(
+			line,
+			ModelParseErrorType::NegativeInitialCount(name.to_string()),
+		)
+	}
+
+	fn init_unspecified(line: usize, name: &dyn ToString) -> Self {
+		Self::new(line, ModelParseErrorType::InitUnspecified(name.to_string()))
 	}
 
 	fn unexpected_token(line: usize, token: &dyn ToString) -> Self {
-		Self {
+		Self::new(
 			line,
-			etype: ModelParseErrorType::UnexpextedTokenError(token.to_string()),
-		}
+			ModelParseErrorType::UnexpextedTokenError(token.to_string()),
+		)
 	}
 
 	fn expected_integer(line: usize, value: &dyn ToString) -> Self {
-		Self {
+		Self::new(
 			line,
-			etype: ModelParseErrorType::ExpectedInteger(value.to_string()),
-		}
+			ModelParseErrorType::ExpectedInteger(value.to_string()),
+		)
 	}
 
 	fn expected_float(line: usize, value: &dyn ToString) -> Self {
-		Self {
-			line,
-			etype: ModelParseErrorType::ExpectedFloat(value.to_string()),
-		}
+		Self::new(line, ModelParseErrorType::ExpectedFloat(value.to_string()))
 	}
 
 	fn unspecified_transition(line: usize, tname: &dyn ToString) -> Self {
-		Self {
+		Self::new(
 			line,
-			etype: ModelParseErrorType::UnspecifiedTransitionError(tname.to_string()),
-		}
+			ModelParseErrorType::UnspecifiedTransitionError(tname.to_string()),
+		)
 	}
 
 	fn unspecified_variable(line: usize, vname: &dyn ToString) -> Self {
-		Self {
+		Self::new(
 			line,
-			etype: ModelParseErrorType::UnspecifiedVariableError(vname.to_string()),
-		}
+			ModelParseErrorType::UnspecifiedVariableError(vname.to_string()),
+		)
+	}
+
+	fn unspecified_rate(line: usize, tname: &dyn ToString) -> Self {
+		Self::new(
+			line,
+			ModelParseErrorType::UnspecifiedRateError(tname.to_string()),
+		)
+	}
+
+	fn duplicate_variable(line: usize, vname: &dyn ToString) -> Self {
+		Self::new(
+			line,
+			ModelParseErrorType::DuplicateVariableError(vname.to_string()),
+		)
+	}
+
+	fn duplicate_transition(line: usize, tname: &dyn ToString) -> Self {
+		Self::new(
+			line,
+			ModelParseErrorType::DuplicateTransitionError(tname.to_string()),
+		)
 	}
 
 	fn general(line: usize, desc: &dyn ToString) -> Self {
-		Self {
+		Self::new(
 			line,
-			etype: ModelParseErrorType::GeneralParseError(desc.to_string()),
-		}
+			ModelParseErrorType::GeneralParseError(desc.to_string()),
+		)
+	}
+}
+
+/// The zero-based columns of `part` within `line`, if `part` is a slice of `line`
+fn span_of(line: &str, part: &str) -> Option<(usize, usize)> {
+	let start = (part.as_ptr() as usize).checked_sub(line.as_ptr() as usize)?;
+	if start + part.len() > line.len() {
+		return None;
 	}
+	let column = line[..start].chars().count();
+	Some((column, column + part.chars().count()))
+}
+
+/// The rest of the line from the word at index `from`, e.g., an expression.
+/// The words must be slices of the line.
+fn words_from<'a>(line: &'a str, words: &[&'a str], from: usize) -> &'a str {
+	let start = words[from].as_ptr() as usize - line.as_ptr() as usize;
+	line[start..].trim_end()
+}
+
+/// The last occurrence of `part` in the line, as a slice of the line for `ModelParseError::at`
+fn find_in<'a>(line: &'a str, part: &str) -> &'a str {
+	line.rfind(part)
+		.map_or(line.trim(), |start| &line[start..start + part.len()])
 }
+
 fn get_variable_id(v: &[String], name: &str) -> Option<usize> {
 	v.iter().position(|r| r == name)
 }
 
+/// Builds the parameters declared as `param NAME = EXPR`. Each expression may
+/// use the parameters declared before it, and values given on the command
+/// line replace the declared ones. Returns the parameters and their names.
+fn build_parameters(
+	raw_data: Vec<(usize, String)>,
+	overrides: &ParameterOverrides,
+	errors: &mut Vec<ModelParseError>,
+) -> (ParameterContext, Vec<String>) {
+	let mut parameters = ParameterContext::new();
+	let mut parameter_names = Vec::<String>::new();
+	for (line_num, line) in raw_data.iter() {
+		// Everything after the keyword is `NAME = EXPR`
+		let declaration = line
+			.trim_start()
+			.split_once(char::is_whitespace)
+			.map(|(_, rest)| rest)
+			.unwrap_or("");
+		let Some((name, expression)) = declaration
+			.split_once('=')
+			.map(|(name, expression)| (name.trim(), expression.trim()))
+			.filter(|(name, expression)| {
+				!expression.is_empty()
+					&& !name.is_empty()
+					&& name.chars().all(|c| c.is_alphanumeric() || c == '_')
+			})
+		else {
+			errors.push(
+				ModelParseError::unexpected_token(*line_num, &line.trim()).at(line, line.trim()),
+			);
+			continue;
+		};
+		if parameter_names.iter().any(|existing| existing == name) {
+			errors.push(
+				ModelParseError::general(
+					*line_num,
+					&format!("Parameter `{}` is declared more than once.", name),
+				)
+				.at(line, name),
+			);
+			continue;
+		}
+		let expression = overrides.get(name).unwrap_or(expression);
+		if let Err(e) = define_parameter(name, expression, &mut parameters) {
+			errors.push(ModelParseError::general(*line_num, &e).at(line, expression));
+		}
+		parameter_names.push(name.to_string());
+	}
+	(parameters, parameter_names)
+}
+
 /// Builds two variable objects (names and initial values)
 fn build_variables(
 	raw_data: Vec<(usize, String)>,
-) -> Result<(Box<[String]>, Box<[VasValue]>), ModelParseError> {
+	parameters: &ParameterContext,
+	errors: &mut Vec<ModelParseError>,
+) -> (Box<[String]>, Box<[VasValue]>) {
 	let mut variable_names = Vec::<String>::new();
 	let mut initial_state = Vec::<VasValue>::new();
-	for declaration in raw_data.iter() {
-		let words: &[&str] = &declaration.1.split_whitespace().collect::<Vec<&str>>()[..];
-		let variable_name;
-		let variable_init;
-		match words.len() {
-			2 => {
-				// Handle case with just variable names (i.e., initial value is assumed to be 0)
-				variable_name = words[1].to_string();
-				variable_init = 0;
-			}
-			4 => {
-				// Handle case with initialization (i.e., initial value follows word "init")
-				if INITIAL_TERMS.contains(&words[2]) {
-					variable_name = words[1].to_string();
-					if let Ok(count) = words[3].parse::<VasValue>() {
-						variable_init = count;
-					} else {
-						return Err(ModelParseError::invalid_init(
-							declaration.0.try_into().unwrap(),
-							&words[3],
-						));
+	for (line_num, line) in raw_data.iter() {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let (variable_name, variable_init) = match words.len() {
+			// Handle case with just variable names (i.e., initial value is assumed to be 0)
+			2 => (words[1], 0),
+			// Handle case with initialization (i.e., initial value follows word "init")
+			n if n >= 4 && INITIAL_TERMS.contains(&words[2]) => {
+				// The initial value may be an expression over parameters
+				let expression = words_from(line, &words, 3);
+				let variable_init = match evaluate_integer(expression, parameters) {
+					Ok(count) if count < 0 => {
+						errors.push(
+							ModelParseError::negative_init(*line_num, &words[1])
+								.at(line, expression),
+						);
+						0
 					}
-				} else {
-					return Err(ModelParseError::init_unspecified(
-						declaration.0.try_into().unwrap(),
-						&words[1],
-					));
-				}
+					Ok(count) => count,
+					Err(_) => {
+						errors.push(
+							ModelParseError::invalid_init(*line_num, &expression)
+								.at(line, expression),
+						);
+						0
+					}
+				};
+				(words[1], variable_init)
+			}
+			n if n >= 3 => {
+				errors.push(
+					ModelParseError::init_unspecified(*line_num, &words[1])
+						.at(line, words_from(line, &words, 2)),
+				);
+				(words[1], 0)
 			}
 			_ => {
-				return Err(ModelParseError::unexpected_token(
-					declaration.0.try_into().unwrap(),
-					&declaration.1,
-				));
+				errors.push(
+					ModelParseError::unexpected_token(*line_num, &line.trim())
+						.at(line, line.trim()),
+				);
+				continue;
 			}
+		};
+		if variable_names.iter().any(|name| name == variable_name) {
+			errors.push(
+				ModelParseError::duplicate_variable(*line_num, &variable_name)
+					.at(line, variable_name),
+			);
+			continue;
+		}
+		if parameters.get_value(variable_name).is_some() {
+			errors.push(
+				ModelParseError::general(
+					*line_num,
+					&format!(
+						"Variable `{}` has the same name as a parameter.",
+						variable_name
+					),
+				)
+				.at(line, variable_name),
+			);
 		}
-		variable_names.push(variable_name);
+		variable_names.push(variable_name.to_string());
 		initial_state.push(variable_init);
 	}
-	Ok((
+	(
 		variable_names.into_boxed_slice(),
 		initial_state.into_boxed_slice(),
-	))
+	)
+}
+
+/// Builds the initial states from the `initial [WEIGHT]` blocks. Each line of a block
+/// is `VARIABLE VALUE`, and variables left out keep the value from their declaration.
+/// Without any blocks, the declared values are the only initial state.
+/// Returns the states with their (unnormalized) weights.
+fn build_initial_states(
+	raw_data: Vec<Vec<(usize, String)>>,
+	variable_names: &[String],
+	default_state: &[VasValue],
+	parameters: &ParameterContext,
+	errors: &mut Vec<ModelParseError>,
+) -> (Vec<VasState>, Vec<ProbabilityOrRate>) {
+	if raw_data.is_empty() {
+		return (
+			vec![VasState::new(DVector::from_vec(default_state.to_vec()))],
+			vec![1.0],
+		);
+	}
+	let mut initial_states = Vec::new();
+	let mut initial_weights = Vec::new();
+	for block in raw_data.iter() {
+		let (header_num, header) = &block[0];
+		let words: Vec<&str> = header.split_whitespace().collect();
+		let weight = if words.len() >= 2 {
+			let expression = words_from(header, &words, 1);
+			match evaluate_float(expression, parameters) {
+				Ok(weight) if weight > 0.0 && weight.is_finite() => weight,
+				Ok(_) => {
+					errors.push(
+						ModelParseError::general(
+							*header_num,
+							&"The weight of an initial state must be positive.",
+						)
+						.at(header, expression),
+					);
+					1.0
+				}
+				Err(_) => {
+					errors.push(
+						ModelParseError::expected_float(*header_num, &expression)
+							.at(header, expression),
+					);
+					1.0
+				}
+			}
+		} else {
+			1.0
+		};
+		let mut state = default_state.to_vec();
+		let mut assigned = Vec::<usize>::new();
+		for (line_num, line) in block[1..].iter() {
+			let words: Vec<&str> = line.split_whitespace().collect();
+			if words.len() < 2 {
+				errors.push(
+					ModelParseError::unexpected_token(*line_num, &line.trim())
+						.at(line, line.trim()),
+				);
+				continue;
+			}
+			let Some(variable_id) = get_variable_id(variable_names, words[0]) else {
+				errors.push(
+					ModelParseError::unspecified_variable(*line_num, &words[0]).at(line, words[0]),
+				);
+				continue;
+			};
+			if assigned.contains(&variable_id) {
+				errors.push(
+					ModelParseError::general(
+						*line_num,
+						&format!(
+							"`{}` is set more than once in this initial state.",
+							words[0]
+						),
+					)
+					.at(line, words[0]),
+				);
+				continue;
+			}
+			assigned.push(variable_id);
+			let expression = words_from(line, &words, 1);
+			match evaluate_integer(expression, parameters) {
+				Ok(count) if count < 0 => errors.push(
+					ModelParseError::negative_init(*line_num, &words[0]).at(line, expression),
+				),
+				Ok(count) => state[variable_id] = count,
+				Err(_) => errors.push(
+					ModelParseError::invalid_init(*line_num, &expression).at(line, expression),
+				),
+			}
+		}
+		initial_states.push(VasState::new(DVector::from_vec(state)));
+		initial_weights.push(weight);
+	}
+	(initial_states, initial_weights)
 }
 
-/// Builds the transition objects
+/// Reads a `consume` or `produce` line, returning the variable index and count
+fn build_update(
+	line_num: usize,
+	line: &str,
+	variable_names: &[String],
+	parameters: &ParameterContext,
+) -> Result<(usize, VasValue), ModelParseError> {
+	let words: Vec<&str> = line.split_whitespace().collect();
+	let count = match words.len() {
+		// If the count is omitted it is 1
+		2 => 1,
+		_ if words.len() >= 3 => {
+			// The count may be an expression over parameters
+			let expression = words_from(line, &words, 2);
+			match evaluate_integer(expression, parameters) {
+				Ok(count) if count < 0 => {
+					return Err(ModelParseError::general(
+						line_num,
+						&format!("Count `{}` must not be negative.", expression),
+					)
+					.at(line, expression))
+				}
+				Ok(count) => count,
+				Err(_) => {
+					return Err(ModelParseError::expected_integer(line_num, &expression)
+						.at(line, expression))
+				}
+			}
+		}
+		_ => {
+			return Err(
+				ModelParseError::unexpected_token(line_num, &line.trim()).at(line, line.trim())
+			)
+		}
+	};
+	match get_variable_id(variable_names, words[1]) {
+		Some(index) => Ok((index, count)),
+		None => Err(ModelParseError::unspecified_variable(line_num, &words[1]).at(line, words[1])),
+	}
+}
+
+/// Builds the transition objects. The first line of each declaration is the
+/// `reaction` line.
 fn build_transitions(
 	raw_data: Vec<Vec<(usize, std::string::String)>>,
-	variable_names: &Box<[String]>,
-) -> Result<Vec<<AbstractVas as AbstractModel>::TransitionType>, ModelParseError> {
+	variable_names: &[String],
+	parameters: &ParameterContext,
+	errors: &mut Vec<ModelParseError>,
+) -> Vec<<AbstractVas as AbstractModel>::TransitionType> {
 	let mut transitions = Vec::<<AbstractVas as AbstractModel>::TransitionType>::new();
 	let num_variables = variable_names.len();
-	let mut transition_id: usize = 0;
+	let mut transition_names = Vec::<String>::new();
 
 	for declaration in raw_data {
-		let mut transition_name = String::new();
-		let mut increment = [0; 64][..num_variables].to_vec().into_boxed_slice();
-		let mut decrement = [0; 64][..num_variables].to_vec().into_boxed_slice();
-		let mut rate_const: ProbabilityOrRate = 0.0;
-
-		for line in declaration.iter() {
-			let words: &[&str] = &line.1.split_whitespace().collect::<Vec<&str>>()[..];
-			let first_word = words.get(0).unwrap_or(&"");
-			if TRANSITION_TERMS.contains(first_word) {
-				if words.len() == 2 {
-					transition_name = words[1].to_string();
-				} else {
-					return Err(ModelParseError::unexpected_token(
-						line.0.try_into().unwrap(),
-						&line.1,
-					));
-				}
-			} else if DECREASE_TERMS.contains(first_word) {
-				let variable_name;
-				let decrease_count: VasValue;
-				match words.len() {
-					2 => {
-						variable_name = words[1].to_string();
-						decrease_count = 1;
-					}
-					3 => {
-						variable_name = words[1].to_string();
-						if let Ok(count) = words[2].parse::<VasValue>() {
-							if count < 0 {
-								// TODO: This error, and presumably others, just cause a stack overflow. Need to fix this.
-								return Err(ModelParseError::unexpected_token(
-									line.0.try_into().unwrap(),
-									&line.1,
-								));
-							} else {
-								decrease_count = count;
-							};
-						} else {
-							return Err(ModelParseError::unexpected_token(
-								line.0.try_into().unwrap(),
-								&line.1,
-							));
-						}
-					}
-					_ => {
-						return Err(ModelParseError::unexpected_token(
-							line.0.try_into().unwrap(),
-							&line.1,
-						));
+		let (header_num, header) = &declaration[0];
+		let header_words: Vec<&str> = header.split_whitespace().collect();
+		let transition_name = header_words.get(1).copied().unwrap_or_default();
+		if header_words.len() != 2 {
+			errors.push(
+				ModelParseError::unexpected_token(*header_num, &header.trim())
+					.at(header, header.trim()),
+			);
+		} else if transition_names.iter().any(|name| name == transition_name) {
+			errors.push(
+				ModelParseError::duplicate_transition(*header_num, &transition_name)
+					.at(header, transition_name),
+			);
+		}
+		transition_names.push(transition_name.to_string());
+
+		let mut increment = vec![0; num_variables].into_boxed_slice();
+		let mut decrement = vec![0; num_variables].into_boxed_slice();
+		let mut rate_const: Option<ProbabilityOrRate> = None;
+		let mut custom_rate_fn = None;
+
+		for (line_num, line) in declaration.iter().skip(1) {
+			let words: Vec<&str> = line.split_whitespace().collect();
+			let first_word = words.first().unwrap_or(&"");
+			if DECREASE_TERMS.contains(first_word) || INCREASE_TERMS.contains(first_word) {
+				let (index, count) = match build_update(*line_num, line, variable_names, parameters)
+				{
+					Ok(update) => update,
+					Err(e) => {
+						errors.push(e);
+						continue;
 					}
-				}
+				};
 				// update the transition
-				if let Some(index) = get_variable_id(&variable_names, &variable_name) {
-					if decrement[index] != 0 {
-						return Err(ModelParseError::general(line.0.try_into().unwrap(), &format!("Model parsing error: variable {} decreases by multiple declared values in the same transition.", variable_name)));
-					}
-					decrement[index] = decrease_count;
+				let (vector, direction) = if DECREASE_TERMS.contains(first_word) {
+					(&mut decrement, "decreases")
 				} else {
-					return Err(ModelParseError::unspecified_variable(
-						line.0.try_into().unwrap(),
-						&variable_name,
-					));
+					(&mut increment, "increases")
+				};
+				if vector[index] != 0 {
+					errors.push(ModelParseError::general(*line_num, &format!("Model parsing error: variable {} {} by multiple declared values in the same transition.", variable_names[index], direction)).at(line, words[1]));
 				}
-			} else if INCREASE_TERMS.contains(first_word) {
-				let variable_name;
-				let increase_count;
-				match words.len() {
-					2 => {
-						variable_name = words[1].to_string();
-						increase_count = 1;
-					}
-					3 => {
-						variable_name = words[1].to_string();
-						if let Ok(count) = words[2].parse::<VasValue>() {
-							increase_count = if count < 0 {
-								return Err(ModelParseError::unexpected_token(
-									line.0.try_into().unwrap(),
-									&line.1,
-								));
-							} else {
-								count
-							};
-						} else {
-							return Err(ModelParseError::unexpected_token(
-								line.0.try_into().unwrap(),
-								&line.1,
-							));
+				vector[index] = count;
+			} else if RATE_TERMS.contains(first_word) && words.len() >= 2 {
+				// The rate may be an expression over parameters, e.g., `const k1*2`,
+				// or a rate law over the species, e.g., `rate Vmax*S/(K+S)`
+				let expression = words_from(line, &words, 1);
+				if refers_to_variables(expression, variable_names) {
+					match compile_rate_law(expression, variable_names, parameters) {
+						Ok(rate_fn) => {
+							rate_const = Some(1.0);
+							custom_rate_fn = Some(rate_fn);
 						}
+						Err(e) => errors
+							.push(ModelParseError::general(*line_num, &e).at(line, expression)),
 					}
-					_ => {
-						return Err(ModelParseError::unexpected_token(
-							line.0.try_into().unwrap(),
-							&line.1,
-						));
-					}
-				}
-				// update the transition
-				if let Some(index) = get_variable_id(&variable_names.clone(), &variable_name) {
-					if increment[index] != 0 {
-						return Err(ModelParseError::general(line.0.try_into().unwrap(), &format!("Model parsing error: variable {} increases by multiple declared values in the same transition.", variable_name)));
-					}
-					increment[index] = increase_count;
-				} else {
-					return Err(ModelParseError::unspecified_variable(
-						line.0.try_into().unwrap(),
-						&variable_name,
-					));
-				}
-			} else if RATE_TERMS.contains(first_word) {
-				if words.len() == 2 {
-					if let Ok(rate) = words[1].parse::<ProbabilityOrRate>() {
-						rate_const = rate;
-					} else {
-						return Err(ModelParseError::expected_float(
-							line.0.try_into().unwrap(),
-							&words[1],
-						));
-					}
+				} else if let Ok(rate) = evaluate_float(expression, parameters) {
+					rate_const = Some(rate);
+					custom_rate_fn = None;
 				} else {
-					return Err(ModelParseError::unexpected_token(
-						line.0.try_into().unwrap(),
-						&line.1,
-					));
+					errors.push(
+						ModelParseError::expected_float(*line_num, &expression)
+							.at(line, expression),
+					);
 				}
 			} else {
-				return Err(ModelParseError::unexpected_token(
-					line.0.try_into().unwrap(),
-					&line.1,
-				));
+				errors.push(
+					ModelParseError::unexpected_token(*line_num, &line.trim())
+						.at(line, line.trim()),
+				);
 			}
 		}
 
-		let transition = VasTransition::new(
-			transition_id,
-			transition_name,
+		// A rate that failed to parse has been reported already
+		let rate_declared = declaration.iter().skip(1).any(|(_, line)| {
+			line.split_whitespace()
+				.next()
+				.is_some_and(|word| RATE_TERMS.contains(&word))
+		});
+		if !rate_declared {
+			errors.push(
+				ModelParseError::unspecified_rate(*header_num, &transition_name)
+					.at(header, transition_name),
+			);
+		}
+
+		let mut transition = VasTransition::new(
+			transitions.len(),
+			transition_name.to_string(),
 			increment,
 			decrement,
-			rate_const,
+			rate_const.unwrap_or(0.0),
 		);
+		if let Some(rate_fn) = custom_rate_fn {
+			transition.set_custom_rate_fn(rate_fn);
+		}
 
 		transitions.push(transition);
-
-		transition_id += 1;
 	}
 
-	// For now, we'll return an error as a placeholder
-	Ok(transitions)
+	transitions
 }
 
-/// Builds the property object
-fn build_property(
+/// Builds the target object. Each target line holds a boolean expression over
+/// the variables, and multiple target lines are conjoined.
+fn build_target(
 	raw_data: Vec<(usize, String)>,
-	variable_names: Box<[String]>,
-) -> Result<VasProperty, ModelParseError> {
-	if raw_data.len() != 1 {
-		return Err(ModelParseError::general(
-			0,
-			&"Model parsing error: property must be a single line.",
-		));
+	variable_names: &[String],
+	parameters: &ParameterContext,
+	errors: &mut Vec<ModelParseError>,
+) -> Option<VasTarget> {
+	let constants = parameter_values(parameters);
+	let mut target: Option<VasTarget> = None;
+	for (line_num, line) in raw_data.iter() {
+		// Everything after the target keyword is the expression
+		let expression = line
+			.trim()
+			.split_once(char::is_whitespace)
+			.map(|(_, rest)| rest.trim_start())
+			.unwrap_or("");
+		match parse_target_with_constants(expression, variable_names, &constants) {
+			Ok(line_target) => {
+				target = Some(match target {
+					Some(target) => target.and(line_target),
+					None => line_target,
+				});
+			}
+			Err(e) => errors.push(ModelParseError::general(*line_num, &e).at(line, expression)),
+		}
 	}
-	let words: &[&str] = &raw_data[0].1.split_whitespace().collect::<Vec<&str>>()[..];
-	let variable_name = words.get(1).unwrap_or(&"");
-	let variable_index = get_variable_id(&*variable_names, variable_name);
-	if variable_index.is_none() {
-		return Err(ModelParseError::unspecified_variable(
-			raw_data[0].0.try_into().unwrap(),
-			&variable_name,
-		));
+	target
+}
+
+/// Reads the optional `kinetics stochastic|deterministic` line, which selects
+/// the rate convention for the whole model
+fn build_rate_convention(
+	raw_data: Vec<(usize, String)>,
+	errors: &mut Vec<ModelParseError>,
+) -> RateConvention {
+	if let Some((line_num, line)) = raw_data.get(1) {
+		errors.push(
+			ModelParseError::general(
+				*line_num,
+				&"Model parsing error: kinetics is declared more than once.",
+			)
+			.at(line, line.trim()),
+		);
 	}
-	let variable_index = variable_index.unwrap();
-	let target_value = if words.len() == 4 {
-		if let Ok(value) = words[3].parse::<VasValue>() {
-			value
-		} else {
-			return Err(ModelParseError::expected_integer(
-				raw_data[0].0.try_into().unwrap(),
-				&words[2],
-			));
-		}
-	} else {
-		return Err(ModelParseError::unexpected_token(
-			raw_data[0].0.try_into().unwrap(),
-			&raw_data[0].1,
-		));
+	let Some((line_num, line)) = raw_data.first() else {
+		return RateConvention::default();
 	};
+	let words: Vec<&str> = line.split_whitespace().collect();
+	if words.len() != 2 {
+		errors
+			.push(ModelParseError::unexpected_token(*line_num, &line.trim()).at(line, line.trim()));
+		return RateConvention::default();
+	}
+	words[1].parse::<RateConvention>().unwrap_or_else(|e| {
+		errors.push(ModelParseError::general(*line_num, &e).at(line, words[1]));
+		RateConvention::default()
+	})
+}
 
-	let property = VasProperty {
-		variable_index,
-		target_value,
+/// Reads the optional `ctmc` or `dtmc` line, which selects whether the
+/// transitions have rates or weights
+fn build_model_type(
+	raw_data: Vec<(usize, String)>,
+	errors: &mut Vec<ModelParseError>,
+) -> ModelType {
+	if let Some((line_num, line)) = raw_data.get(1) {
+		errors.push(
+			ModelParseError::general(
+				*line_num,
+				&"Model parsing error: the model type is declared more than once.",
+			)
+			.at(line, line.trim()),
+		);
+	}
+	let Some((line_num, line)) = raw_data.first() else {
+		return ModelType::ContinuousTime;
 	};
+	let words: Vec<&str> = line.split_whitespace().collect();
+	if words.len() != 1 {
+		errors.push(ModelParseError::unexpected_token(*line_num, &words[1]).at(line, words[1]));
+	}
+	words[0].parse::<ModelType>().unwrap_or_else(|e| {
+		errors.push(ModelParseError::general(*line_num, &e).at(line, words[0]));
+		ModelType::ContinuousTime
+	})
+}
 
-	Ok(property)
+/// Reads every line of a file, failing on the first I/O error rather than
+/// parsing a file that was only partly read
+fn read_all_lines(path: &Path) -> io::Result<Vec<String>> {
+	read_lines(path)?.collect()
 }
 
-/// Reads a VAS model file and builds an AbstractVas model
-pub fn build_model(filename: &str) -> Result<AbstractVas, ModelParseError> {
-	// Initialize everything
-	let lines = read_lines(&filename).map_err(|_| {
-		ModelParseError::general(
+/// Reads a VAS model file and builds an AbstractVas model.
+/// Parsing continues past errors, so that every problem in the file is reported.
+pub fn build_model(
+	filename: &str,
+	options: &ModelOptions,
+) -> Result<AbstractVas, Vec<ModelParseError>> {
+	let lines = read_all_lines(Path::new(filename)).map_err(|e| {
+		vec![ModelParseError::general(
 			0,
-			&"line-by-line file parsing not Ok. Check your model file.",
-		)
+			&format!("Unable to read `{}`: {}", filename, e),
+		)]
 	})?;
+	build_model_from_lines(lines.into_iter(), filename, options)
+}
 
+/// Builds an AbstractVas model from the lines of a model file
+pub fn build_model_from_lines(
+	lines: impl Iterator<Item = String>,
+	filename: &str,
+	options: &ModelOptions,
+) -> Result<AbstractVas, Vec<ModelParseError>> {
+	// The file itself is on the include stack, so that it cannot include itself
+	let mut include_stack: Vec<PathBuf> = Path::new(filename).canonicalize().into_iter().collect();
+	// A property file given with `--prop` takes the place of the target lines
+	let target_required = options.property_file.is_none();
+	let parts = build_model_parts(
+		lines,
+		filename,
+		&mut include_stack,
+		target_required,
+		options,
+	)?;
+	options
+		.parameters
+		.warn_unused(&parts.parameter_names, filename);
+	let target = match override_target(options, &parts.variable_names) {
+		Some(target) => Some(target.map_err(|e| vec![ModelParseError::general(0, &e)])?),
+		None => parts.target,
+	};
+	let Some(target) = target else {
+		return Err(vec![ModelParseError::general(
+			0,
+			&"Model parsing error: no target property specified.",
+		)]);
+	};
+	let mut model = AbstractVas::new(
+		parts.variable_names,
+		parts.initial_states,
+		parts.transitions,
+		target,
+	);
+	model.set_rate_convention(parts.rate_convention);
+	model.set_model_type(parts.model_type);
+	if let Err(e) = model.set_initial_weights(parts.initial_weights) {
+		return Err(vec![ModelParseError::general(0, &e)]);
+	}
+	Ok(model)
+}
+
+/// The parts of a model file, with its included files merged in
+struct ModelParts {
+	variable_names: Box<[String]>,
+	initial_states: Vec<VasState>,
+	initial_weights: Vec<ProbabilityOrRate>,
+	transitions: Vec<VasTransition>,
+	rate_convention: RateConvention,
+	model_type: ModelType,
+	// Only required of the top-level file
+	target: Option<VasTarget>,
+	// The parameters declared here and in the included files
+	parameter_names: Vec<String>,
+}
+
+/// A file included as `include "FILE" as PREFIX`
+struct Include {
+	line_num: usize,
+	line: String,
+	path: String,
+	prefix: String,
+	parts: ModelParts,
+}
+
+/// A variable of an included file shared as `share PREFIX.VARIABLE = NAME`
+struct Share {
+	line_num: usize,
+	line: String,
+	prefix: String,
+	variable: String,
+	name: String,
+	used: bool,
+}
+
+/// Builds the parts of a model from the lines of a model file. The stack holds
+/// the files being included, to catch files that include themselves.
+fn build_model_parts(
+	lines: impl Iterator<Item = String>,
+	filename: &str,
+	include_stack: &mut Vec<PathBuf>,
+	target_required: bool,
+	options: &ModelOptions,
+) -> Result<ModelParts, Vec<ModelParseError>> {
 	// Setup strings for the various things
+	let mut errors = Vec::<ModelParseError>::new();
+	let mut parameter_lines = Vec::<(usize, String)>::new();
 	let mut variable_lines = Vec::<(usize, String)>::new();
 	let mut transition_lines = Vec::<Vec<(usize, String)>>::new();
 	let mut property_lines = Vec::<(usize, String)>::new();
+	let mut kinetics_lines = Vec::<(usize, String)>::new();
+	let mut include_lines = Vec::<(usize, String)>::new();
+	let mut share_lines = Vec::<(usize, String)>::new();
+	let mut model_type_lines = Vec::<(usize, String)>::new();
 	let mut current_transition = Vec::<(usize, String)>::new();
+	let mut initial_lines = Vec::<Vec<(usize, String)>>::new();
+	// The `initial` block being read, which lasts until the next keyword
+	let mut current_initial: Option<Vec<(usize, String)>> = None;
 
-	for (num, line) in lines.flatten().enumerate() {
-		// Split the line into words and convert to a slice, then sort the line by first words
-		let words: &[&str] = &line.split_whitespace().collect::<Vec<&str>>()[..];
-		let first_word = words.get(0).unwrap_or(&"");
-
-		// VARIABLE_TERMS
-		// INITIAL_TERMS
-		// TRANSITION_TERMS
-		// DECREASE_TERMS
-		// INCREASE_TERMS
-		// RATE_TERMS
-		// TARGET_TERMS
+	for (index, line) in lines.enumerate() {
+		// Line numbers are one-based
+		let num = index + 1;
+		// Split the line into words, then sort the line by first words
+		let first_word = line.split_whitespace().next().unwrap_or("");
+		if ALL_TERMS.iter().any(|terms| terms.contains(&first_word)) {
+			if let Some(block) = current_initial.take() {
+				initial_lines.push(block);
+			}
+		}
 
 		// Check the first word against the keywords
-		if VARIABLE_TERMS.contains(first_word) {
+		if let Some(block) = current_initial.as_mut().filter(|_| !first_word.is_empty()) {
+			// Inside an `initial` block, every line sets a variable
+			block.push((num, line));
+		} else if INITIAL_TERMS.contains(&first_word) {
+			// An `initial` block ends the reaction before it
+			if !current_transition.is_empty() {
+				transition_lines.push(std::mem::take(&mut current_transition));
+			}
+			current_initial = Some(Vec::from([(num, line)]));
+		} else if PARAMETER_TERMS.contains(&first_word) {
+			parameter_lines.push((num, line));
+		} else if VARIABLE_TERMS.contains(&first_word) {
 			variable_lines.push((num, line));
-		} else if TRANSITION_TERMS.contains(first_word) {
+		} else if TRANSITION_TERMS.contains(&first_word) {
 			if !current_transition.is_empty() {
 				transition_lines.push(current_transition);
 			}
 			current_transition = Vec::from([(num, line)]);
-		} else if DECREASE_TERMS.contains(first_word) || INCREASE_TERMS.contains(first_word) {
-			current_transition.push((num, line));
-		} else if RATE_TERMS.contains(first_word) {
-			current_transition.push((num, line));
-		} else if TARGET_TERMS.contains(first_word) {
+		} else if DECREASE_TERMS.contains(&first_word)
+			|| INCREASE_TERMS.contains(&first_word)
+			|| RATE_TERMS.contains(&first_word)
+		{
+			if current_transition.is_empty() {
+				errors.push(
+					ModelParseError::general(
+						num,
+						&format!("`{}` must follow a `reaction` line.", first_word),
+					)
+					.at(&line, first_word),
+				);
+			} else {
+				current_transition.push((num, line));
+			}
+		} else if TARGET_TERMS.contains(&first_word) {
 			property_lines.push((num, line));
-		} else if line != "" {
-			return Err(ModelParseError::unexpected_token(
-				num.try_into().unwrap(),
-				&line,
-			));
+		} else if KINETICS_TERMS.contains(&first_word) {
+			kinetics_lines.push((num, line));
+		} else if INCLUDE_TERMS.contains(&first_word) {
+			include_lines.push((num, line));
+		} else if SHARE_TERMS.contains(&first_word) {
+			share_lines.push((num, line));
+		} else if MODEL_TYPE_TERMS.contains(&first_word) {
+			model_type_lines.push((num, line));
+		} else if !first_word.is_empty() {
+			errors.push(ModelParseError::unexpected_token(num, &first_word).at(&line, first_word));
 		}
-		// TODO: Implement better error handling at initial parse.
 	}
-	transition_lines.push(current_transition);
+	if !current_transition.is_empty() {
+		transition_lines.push(current_transition);
+	}
+	if let Some(block) = current_initial {
+		initial_lines.push(block);
+	}
 
-	// Parse the variables and initial states
-	let (variable_names, initial_states) = match build_variables(variable_lines) {
-		Ok(result) => result,
-		Err(e) => return Err(e),
-	};
+	// Parse the parameters, which may be used everywhere below
+	let (parameters, mut parameter_names) =
+		build_parameters(parameter_lines, &options.parameters, &mut errors);
 
-	// Read the property
-	let target = build_property(property_lines, variable_names.clone())?;
+	let rate_convention = build_rate_convention(kinetics_lines, &mut errors);
+	let model_type = build_model_type(model_type_lines, &mut errors);
 
-	// Read the transitions
-	let transitions = match build_transitions(transition_lines, &variable_names) {
-		Ok(result) => result,
-		Err(e) => {
-			error!("ERROR DURING TRANSITION PARSING:\n{}", e.to_string());
-			return Err(e);
+	// Parse the variables, then merge in the variables of the included files
+	let (variable_names, default_initial_state) =
+		build_variables(variable_lines, &parameters, &mut errors);
+	let mut variable_names = variable_names.into_vec();
+	let mut default_initial_state = default_initial_state.into_vec();
+	let includes = build_includes(include_lines, filename, include_stack, options, &mut errors);
+	let mut shares = build_shares(share_lines, &mut errors);
+	let mut index_maps = Vec::<Vec<usize>>::new();
+	for include in includes.iter() {
+		parameter_names.extend(include.parts.parameter_names.iter().cloned());
+		if include.parts.rate_convention != rate_convention {
+			errors.push(
+				ModelParseError::general(
+					include.line_num,
+					&format!(
+						"`{}` uses {} kinetics, but this model uses {} kinetics.",
+						include.path, include.parts.rate_convention, rate_convention
+					),
+				)
+				.at(&include.line, find_in(&include.line, &include.path)),
+			);
 		}
-	};
+		if include.parts.model_type != model_type {
+			errors.push(
+				ModelParseError::general(
+					include.line_num,
+					&format!(
+						"`{}` is a {}, but this model is a {}.",
+						include.path, include.parts.model_type, model_type
+					),
+				)
+				.at(&include.line, find_in(&include.line, &include.path)),
+			);
+		}
+		index_maps.push(merge_variables(
+			include,
+			&mut shares,
+			&mut variable_names,
+			&mut default_initial_state,
+			&mut errors,
+		));
+	}
+	for share in shares.iter().filter(|share| !share.used) {
+		let shared = format!("{}.{}", share.prefix, share.variable);
+		errors.push(
+			ModelParseError::general(
+				share.line_num,
+				&format!("`{}` is not a variable of an included file.", shared),
+			)
+			.at(&share.line, share.line.trim()),
+		);
+	}
+	let variable_names = variable_names.into_boxed_slice();
 
-	// Return the model
-	let model = AbstractVas::new(
+	// Parse the initial states over every variable
+	let (initial_states, initial_weights) = build_initial_states(
+		initial_lines,
+		&variable_names,
+		&default_initial_state,
+		&parameters,
+		&mut errors,
+	);
+
+	// Read the target
+	if target_required && property_lines.is_empty() {
+		errors.push(ModelParseError::general(
+			0,
+			&"Model parsing error: no target property specified.",
+		));
+	}
+	let target = build_target(property_lines, &variable_names, &parameters, &mut errors);
+
+	// Read the transitions, then add those of the included files after them,
+	// numbered in the order they are added
+	let mut transitions =
+		build_transitions(transition_lines, &variable_names, &parameters, &mut errors);
+	for (include, index_map) in includes.iter().zip(index_maps.iter()) {
+		for transition in include.parts.transitions.iter() {
+			let name = format!("{}.{}", include.prefix, transition.transition_name);
+			if transitions.iter().any(|t| t.transition_name == name) {
+				errors.push(
+					ModelParseError::duplicate_transition(include.line_num, &name)
+						.at(&include.line, find_in(&include.line, &include.prefix)),
+				);
+			}
+			transitions.push(transition.embedded(
+				transitions.len(),
+				name,
+				variable_names.len(),
+				index_map,
+			));
+		}
+	}
+
+	// Report every problem, in the order they appear in the file
+	if !errors.is_empty() {
+		errors.sort_by_key(|e| e.line);
+		return Err(errors);
+	}
+	Ok(ModelParts {
 		variable_names,
-		[VasState::new(DVector::from_vec(initial_states.to_vec()))].to_vec(),
+		initial_states,
+		initial_weights,
 		transitions,
+		rate_convention,
+		model_type,
 		target,
-	);
+		parameter_names,
+	})
+}
 
-	Ok(model)
+/// Reads the `include "FILE" as PREFIX` lines and parses the included files.
+/// Paths are relative to the including file.
+fn build_includes(
+	raw_data: Vec<(usize, String)>,
+	filename: &str,
+	include_stack: &mut Vec<PathBuf>,
+	options: &ModelOptions,
+	errors: &mut Vec<ModelParseError>,
+) -> Vec<Include> {
+	let mut includes = Vec::<Include>::new();
+	for (line_num, line) in raw_data {
+		let declaration = line
+			.trim()
+			.split_once(char::is_whitespace)
+			.map(|(_, rest)| rest.trim())
+			.unwrap_or("");
+		// The path is quoted, so that it may contain spaces
+		let parsed = declaration
+			.strip_prefix('"')
+			.and_then(|rest| rest.split_once('"'))
+			.and_then(
+				|(path, rest)| match rest.split_whitespace().collect::<Vec<_>>()[..] {
+					["as", prefix]
+						if !prefix.is_empty()
+							&& prefix.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+					{
+						Some((path.to_string(), prefix.to_string()))
+					}
+					_ => None,
+				},
+			);
+		let Some((path, prefix)) = parsed else {
+			errors.push(
+				ModelParseError::general(line_num, &"Expected `include \"FILE\" as PREFIX`.")
+					.at(&line, line.trim()),
+			);
+			continue;
+		};
+		if includes.iter().any(|include| include.prefix == prefix) {
+			errors.push(
+				ModelParseError::general(
+					line_num,
+					&format!("The prefix `{}` is used more than once.", prefix),
+				)
+				.at(&line, find_in(&line, &prefix)),
+			);
+			continue;
+		}
+		let resolved = Path::new(filename)
+			.parent()
+			.unwrap_or(Path::new(""))
+			.join(&path);
+		let canonical = resolved.canonicalize().unwrap_or(resolved.clone());
+		if include_stack.contains(&canonical) {
+			errors.push(
+				ModelParseError::general(line_num, &format!("`{}` includes itself.", path))
+					.at(&line, find_in(&line, &path)),
+			);
+			continue;
+		}
+		let lines = match read_all_lines(&resolved) {
+			Ok(lines) => lines,
+			Err(e) => {
+				errors.push(
+					ModelParseError::general(
+						line_num,
+						&format!("Unable to read `{}`: {}", resolved.display(), e),
+					)
+					.at(&line, find_in(&line, &path)),
+				);
+				continue;
+			}
+		};
+		include_stack.push(canonical);
+		let parsed = build_model_parts(
+			lines.into_iter(),
+			&resolved.to_string_lossy(),
+			include_stack,
+			false,
+			options,
+		);
+		include_stack.pop();
+		match parsed {
+			Ok(parts) => includes.push(Include {
+				line_num,
+				line,
+				path,
+				prefix,
+				parts,
+			}),
+			// The errors of an included file are reported at the `include` line
+			Err(included_errors) => {
+				for error in included_errors {
+					errors.push(
+						ModelParseError::general(
+							line_num,
+							&format!(
+								"In `{}`, line {}: {}",
+								path,
+								error.line,
+								error.etype.to_string()
+							),
+						)
+						.at(&line, find_in(&line, &path)),
+					);
+				}
+			}
+		}
+	}
+	includes
+}
+
+/// Reads the `share PREFIX.VARIABLE = NAME` lines
+fn build_shares(raw_data: Vec<(usize, String)>, errors: &mut Vec<ModelParseError>) -> Vec<Share> {
+	let mut shares = Vec::<Share>::new();
+	for (line_num, line) in raw_data {
+		let declaration = line
+			.trim()
+			.split_once(char::is_whitespace)
+			.map(|(_, rest)| rest.trim())
+			.unwrap_or("");
+		let parsed = declaration.split_once('=').and_then(|(shared, name)| {
+			let (prefix, variable) = shared.trim().split_once('.')?;
+			let name = name.trim();
+			(!prefix.is_empty() && !variable.is_empty() && !name.is_empty())
+				.then(|| (prefix.to_string(), variable.to_string(), name.to_string()))
+		});
+		let Some((prefix, variable, name)) = parsed else {
+			errors.push(
+				ModelParseError::general(line_num, &"Expected `share PREFIX.VARIABLE = NAME`.")
+					.at(&line, line.trim()),
+			);
+			continue;
+		};
+		if shares
+			.iter()
+			.any(|share| share.prefix == prefix && share.variable == variable)
+		{
+			errors.push(
+				ModelParseError::general(
+					line_num,
+					&format!("`{}.{}` is shared more than once.", prefix, variable),
+				)
+				.at(&line, declaration),
+			);
+			continue;
+		}
+		shares.push(Share {
+			line_num,
+			line,
+			prefix,
+			variable,
+			name,
+			used: false,
+		});
+	}
+	shares
+}
+
+/// Adds the variables of an included file as `PREFIX.VARIABLE`, unless they are
+/// shared with a variable declared before them. Returns the index of each
+/// variable of the included file in the merged variables.
+fn merge_variables(
+	include: &Include,
+	shares: &mut [Share],
+	variable_names: &mut Vec<String>,
+	initial_state: &mut Vec<VasValue>,
+	errors: &mut Vec<ModelParseError>,
+) -> Vec<usize> {
+	let mut index_map = Vec::new();
+	if include.parts.initial_states.len() != 1 {
+		errors.push(
+			ModelParseError::general(
+				include.line_num,
+				&format!(
+					"`{}` has more than one initial state, which cannot be included.",
+					include.path
+				),
+			)
+			.at(&include.line, find_in(&include.line, &include.path)),
+		);
+	}
+	let included_state = &include.parts.initial_states[0].vector;
+	for (i, variable) in include.parts.variable_names.iter().enumerate() {
+		let share = shares
+			.iter_mut()
+			.find(|share| share.prefix == include.prefix && &share.variable == variable);
+		if let Some(share) = share {
+			share.used = true;
+			match get_variable_id(variable_names, &share.name) {
+				Some(index) => {
+					if initial_state[index] != included_state[i] {
+						warning!(
+							"`{}.{}` starts at {} in `{}`, but is shared with `{}`, which starts at {}.",
+							include.prefix,
+							variable,
+							included_state[i],
+							include.path,
+							share.name,
+							initial_state[index]
+						);
+					}
+					index_map.push(index);
+					continue;
+				}
+				None => errors.push(
+					ModelParseError::unspecified_variable(share.line_num, &share.name)
+						.at(&share.line, find_in(&share.line, &share.name)),
+				),
+			}
+		}
+		let name = format!("{}.{}", include.prefix, variable);
+		if variable_names.contains(&name) {
+			errors.push(
+				ModelParseError::duplicate_variable(include.line_num, &name)
+					.at(&include.line, find_in(&include.line, &include.prefix)),
+			);
+		}
+		index_map.push(variable_names.len());
+		variable_names.push(name);
+		initial_state.push(included_state[i]);
+	}
+	index_map
 }
@@ -1,20 +1,23 @@
 use crate::model::vas_model::AbstractVas;
 
-use super::parser::{ModelParseError, Parser};
+use super::parser::{ModelOptions, ModelParseError, Parser};
 
 pub(crate) struct VasParser;
 impl Parser for VasParser {
 	type ModelType = AbstractVas;
 	type ParserErrorType = VasParseError;
 
-	fn parse(_filename: &str) -> Result<Self::ModelType, Self::ParserErrorType> {
+	fn parse(
+		_filename: &str,
+		_options: &ModelOptions,
+	) -> Result<Self::ModelType, Self::ParserErrorType> {
 		// Implement the parsing logic here
 		// For now, we'll return an error as a placeholder
 		Err(VasParseError::new(1, "Placeholder error".to_string()))
 	}
 
-	fn parse_or_panic(filename: &str) -> ModelType {
-		let model = Self::parse(filename);
+	fn parse_or_panic(filename: &str, options: &ModelOptions) -> ModelType {
+		let model = Self::parse(filename, options);
 		match model {
 			Ok(model) => {
 				return model.into();
@@ -29,8 +32,8 @@ impl Parser for VasParser {
 // Ensure ModelType is properly imported or defined
 use crate::model::model::ModelType;
 impl From<AbstractVas> for ModelType {
-	fn from(_abstract_vas: AbstractVas) -> Self {
-		unimplemented!("Conversion from AbstractVas to ModelType is not implemented yet");
+	fn from(abstract_vas: AbstractVas) -> Self {
+		abstract_vas.m_type
 	}
 }
 
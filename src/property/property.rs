@@ -1,4 +1,12 @@
-use std::fmt::{Display, Error, Formatter};
+use std::{
+	cmp::Ordering,
+	fmt::{Display, Error, Formatter},
+	sync::Once,
+};
+
+use evalexpr::{DefaultNumericTypes, Node};
+
+use crate::{model::model::State, warning};
 
 /// A trait representing a label on a labeled type
 pub(crate) trait Label: ToString + Clone {
@@ -8,7 +16,7 @@ pub(crate) trait Label: ToString + Clone {
 	/// E.g., a label containing `"A > 5 & B < 3"` would be a subset
 	/// of the label `"A > 5"`.
 
-	fn contains(&self, label: &Self);
+	fn contains(&self, label: &Self) -> bool;
 	/// Composes two labels to create a label that represents both
 
 	fn compose(&self, label: &Self) -> Self;
@@ -37,7 +45,7 @@ pub(crate) enum PropertyClass {
 	LinearTemporalLogic,                   // Nonprobabilistic properties
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Property {
 	/// Where the state formula holds for all
 	Globally(StateFormula),
@@ -45,13 +53,116 @@ pub(crate) enum Property {
 	Until(StateFormula, StateFormula, Option<f64>), // Optional bound
 }
 
-#[derive(Debug)]
+impl Property {
+	/// The time bound of the property, if any
+	pub fn time_bound(&self) -> Option<f64> {
+		match self {
+			Property::Globally(_) => None,
+			Property::Finally(_, bound) | Property::Until(_, _, bound) => *bound,
+		}
+	}
+
+	/// The states a path must reach to satisfy the property. Only `F` (and
+	/// `true U`) properties are decided by reaching a set of states, so other
+	/// path forms are an error rather than a target that drops part of them.
+	pub fn target_formula(&self) -> Result<StateFormula, String> {
+		match self {
+			Property::Finally(formula, _) => Ok(formula.clone()),
+			Property::Until(StateFormula::Constant(true), formula, _) => Ok(formula.clone()),
+			property => Err(format!(
+				"`{}` cannot be expressed as a target to reach. Use an `F` property or define `label \"target\"`.",
+				property
+			)),
+		}
+	}
+}
+
+impl Display for Property {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+		let bound = |bound: &Option<f64>| bound.map_or(String::new(), |t| format!("<={}", t));
+		match self {
+			Property::Globally(formula) => write!(f, "G {}", formula),
+			Property::Finally(formula, time) => write!(f, "F{} {}", bound(time), formula),
+			Property::Until(lhs, rhs, time) => write!(f, "{} U{} {}", lhs, bound(time), rhs),
+		}
+	}
+}
+
+/// The comparison in a probability bound, e.g., the `>=` of `P>=0.9 [...]`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ProbabilityRelation {
+	LessThan,
+	LessThanOrEqual,
+	GreaterThan,
+	GreaterThanOrEqual,
+}
+
+impl ProbabilityRelation {
+	/// Evaluates `probability <relation> bound`
+	pub fn holds(&self, probability: f64, bound: f64) -> bool {
+		match self {
+			ProbabilityRelation::LessThan => probability < bound,
+			ProbabilityRelation::LessThanOrEqual => probability <= bound,
+			ProbabilityRelation::GreaterThan => probability > bound,
+			ProbabilityRelation::GreaterThanOrEqual => probability >= bound,
+		}
+	}
+}
+
+impl Display for ProbabilityRelation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+		match self {
+			ProbabilityRelation::LessThan => write!(f, "<"),
+			ProbabilityRelation::LessThanOrEqual => write!(f, "<="),
+			ProbabilityRelation::GreaterThan => write!(f, ">"),
+			ProbabilityRelation::GreaterThanOrEqual => write!(f, ">="),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum PropertyQuery {
 	/// We are computing the probability of something.
-	Probability(Property), // TODO: should have Option<(evalexpr::Operator, f64)> for specific
-	// bounds? Or just leave this as is?
+	Probability(Property),
+	/// Whether the probability of something meets a bound, e.g., `P>=0.9 [...]`
+	BoundedProbability(Property, ProbabilityRelation, f64),
 	MaxProbability(Property),
-	SteadyState(Property),
+	/// The long-run probability of being in states where the formula holds
+	SteadyState(StateFormula),
+}
+
+impl PropertyQuery {
+	/// The path property of a probability query
+	pub fn property(&self) -> Option<&Property> {
+		match self {
+			PropertyQuery::Probability(property)
+			| PropertyQuery::BoundedProbability(property, _, _)
+			| PropertyQuery::MaxProbability(property) => Some(property),
+			PropertyQuery::SteadyState(_) => None,
+		}
+	}
+
+	/// The states of interest: the target of the path property, or the
+	/// states counted by a steady-state query
+	pub fn target_formula(&self) -> Result<StateFormula, String> {
+		match self {
+			PropertyQuery::SteadyState(formula) => Ok(formula.clone()),
+			query => query.property().unwrap().target_formula(),
+		}
+	}
+}
+
+impl Display for PropertyQuery {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+		match self {
+			PropertyQuery::Probability(property) => write!(f, "P=? [ {} ]", property),
+			PropertyQuery::BoundedProbability(property, relation, bound) => {
+				write!(f, "P{}{} [ {} ]", relation, bound, property)
+			}
+			PropertyQuery::MaxProbability(property) => write!(f, "Pmax=? [ {} ]", property),
+			PropertyQuery::SteadyState(formula) => write!(f, "S=? [ {} ]", formula),
+		}
+	}
 }
 
 /// A trait representing any type of CSL, PCTL, or LTL property
@@ -61,24 +172,230 @@ pub(crate) enum PropertyQuery {
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub(crate) enum StateFormula {
+	/// `true` or `false`
+	Constant(bool),
+	/// A label defined in the property file or the model, e.g., `"target"`
 	StateLabel(String),
-	Expression(Box<crate::property::property::StateFormula>),
+	/// An atomic comparison over the variables, e.g., `S1 >= 30`
+	Expression(StateExpression),
+	Not(Box<StateFormula>),
+	And(Box<StateFormula>, Box<StateFormula>),
+	Or(Box<StateFormula>, Box<StateFormula>),
+}
+
+impl StateFormula {
+	/// The label with the given name, e.g., `"target"`
+	pub fn label(name: &str) -> Self {
+		StateFormula::StateLabel(name.to_string())
+	}
+
+	/// Whether the formula holds in a state. Labels are looked up in the
+	/// state, and expressions are evaluated over its variables.
+	pub fn holds_in<S>(&self, state: &S) -> bool
+	where
+		S: State + Labeled<LabelType = StateFormula>,
+		S: evalexpr::Context<NumericTypes = DefaultNumericTypes>,
+	{
+		match self {
+			StateFormula::Constant(value) => *value,
+			StateFormula::StateLabel(_) => state.has_label(self),
+			StateFormula::Expression(expression) => expression.holds_in(state),
+			StateFormula::Not(inner) => !inner.holds_in(state),
+			StateFormula::And(lhs, rhs) => lhs.holds_in(state) && rhs.holds_in(state),
+			StateFormula::Or(lhs, rhs) => lhs.holds_in(state) || rhs.holds_in(state),
+		}
+	}
+
+	/// Whether the formula holds in a state that has labels but no variables, e.g.,
+	/// a sink that stands for other states. `None` if the labels do not decide it.
+	pub fn holds_by_labels<S>(&self, state: &S) -> Option<bool>
+	where
+		S: Labeled<LabelType = StateFormula>,
+	{
+		match self {
+			StateFormula::Constant(value) => Some(*value),
+			StateFormula::StateLabel(_) => Some(state.has_label(self)),
+			StateFormula::Expression(_) => None,
+			StateFormula::Not(inner) => inner.holds_by_labels(state).map(|holds| !holds),
+			StateFormula::And(lhs, rhs) => {
+				match (lhs.holds_by_labels(state), rhs.holds_by_labels(state)) {
+					(Some(false), _) | (_, Some(false)) => Some(false),
+					(Some(true), Some(true)) => Some(true),
+					_ => None,
+				}
+			}
+			StateFormula::Or(lhs, rhs) => {
+				match (lhs.holds_by_labels(state), rhs.holds_by_labels(state)) {
+					(Some(true), _) | (_, Some(true)) => Some(true),
+					(Some(false), Some(false)) => Some(false),
+					_ => None,
+				}
+			}
+		}
+	}
+}
+
+/// A boolean expression over the variables of a state, kept as written in
+/// PRISM syntax and compiled for evalexpr
+#[derive(Clone, Debug)]
+pub(crate) struct StateExpression {
+	text: String,
+	tree: Node<DefaultNumericTypes>,
+}
+
+impl StateExpression {
+	/// Compiles an expression such as `S1 + 2*S2 >= 40` or `S1 = 30`
+	pub fn new(text: &str) -> Result<Self, String> {
+		let tree = evalexpr::build_operator_tree::<DefaultNumericTypes>(&evalexpr_syntax(text))
+			.map_err(|e| format!("Unable to parse `{}`: {}", text, e))?;
+		Ok(Self {
+			text: text.to_string(),
+			tree,
+		})
+	}
+
+	/// The expression as written
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	/// Whether the expression holds in a state. Expressions that cannot be
+	/// evaluated, e.g., over unknown variables, do not hold, and the first
+	/// such failure is reported.
+	pub fn holds_in<C>(&self, state: &C) -> bool
+	where
+		C: evalexpr::Context<NumericTypes = DefaultNumericTypes>,
+	{
+		static WARNED: Once = Once::new();
+		self.tree
+			.eval_boolean_with_context(state)
+			.unwrap_or_else(|e| {
+				WARNED.call_once(|| {
+					warning!(
+						"Unable to evaluate `{}`: {}. It does not hold wherever it fails.",
+						self.text,
+						e
+					);
+				});
+				false
+			})
+	}
+}
+
+/// Rewrites an expression from PRISM syntax for evalexpr: `=` compares, `&`
+/// and `|` are `&&` and `||`, and integers are written as floats, since the
+/// values of a state are floats and evalexpr does not equate `30` and `30.0`.
+fn evalexpr_syntax(text: &str) -> String {
+	let chars: Vec<char> = text.chars().collect();
+	let mut output = String::new();
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		let previous = i.checked_sub(1).map(|j| chars[j]);
+		let next = chars.get(i + 1).copied();
+		let in_identifier = previous.is_some_and(|p| p.is_alphanumeric() || p == '_' || p == '.');
+		match c {
+			'=' if !matches!(previous, Some('<' | '>' | '!' | '=')) && next != Some('=') => {
+				output.push_str("==")
+			}
+			'&' if previous != Some('&') && next != Some('&') => output.push_str("&&"),
+			'|' if previous != Some('|') && next != Some('|') => output.push_str("||"),
+			_ if c.is_ascii_digit() && !in_identifier => {
+				let start = i;
+				while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+					i += 1;
+				}
+				let literal: String = chars[start..i].iter().collect();
+				output.push_str(&literal);
+				if !literal.contains('.') && !matches!(chars.get(i), Some('e' | 'E')) {
+					output.push_str(".0");
+				}
+				continue;
+			}
+			_ => output.push(c),
+		}
+		i += 1;
+	}
+	output
+}
+
+impl PartialEq for StateExpression {
+	fn eq(&self, other: &Self) -> bool {
+		self.text == other.text
+	}
+}
+
+impl Eq for StateExpression {}
+
+impl PartialOrd for StateExpression {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for StateExpression {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.text.cmp(&other.text)
+	}
+}
+
+impl Display for StateExpression {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+		write!(f, "{}", self.text)
+	}
 }
 
 impl Label for StateFormula {
 	type LabeledType = StateFormula;
 
-	fn contains(&self, _label: &Self) {
-		todo!()
+	/// A syntactic check: `label` is a subset of this formula if it is the
+	/// same formula, a conjunction with a part that is a subset, and so on.
+	/// Formulas that are equivalent only semantically are not recognized.
+	fn contains(&self, label: &Self) -> bool {
+		if self == label {
+			return true;
+		}
+		match (self, label) {
+			(StateFormula::Constant(true), _) | (_, StateFormula::Constant(false)) => true,
+			(_, StateFormula::And(lhs, rhs)) if self.contains(lhs) || self.contains(rhs) => true,
+			(_, StateFormula::Or(lhs, rhs)) => self.contains(lhs) && self.contains(rhs),
+			(StateFormula::And(lhs, rhs), _) => lhs.contains(label) && rhs.contains(label),
+			(StateFormula::Or(lhs, rhs), _) => lhs.contains(label) || rhs.contains(label),
+			_ => false,
+		}
 	}
 
-	fn compose(&self, _label: &Self) -> Self {
-		todo!()
+	fn compose(&self, label: &Self) -> Self {
+		StateFormula::And(Box::new(self.clone()), Box::new(label.clone()))
 	}
 }
 
 impl Display for StateFormula {
-	fn fmt(&self, _f: &mut Formatter<'_>) -> Result<(), Error> {
-		todo!()
+	/// Writes the formula in PRISM syntax, with parentheses only where needed
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+		match self {
+			StateFormula::Constant(value) => write!(f, "{}", value),
+			StateFormula::StateLabel(label) => write!(f, "\"{}\"", label),
+			StateFormula::Expression(expression) => write!(f, "{}", expression),
+			StateFormula::Not(inner) => match inner.as_ref() {
+				StateFormula::Constant(_) | StateFormula::StateLabel(_) | StateFormula::Not(_) => {
+					write!(f, "!{}", inner)
+				}
+				_ => write!(f, "!({})", inner),
+			},
+			StateFormula::And(lhs, rhs) => {
+				for (i, part) in [lhs, rhs].into_iter().enumerate() {
+					if i > 0 {
+						write!(f, " & ")?;
+					}
+					match part.as_ref() {
+						StateFormula::Or(_, _) => write!(f, "({})", part)?,
+						_ => write!(f, "{}", part)?,
+					}
+				}
+				Ok(())
+			}
+			StateFormula::Or(lhs, rhs) => write!(f, "{} | {}", lhs, rhs),
+		}
 	}
 }
@@ -1 +1,2 @@
+pub(crate) mod poisson;
 pub(crate) mod util;
@@ -0,0 +1,64 @@
+/// Poisson probabilities `P(N = k)` for `k` from `left` to `left + weights.len() - 1`,
+/// leaving out the tails that together hold about `epsilon` of the mass.
+/// Used to uniformize continuous-time chains.
+pub(crate) struct PoissonWeights {
+	pub(crate) left: usize,
+	pub(crate) weights: Vec<f64>,
+}
+
+impl PoissonWeights {
+	/// The largest `k` with a weight
+	pub(crate) fn right(&self) -> usize {
+		self.left + self.weights.len() - 1
+	}
+
+	/// The weight of `k`, or 0 outside the window
+	pub(crate) fn weight(&self, k: usize) -> f64 {
+		k.checked_sub(self.left)
+			.and_then(|i| self.weights.get(i))
+			.copied()
+			.unwrap_or(0.0)
+	}
+}
+
+/// Computes the Poisson probabilities with mean `lambda` in the manner of Fox and
+/// Glynn, starting from the mode and working outwards so that large means neither
+/// underflow nor overflow, then normalizing
+pub(crate) fn poisson_weights(lambda: f64, epsilon: f64) -> PoissonWeights {
+	if lambda <= 0.0 {
+		return PoissonWeights {
+			left: 0,
+			weights: vec![1.0],
+		};
+	}
+	let mode = lambda.floor() as usize;
+	// Weights relative to the mode, which are normalized at the end
+	let cutoff = epsilon / (2.0 * (lambda.sqrt() + 1.0));
+	let mut below = Vec::new();
+	let mut weight = 1.0;
+	let mut k = mode;
+	while k > 0 {
+		weight *= k as f64 / lambda;
+		if weight < cutoff {
+			break;
+		}
+		below.push(weight);
+		k -= 1;
+	}
+	let left = mode - below.len();
+	let mut weights: Vec<f64> = below.into_iter().rev().collect();
+	weights.push(1.0);
+	let mut weight = 1.0;
+	let mut k = mode;
+	loop {
+		k += 1;
+		weight *= lambda / k as f64;
+		if weight < cutoff {
+			break;
+		}
+		weights.push(weight);
+	}
+	let total: f64 = weights.iter().sum();
+	weights.iter_mut().for_each(|weight| *weight /= total);
+	PoissonWeights { left, weights }
+}
@@ -1,4 +1,8 @@
-use crate::model::vas_model::{AbstractVas, VasProperty, VasTransition, VasValue};
+use crate::model::{
+	model::{ModelType, ProbabilityOrRate},
+	vas_model::{AbstractVas, PrismVasModel, VasProperty, VasState, VasTransition, VasValue},
+	vas_target::VasTarget,
+};
 use ::std::collections::HashMap;
 use colored::{ColoredString, Colorize};
 
@@ -32,49 +36,93 @@ fn check_variable_names(variable_names: &Box<[String]>) -> Vec<String> {
 	errors
 }
 
-fn initial_state_neq_target(initial_state: Box<[VasValue]>, property: &VasProperty) -> Vec<String> {
+fn initial_state_neq_target(
+	initial_states: &[VasState],
+	target: &VasTarget,
+	variable_names: &[String],
+) -> Vec<String> {
 	let mut errors = Vec::new();
-	if initial_state[property.variable_index] == property.target_value {
-		errors.push(format!(
-			"Initial state [ {} ] satisfies target with value {}",
-			initial_state
-				.iter()
-				.map(|x| format!("{}", x))
-				.collect::<Vec<String>>()
-				.join(" "),
-			property.target_value
-		));
+	for initial_state in initial_states {
+		if target.is_satisfied(&initial_state.vector) {
+			errors.push(format!(
+				"Initial state [ {} ] satisfies target {}",
+				initial_state
+					.vector
+					.iter()
+					.map(|x| format!("{}", x))
+					.collect::<Vec<String>>()
+					.join(" "),
+				target.to_string_with_names(variable_names)
+			));
+		}
 	}
 	errors
 }
 
-// TODO: To check the SCK assumption, I think we actually need the increment/decrement vectors
+/// The most molecules a reaction may consume (uni- or bimolecular)
+const SCK_MAX_REACTANTS: VasValue = 2;
+/// The most molecules a reaction may produce
+const SCK_MAX_PRODUCTS: VasValue = 3;
+
+/// Under the SCK assumption every reaction is uni- or bimolecular, with a
+/// small number of products. Catalysts count as both reactants and products.
+/// Reactions with custom rate laws are left to `check_custom_rates`.
 fn check_sck_assumption(transitions: Vec<VasTransition>) -> Vec<String> {
 	let mut errors = Vec::new();
 
 	for transition in transitions {
-		let total_update: VasValue = transition.update_vector.iter().map(|&val| val).sum();
-		if total_update > 3 || total_update < -3 {
+		if transition.custom_rate_fn.is_some() {
+			continue;
+		}
+		if transition
+			.consume_vector
+			.iter()
+			.chain(transition.produce_vector.iter())
+			.any(|&val| val < 0)
+		{
 			errors.push(format!(
-				"Transition {} has an update vector with total change > 3",
+				"Transition {} has a negative reactant or product count",
 				transition.transition_name
 			));
 		}
 
-		if transition.update_vector.iter().any(|&val| val > 2) {
+		let reactants: VasValue = transition.consume_vector.iter().sum();
+		if reactants > SCK_MAX_REACTANTS {
 			errors.push(format!(
-				"Transition {} has an update with a value > 2",
-				transition.transition_name
+				"Transition {} consumes {} molecules (at most {} allowed)",
+				transition.transition_name, reactants, SCK_MAX_REACTANTS
+			));
+		}
+
+		let products: VasValue = transition.produce_vector.iter().sum();
+		if products > SCK_MAX_PRODUCTS {
+			errors.push(format!(
+				"Transition {} produces {} molecules (at most {} allowed)",
+				transition.transition_name, products, SCK_MAX_PRODUCTS
 			));
 		}
 	}
 	errors
 }
 
+/// Flags reactions with custom rate laws, which the SCK checks do not cover
+fn check_custom_rates(transitions: Vec<VasTransition>) -> Vec<String> {
+	transitions
+		.iter()
+		.filter(|transition| transition.custom_rate_fn.is_some())
+		.map(|transition| {
+			format!(
+				"Transition {} has a custom rate law, so the SCK checks do not apply to it",
+				transition.transition_name
+			)
+		})
+		.collect()
+}
+
 fn check_rate_constant(transitions: Vec<VasTransition>) -> Vec<String> {
 	let mut errors = Vec::new();
 	for transition in transitions {
-		if transition.rate_const <= 0.0 {
+		if transition.custom_rate_fn.is_none() && transition.rate_const <= 0.0 {
 			errors.push(format!(
 				"Transition {} has a non-positive rate constant {}",
 				transition.transition_name, transition.rate_const
@@ -84,6 +132,31 @@ fn check_rate_constant(transitions: Vec<VasTransition>) -> Vec<String> {
 	errors
 }
 
+/// How far a sum of probabilities may exceed 1 through rounding
+const PROBABILITY_TOLERANCE: ProbabilityOrRate = 1e-6;
+
+/// In an explicit DTMC, the outgoing probabilities of every state, absorbing
+/// transitions included, must sum to at most 1
+pub fn check_explicit_probabilities(model: &PrismVasModel) -> Vec<String> {
+	let mut errors = Vec::new();
+	if model.m_type != ModelType::DiscreteTime {
+		return errors;
+	}
+	for (from_state, outgoing) in model.transition_map.iter() {
+		let total: ProbabilityOrRate = outgoing
+			.iter()
+			.map(|(_, index)| model.transitions[*index].rate)
+			.sum();
+		if total > 1.0 + PROBABILITY_TOLERANCE {
+			errors.push(format!(
+				"Outgoing probabilities of state {} sum to {}",
+				from_state, total
+			));
+		}
+	}
+	errors
+}
+
 pub fn write_outcome(test_name: &str, errors: Vec<String>) -> String {
 	let fail = "FAIL".red();
 	let pass = "PASS".green();
@@ -104,7 +177,7 @@ pub fn write_outcome(test_name: &str, errors: Vec<String>) -> String {
 	result
 }
 
-pub fn validate_vas(model: &AbstractVas, property: &VasProperty) -> Result<String, String> {
+pub fn validate_vas(model: &AbstractVas, target: &VasTarget) -> Result<String, String> {
 	let mut result = String::new();
 
 	result.push_str("===============================================\n");
@@ -116,20 +189,18 @@ pub fn validate_vas(model: &AbstractVas, property: &VasProperty) -> Result<Strin
 		"Check Variable Names",
 		check_variable_names(&model.variable_names),
 	));
-	let initial_state: Box<[VasValue]> = model.initial_states[0]
-		.vector
-		.iter()
-		.map(|&val| val)
-		.collect::<Vec<VasValue>>()
-		.into_boxed_slice();
 	result.push_str(&write_outcome(
-		"Check Initial State != Target",
-		initial_state_neq_target(initial_state, &property),
+		"Check Initial States != Target",
+		initial_state_neq_target(&model.initial_states, target, &model.variable_names),
 	));
 	result.push_str(&write_outcome(
 		"Check SCK Assumption (CRNs Only)",
 		check_sck_assumption(model.transitions.clone()),
 	));
+	result.push_str(&write_outcome(
+		"Check Mass-Action Rates",
+		check_custom_rates(model.transitions.clone()),
+	));
 	result.push_str(&write_outcome(
 		"Check Rate Constant",
 		check_rate_constant(model.transitions.clone()),